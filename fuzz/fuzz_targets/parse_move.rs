@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use chess::muve;
+use chess::piece::Player;
+use chess::square;
+
+// Parsing a move or a square must never panic, whatever the input.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+	let _ = square::from_str(s);
+	let _ = muve::from_str(s,Player::White);
+	let _ = muve::from_str(s,Player::Black);
+    }
+});
@@ -0,0 +1,258 @@
+//! Checks run by the fuzz targets.  These are kept separate from the
+//! targets themselves so the seed corpora can also be replayed by
+//! `cargo test` (which does not require libFuzzer).  To fuzz a target
+//! starting from its seeds, run (from this directory):
+//!
+//! ```text
+//! cargo fuzz run movegen_diff seeds/movegen_diff
+//! ```
+//!
+//! The seeds for games come from the tests in `game.rs` and from
+//! `game.txt`, whilst those for positions come from the FEN strings
+//! used in tests.
+use std::str::FromStr;
+use chess::board::*;
+use chess::fen;
+use chess::game::Game;
+use chess::movegen;
+use chess::muve::{Castle, Move, SimpleMove, SimpleTake};
+use chess::piece::*;
+use chess::report::Report;
+use chess::square::Square;
+
+/**
+ * Parse a game which, if successful, must be written back as text
+ * which parses into the same game.  Checking the game must never
+ * panic.
+ */
+pub fn parse_game(s: &str) {
+    if let Ok(g) = Game::from_str(s) {
+	let text = g.to_string();
+	let h = Game::from_str(&text).unwrap();
+	assert_eq!(text, h.to_string());
+	let _ = Report::new(&g,INITIAL).to_json(None);
+    }
+}
+
+/**
+ * Parse a board in FEN which, if successful, must be written back as
+ * FEN which parses into the same board.
+ */
+pub fn parse_fen(s: &str) {
+    if let Ok(b) = fen::from_str(s) {
+	assert_eq!(fen::from_str(&fen::to_string(&b)), Ok(b));
+    }
+}
+
+/**
+ * Apply each move of a game to the initial board (until one fails),
+ * checking the different ways of applying a move agree with each
+ * other and with the move generator.
+ */
+pub fn apply_moves(s: &str) {
+    let g = match Game::from_str(s) {
+	Ok(g) => g,
+	Err(_) => return
+    };
+    let mut board = INITIAL;
+    for i in 0..g.len() {
+	let m = g.get(i).unwrap();
+	let after = m.apply(board);
+	// Making and restoring the move agrees with applying it
+	let mut made = board;
+	let undo = m.make(&mut made);
+	assert_eq!(after.is_some(), undo.is_some());
+	// A reason is given exactly when the move fails
+	assert_eq!(after.is_none(), m.reason(&board).is_some());
+	let (after,undo) = match (after,undo) {
+	    (Some(a),Some(u)) => (a,u),
+	    _ => return
+	};
+	assert_eq!(made, after);
+	made.restore(&undo);
+	assert_eq!(made, board);
+	// The incrementally updated hash matches hashing from scratch
+	assert_eq!(fen::from_str(&fen::to_string(&after)), Ok(after));
+	// The move generator produces this move
+	assert!(keys(&movegen::legal_moves(&board)).contains(&key(m)));
+	board = after;
+    }
+}
+
+/**
+ * Set up a position from some bytes, then compare the move generator
+ * against the reference implementation on it and on each position of
+ * a random game played from it.
+ */
+pub fn movegen_diff(data: &[u8]) {
+    let (mut board, walk) = match position(data) {
+	Some(p) => p,
+	None => return
+    };
+    for &b in walk {
+	let moves = movegen::legal_moves(&board);
+	assert_eq!(keys(&moves), keys(&reference_moves(&board)), "{}", fen::to_string(&board));
+	if moves.is_empty() {
+	    return;
+	}
+	board = moves[b as usize % moves.len()].apply(board).unwrap();
+    }
+}
+
+/**
+ * Set up a position from some bytes, returning the bytes left over.
+ * The first byte gives the player to move (bit 0) and the castling
+ * rights (bits 1 to 4), the second gives the column which can be
+ * taken en passant (if less than 8), and the third gives the number
+ * of pieces.  Each piece is then given by a square and a piece.
+ * Nothing is returned for positions which cannot arise in a game,
+ * such as those without exactly one king each.
+ */
+pub fn position(data: &[u8]) -> Option<(Board,&[u8])> {
+    const PIECES: [Piece;13] = [BLANK,
+				WHITE_PAWN, WHITE_KNIGHT, WHITE_BISHOP, WHITE_ROOK, WHITE_QUEEN, WHITE_KING,
+				BLACK_PAWN, BLACK_KNIGHT, BLACK_BISHOP, BLACK_ROOK, BLACK_QUEEN, BLACK_KING];
+    let (&flags, &column, &n) = (data.first()?, data.get(1)?, data.get(2)?);
+    let n = n as usize % 33;
+    let pairs = data.get(3..3 + (2 * n))?;
+    let mut squares = [BLANK;64];
+    for p in pairs.chunks(2) {
+	squares[p[0] as usize % 64] = PIECES[p[1] as usize % 13];
+    }
+    let turn = if flags & 1 == 0 { Player::White } else { Player::Black };
+    // Sanity check pieces (as for FEN)
+    let count = |p: Piece| squares.iter().filter(|&&q| q == p).count();
+    if count(WHITE_KING) != 1 || count(BLACK_KING) != 1 {
+	return None;
+    }
+    if (0..8).any(|c| squares[c].kind == Kind::Pawn || squares[56 + c].kind == Kind::Pawn) {
+	return None;
+    }
+    // En passant only makes sense if a pawn just moved two squares
+    let en_passant = if column < 8 {
+	let (row,pawn,from) = match turn {
+	    Player::White => (5,BLACK_PAWN,6),
+	    Player::Black => (2,WHITE_PAWN,1)
+	};
+	let at = |r: u8| squares[Square::new(column,r).to_offset()];
+	let behind = if row == 5 { 4 } else { 3 };
+	if at(behind) != pawn || at(row) != BLANK || at(from) != BLANK {
+	    return None;
+	}
+	Some(Square::new(column,row))
+    } else {
+	None
+    };
+    let board = Board::new(squares,turn,(flags >> 1) & 0xF,en_passant,0,1);
+    // The player who just moved cannot be in check
+    if board.in_check(turn.flip()) {
+	return None;
+    }
+    Some((board,&data[3 + (2 * n)..]))
+}
+
+/**
+ * A simple (but slow) reference implementation of the move generator,
+ * which tries every possible move and keeps those which can be
+ * applied.  This relies on the rules used to check moves, rather than
+ * those used to generate them.
+ */
+pub fn reference_moves(board: &Board) -> Vec<Box<dyn Move>> {
+    let player = board.turn();
+    let last = match player { Player::White => 7, Player::Black => 0 };
+    let mut candidates : Vec<Box<dyn Move>> = vec![Box::new(Castle::new(player,true)),
+						     Box::new(Castle::new(player,false))];
+    for f in 0..64 {
+	let from = Square::new(f % 8, f / 8);
+	let piece = board.get(from);
+	if piece.kind == Kind::Blank || piece.player != player {
+	    continue;
+	}
+	for t in 0..64 {
+	    let to = Square::new(t % 8, t / 8);
+	    let target = board.get(to);
+	    let promotions = if piece.kind == Kind::Pawn && to.row() == last {
+		[Kind::Knight,Kind::Bishop,Kind::Rook,Kind::Queen].iter()
+		    .map(|&kind| Some(Piece{player,kind})).collect()
+	    } else {
+		vec![None]
+	    };
+	    for promote in promotions {
+		if target.kind != Kind::Blank {
+		    candidates.push(Box::new(SimpleTake::new(piece,from,to,target,promote)));
+		} else if piece.kind == Kind::Pawn && board.en_passant() == Some(to) {
+		    let taken = Piece{player: player.flip(), kind: Kind::Pawn};
+		    candidates.push(Box::new(SimpleTake::new(piece,from,to,taken,promote)));
+		} else {
+		    candidates.push(Box::new(SimpleMove::new(piece,from,to,promote)));
+		}
+	    }
+	}
+    }
+    candidates.into_iter().filter(|m| m.apply(*board).is_some()).collect()
+}
+
+/**
+ * Identify a move by its squares and promotion (if any), so that
+ * moves generated in different ways can be compared.
+ */
+fn key(m: &dyn Move) -> (usize,usize,Option<u8>) {
+    (m.from().to_offset(), m.to().to_offset(), m.promotion().map(|p| p.kind as u8))
+}
+
+/**
+ * Identify a set of moves (see `key()`), in sorted order.
+ */
+fn keys(moves: &[Box<dyn Move>]) -> Vec<(usize,usize,Option<u8>)> {
+    let mut ks : Vec<_> = moves.iter().map(|m| key(m.as_ref())).collect();
+    ks.sort();
+    ks
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+/**
+ * Replay every seed for a given target through a given check.
+ */
+#[cfg(test)]
+fn replay(target: &str, check: fn(&[u8])) {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("seeds").join(target);
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+	check(&std::fs::read(entry.unwrap().path()).unwrap());
+	count += 1;
+    }
+    assert!(count > 0);
+}
+
+/**
+ * Convert a check on text into one on bytes (as done by the targets).
+ */
+#[cfg(test)]
+fn text(data: &[u8], check: fn(&str)) {
+    if let Ok(s) = std::str::from_utf8(data) {
+	check(s);
+    }
+}
+
+#[test]
+fn test_01() {
+    replay("parse_game", |d| text(d,parse_game));
+}
+
+#[test]
+fn test_02() {
+    replay("parse_fen", |d| text(d,parse_fen));
+}
+
+#[test]
+fn test_03() {
+    replay("apply_moves", |d| text(d,apply_moves));
+}
+
+#[test]
+fn test_04() {
+    replay("movegen_diff", movegen_diff);
+}
@@ -0,0 +1,289 @@
+use super::annotation::Glyph;
+use super::board::Board;
+use super::game::{Game, Outcome};
+use super::piece::Player;
+use super::san;
+use super::search::{Analysis, Limits, Score, Search};
+
+/**
+ * Scores are capped at this (in centipawns) before being compared, so
+ * that a move which keeps a winning position winning (e.g. missing a
+ * faster mate) is not marked.
+ */
+const CAP: i32 = 1000;
+
+/**
+ * Maximum length of a line of movetext in PGN.
+ */
+const LINE: usize = 79;
+
+/**
+ * How much worse (in centipawns) a move must be than the best move
+ * to be marked.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Thresholds {
+    /**
+     * Loss at which a move is marked as an inaccuracy ("?!")
+     */
+    pub inaccuracy: i32,
+    /**
+     * Loss at which a move is marked as a mistake ("?")
+     */
+    pub mistake: i32,
+    /**
+     * Loss at which a move is marked as a blunder ("??")
+     */
+    pub blunder: i32
+}
+
+impl Default for Thresholds {
+    fn default() -> Thresholds {
+	Thresholds{inaccuracy: 50, mistake: 100, blunder: 300}
+    }
+}
+
+impl Thresholds {
+    /**
+     * Determine the glyph (if any) for a move which loses a given
+     * number of centipawns compared with the best move.
+     */
+    pub fn glyph(&self, loss: i32) -> Option<Glyph> {
+	if loss >= self.blunder {
+	    Some(Glyph::Blunder)
+	} else if loss >= self.mistake {
+	    Some(Glyph::Mistake)
+	} else if loss >= self.inaccuracy {
+	    Some(Glyph::Dubious)
+	} else {
+	    None
+	}
+    }
+}
+
+/**
+ * The assessment of a move played in a game.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    /**
+     * Ply of the move (starting from 1)
+     */
+    pub ply: usize,
+    /**
+     * Move played in SAN
+     */
+    pub san: String,
+    /**
+     * Score of the best move for the player who moved
+     */
+    pub best: Score,
+    /**
+     * Score of the move played for the player who moved
+     */
+    pub played: Score,
+    /**
+     * Glyph marking the move (if it was bad enough)
+     */
+    pub glyph: Option<Glyph>,
+    /**
+     * Line which would have been better in SAN, starting with the
+     * best move (or empty if the move is not marked)
+     */
+    pub variation: Vec<String>
+}
+
+impl Note {
+    /**
+     * Get how much worse (in centipawns) the move played was than the
+     * best move.
+     */
+    pub fn loss(&self) -> i32 {
+	(self.best.0.clamp(-CAP,CAP) - self.played.0.clamp(-CAP,CAP)).max(0)
+    }
+}
+
+/**
+ * Annotate each move of a game played from a given starting board, by
+ * searching every position within some limits and comparing the score
+ * of the move played with that of the best move.  The score of a move
+ * is taken from the search of the position it reaches.  Annotation
+ * stops at the first invalid move (if any).
+ */
+pub fn annotate(game: &Game, start: Board, search: &mut Search, limits: Limits,
+		thresholds: &Thresholds) -> Vec<Note> {
+    let mut analyses = vec![search.run(&start,limits,|_| {})];
+    for (_,_,_,after) in game.positions(start) {
+	analyses.push(search.run(&after,limits,|_| {}));
+    }
+    notes(game,start,&analyses,thresholds)
+}
+
+/**
+ * Annotate each move of a game played from a given starting board,
+ * given an analysis of every position it passes through (e.g. by an
+ * external engine).  The first analysis is of the starting board, and
+ * each other is of the board after the corresponding move.
+ * Annotation stops when either the moves or the analyses run out.
+ */
+pub fn notes(game: &Game, start: Board, analyses: &[Analysis], thresholds: &Thresholds) -> Vec<Note> {
+    let mut notes = Vec::new();
+    for ((ply,m,board,_),pair) in game.positions(start).zip(analyses.windows(2)) {
+	let (before,next) = (&pair[0],&pair[1]);
+	let best = before.score;
+	let played = parent(next.score);
+	let is_best = before.pv.first().is_some_and(|b| b.from() == m.from() && b.to() == m.to()
+						      && b.promotion() == m.promotion());
+	let mut note = Note{ply, san: san::to_string(&board,m), best, played, glyph: None, variation: Vec::new()};
+	if !is_best {
+	    note.glyph = thresholds.glyph(note.loss());
+	}
+	if note.glyph.is_some() {
+	    let mut b = board;
+	    for mv in &before.pv {
+		note.variation.push(san::to_string(&b,mv.as_ref()));
+		b = mv.apply(b).unwrap();
+	    }
+	}
+	notes.push(note);
+    }
+    notes
+}
+
+/**
+ * Write an annotated game in PGN, with given tags (which should
+ * include the seven tag roster) and result.  Marked moves are followed
+ * by their glyph and the better line as a variation.  The starting
+ * board determines how moves are numbered.
+ */
+pub fn to_pgn(start: &Board, notes: &[Note], tags: &[(&str,String)], result: Outcome) -> String {
+    let mut out = String::new();
+    for (name,value) in tags {
+	out.push_str(&format!("[{} \"{}\"]\n",name,value.replace('\\',"\\\\").replace('"',"\\\"")));
+    }
+    out.push('\n');
+    let first = start.turn();
+    let number = |ply: usize| {
+	// Number of the move at a given ply, and whether it is black's
+	let index = ply - 1 + if first == Player::Black { 1 } else { 0 };
+	(start.fullmove() as usize + index / 2, index % 2 == 1)
+    };
+    let mut tokens = Vec::new();
+    // Whether black's next move needs its number (e.g. "3...")
+    let mut resume = true;
+    for n in notes {
+	let (num,black) = number(n.ply);
+	if !black {
+	    tokens.push(format!("{}.",num));
+	} else if resume {
+	    tokens.push(format!("{}...",num));
+	}
+	tokens.push(format!("{}{}",n.san,n.glyph.map(|g| g.symbol()).unwrap_or("")));
+	resume = false;
+	if !n.variation.is_empty() {
+	    tokens.push(format!("({}{}",num,if black { "..." } else { "." }));
+	    for (i,s) in n.variation.iter().enumerate() {
+		let (num,black) = number(n.ply + i);
+		if i > 0 && !black {
+		    tokens.push(format!("{}.",num));
+		}
+		tokens.push(s.clone());
+	    }
+	    tokens.last_mut().unwrap().push(')');
+	    resume = true;
+	}
+    }
+    tokens.push(result.to_string());
+    // Wrap the movetext, keeping each variation's opening
+    // parenthesis with its move number
+    let mut line = String::new();
+    for t in tokens {
+	if !line.is_empty() && line.len() + 1 + t.len() > LINE {
+	    out.push_str(&line);
+	    out.push('\n');
+	    line.clear();
+	}
+	if !line.is_empty() && !line.ends_with('(') {
+	    line.push(' ');
+	}
+	line.push_str(&t);
+    }
+    out.push_str(&line);
+    out.push('\n');
+    out
+}
+
+/**
+ * Convert the score of the position reached by a move into the score
+ * of the move for the player who made it.  Mates are one ply further
+ * away from the position before the move.
+ */
+fn parent(score: Score) -> Score {
+    match score.mate() {
+	Some(n) if n <= 0 => Score(-score.0 - 1),
+	Some(_) => Score(-score.0 + 1),
+	None => Score(-score.0)
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use super::board::INITIAL;
+
+#[cfg(test)]
+fn check(game: &str) -> (Vec<Note>,String) {
+    let g = Game::from_str(game).unwrap();
+    let limits = Limits{depth: Some(2), ..Limits::default()};
+    let notes = annotate(&g,INITIAL,&mut Search::default(),limits,&Thresholds::default());
+    let end = g.apply(INITIAL).unwrap();
+    let pgn = to_pgn(&INITIAL,&notes,&[("Event","Test".to_string())],Outcome::of(&end));
+    (notes,pgn)
+}
+
+#[test]
+fn test_01() {
+    // Walking into a mate in one is a blunder
+    let (notes,pgn) = check("e2-e4 e7-e5\nQd1-h5 Ke8-e7\nQh5xe5");
+    assert_eq!(notes.len(), 5);
+    assert_eq!(notes[3].san, "Ke7");
+    assert_eq!(notes[3].glyph, Some(Glyph::Blunder));
+    assert!(!notes[3].variation.is_empty());
+    assert_eq!(notes[4].san, "Qxe5#");
+    assert_eq!(notes[4].glyph, None);
+    assert_eq!(notes[4].played.mate(), Some(1));
+    assert!(pgn.starts_with("[Event \"Test\"]\n\n1. e4 e5 2. Qh5"));
+    assert!(pgn.contains(" Ke7?? (2..."));
+    assert!(pgn.contains(") 3. Qxe5# 1-0\n"));
+}
+
+#[test]
+fn test_02() {
+    // A variation after white's move is followed by black's number
+    let (notes,pgn) = check("e2-e4 e7-e5\nQd1-h5 Nb8-c6\nQh5xf7 Ke8xQf7");
+    assert_eq!(notes[4].san, "Qxf7+");
+    assert_eq!(notes[4].glyph, Some(Glyph::Blunder));
+    assert_eq!(notes[5].glyph, None);
+    assert!(pgn.contains(" Qxf7+?? (3. "), "{}", pgn);
+    assert!(pgn.contains(") 3... Kxf7 *\n"), "{}", pgn);
+}
+
+#[test]
+fn test_03() {
+    let t = Thresholds::default();
+    assert_eq!(t.glyph(49), None);
+    assert_eq!(t.glyph(50), Some(Glyph::Dubious));
+    assert_eq!(t.glyph(150), Some(Glyph::Mistake));
+    assert_eq!(t.glyph(3000), Some(Glyph::Blunder));
+    // Long games are wrapped
+    let notes: Vec<Note> = (1..=60).map(|ply| {
+	Note{ply, san: "Nf3".to_string(), best: Score(0), played: Score(0), glyph: None, variation: Vec::new()}
+    }).collect();
+    let pgn = to_pgn(&INITIAL,&notes,&[],Outcome::Unfinished);
+    assert!(pgn.lines().all(|l| l.len() <= LINE));
+    assert!(pgn.lines().count() > 3);
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+
+/**
+ * Indicates a move was marked as giving check ("+") or checkmate
+ * ("#").
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    /** Marked with "+" */
+    Check,
+    /** Marked with "#" */
+    Mate
+}
+
+/**
+ * A glyph commenting on the quality of a move (e.g. "!" for a good
+ * move).
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Glyph {
+    /** A good move ("!") */
+    Good,
+    /** A mistake ("?") */
+    Mistake,
+    /** A brilliant move ("!!") */
+    Brilliant,
+    /** A blunder ("??") */
+    Blunder,
+    /** An interesting move ("!?") */
+    Interesting,
+    /** A dubious move ("?!") */
+    Dubious
+}
+
+impl Glyph {
+    /**
+     * Get the Numeric Annotation Glyph (NAG) used for this glyph in
+     * PGN (e.g. "$1" for "!").
+     */
+    pub fn nag(&self) -> u8 {
+	match self {
+	    Glyph::Good => 1,
+	    Glyph::Mistake => 2,
+	    Glyph::Brilliant => 3,
+	    Glyph::Blunder => 4,
+	    Glyph::Interesting => 5,
+	    Glyph::Dubious => 6
+	}
+    }
+
+    /**
+     * Get the symbol for this glyph (e.g. "!").
+     */
+    pub fn symbol(&self) -> &'static str {
+	match self {
+	    Glyph::Good => "!",
+	    Glyph::Mistake => "?",
+	    Glyph::Brilliant => "!!",
+	    Glyph::Blunder => "??",
+	    Glyph::Interesting => "!?",
+	    Glyph::Dubious => "?!"
+	}
+    }
+}
+
+/**
+ * The annotations written after a move in long algebraic notation,
+ * such as "e.p." for a pawn taken en passant, "+" for check and "!?"
+ * for an interesting move (as in "e5xd6e.p.+!?").
+ */
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotation {
+    /**
+     * Whether the move was marked as taking en passant
+     */
+    pub en_passant: bool,
+    /**
+     * Whether the move was marked as giving check or checkmate
+     */
+    pub check: Option<Check>,
+    /**
+     * Glyph commenting on the move (if any)
+     */
+    pub glyph: Option<Glyph>
+}
+
+impl Annotation {
+    /**
+     * Check whether this annotation is empty (i.e. nothing was
+     * written after the move).
+     */
+    pub fn is_empty(&self) -> bool {
+	*self == Annotation::default()
+    }
+}
+
+/**
+ * Identifies why the text following a move could not be parsed as
+ * annotations.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** "e.p." is given after a check or glyph (e.g. "+e.p.") */
+    EnPassant,
+    /** "+" or "#" is repeated or given after a glyph (e.g. "!+") */
+    Check,
+    /** A glyph is not recognised (e.g. "???") */
+    Glyph,
+    /** Something other than annotations is given (e.g. "x") */
+    Text
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let msg = match self {
+	    Error::EnPassant => "\"e.p.\" must come before check and glyph",
+	    Error::Check => "misplaced \"+\" or \"#\"",
+	    Error::Glyph => "unknown glyph",
+	    Error::Text => "unexpected text after move"
+	};
+	write!(f,"{}",msg)
+    }
+}
+
+/**
+ * Parse the annotations following a move, which must be written in
+ * order: "e.p." (if given), then "+" or "#" (if given), and then a
+ * glyph (if given).  An error is returned if anything else follows.
+ */
+pub fn from_str(s: &str) -> Result<Annotation,Error> {
+    let mut a = Annotation::default();
+    let mut s = s;
+    if let Some(r) = s.strip_prefix("e.p.") {
+	a.en_passant = true;
+	s = r;
+    }
+    if let Some(r) = s.strip_prefix('+') {
+	a.check = Some(Check::Check);
+	s = r;
+    } else if let Some(r) = s.strip_prefix('#') {
+	a.check = Some(Check::Mate);
+	s = r;
+    }
+    a.glyph = match s {
+	"" => None,
+	"!" => Some(Glyph::Good),
+	"?" => Some(Glyph::Mistake),
+	"!!" => Some(Glyph::Brilliant),
+	"??" => Some(Glyph::Blunder),
+	"!?" => Some(Glyph::Interesting),
+	"?!" => Some(Glyph::Dubious),
+	_ => return Err(error(s))
+    };
+    Ok(a)
+}
+
+/**
+ * Determine why the text left after any leading annotations is not a
+ * glyph.
+ */
+fn error(rest: &str) -> Error {
+    if rest.contains("e.p.") {
+	Error::EnPassant
+    } else if rest.contains(['+','#']) {
+	Error::Check
+    } else if rest.chars().all(|c| c == '!' || c == '?') {
+	Error::Glyph
+    } else {
+	Error::Text
+    }
+}
+
+/**
+ * Annotations are written in the same form they are parsed.
+ */
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	if self.en_passant {
+	    write!(f,"e.p.")?;
+	}
+	match self.check {
+	    Some(Check::Check) => write!(f,"+")?,
+	    Some(Check::Mate) => write!(f,"#")?,
+	    None => {}
+	}
+	if let Some(g) = self.glyph {
+	    write!(f,"{}",g.symbol())?;
+	}
+	Ok(())
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[test]
+fn test_01() {
+    assert_eq!(from_str(""), Ok(Annotation::default()));
+    assert!(from_str("").unwrap().is_empty());
+}
+
+#[test]
+fn test_02() {
+    let a = from_str("e.p.+!?").unwrap();
+    assert!(a.en_passant);
+    assert_eq!(a.check, Some(Check::Check));
+    assert_eq!(a.glyph, Some(Glyph::Interesting));
+    assert_eq!(a.to_string(), "e.p.+!?");
+}
+
+#[test]
+fn test_03() {
+    for s in ["+","#","!","?","!!","??","!?","?!","#!","e.p.","+?!"] {
+	assert_eq!(from_str(s).unwrap().to_string(), s);
+    }
+}
+
+#[test]
+fn test_04() {
+    for (s,e) in [("x",Error::Text),("e.p",Error::Text),("ep.",Error::Text),("+e.p.",Error::EnPassant),
+		  ("!e.p.",Error::EnPassant),("!+",Error::Check),("+#",Error::Check),("???",Error::Glyph),
+		  ("!?!",Error::Glyph)] {
+	assert_eq!(from_str(s), Err(e), "{}", s);
+    }
+}
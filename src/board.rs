@@ -1,13 +1,68 @@
 use std::fmt;
+use std::hash::{Hash,Hasher};
 use super::piece::*;
 use super::square::*;
+use super::zobrist;
+
+// ======================================================
+// Castling Rights
+// ======================================================
 
 /**
- * Define Chess board representation.
+ * Castling right indicating white can still castle on the king side.
  */
-#[derive(Copy, Clone, Debug)]
+pub const WHITE_KINGSIDE: u8 = 1;
+
+/**
+ * Castling right indicating white can still castle on the queen side.
+ */
+pub const WHITE_QUEENSIDE: u8 = 2;
+
+/**
+ * Castling right indicating black can still castle on the king side.
+ */
+pub const BLACK_KINGSIDE: u8 = 4;
+
+/**
+ * Castling right indicating black can still castle on the queen side.
+ */
+pub const BLACK_QUEENSIDE: u8 = 8;
+
+/**
+ * Define Chess board representation.  In addition to the pieces on
+ * each square, this includes the state needed to decide which moves
+ * are permitted (e.g. whose turn it is, whether castling is still
+ * allowed, etc).
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Board {
-    squares: [Piece;64]
+    squares: [Piece;64],
+    /**
+     * Player whose turn it is to move
+     */
+    turn: Player,
+    /**
+     * Castling rights still available (see WHITE_KINGSIDE, etc)
+     */
+    castling: u8,
+    /**
+     * Square which can be taken en passant (if any)
+     */
+    en_passant: Option<Square>,
+    /**
+     * Number of half moves since the last capture or pawn advance
+     */
+    halfmove: u16,
+    /**
+     * Number of the current full move, starting from one
+     */
+    fullmove: u16,
+    /**
+     * Zobrist hash of this board, maintained incrementally as the
+     * board is updated.  This excludes the en passant square, which
+     * is accounted for separately (see `hash()`).
+     */
+    key: u64
 }
 
 /**
@@ -23,13 +78,37 @@ pub const INITIAL: Board = Board {
         BLANK, BLANK, BLANK, BLANK, BLANK, BLANK, BLANK, BLANK,         
         BLACK_PAWN, BLACK_PAWN, BLACK_PAWN, BLACK_PAWN, BLACK_PAWN, BLACK_PAWN, BLACK_PAWN, BLACK_PAWN,
         BLACK_ROOK, BLACK_KNIGHT, BLACK_BISHOP, BLACK_QUEEN, BLACK_KING, BLACK_BISHOP, BLACK_KNIGHT, BLACK_ROOK
-    ]
+    ],
+    turn: Player::White,
+    castling: WHITE_KINGSIDE | WHITE_QUEENSIDE | BLACK_KINGSIDE | BLACK_QUEENSIDE,
+    en_passant: None,
+    halfmove: 0,
+    fullmove: 1,
+    key: 0x463B96181691FC9C
 };
 
 /**
  * Provide board manipulation methods
  */
 impl Board {    
+    /**
+     * Construct a board from the pieces on each square (indexed by
+     * `Square::to_offset()`), along with the remaining game state.
+     * This is useful for setting up positions other than the initial
+     * one (e.g. from FEN).
+     */
+    pub fn new(squares: [Piece;64], turn: Player, castling: u8, en_passant: Option<Square>,
+	       halfmove: u16, fullmove: u16) -> Board {
+	let mut board = Board{squares,turn,castling,en_passant,halfmove,fullmove,key:0};
+	// Hash from scratch, excluding en passant (see `key`)
+	board.key = zobrist::hash(&Board{en_passant: None, ..board});
+	board
+    }
+
+    /**
+     * Get the piece at a given square on the board (which may be
+     * blank).
+     */
     pub fn get(&self, s:Square) -> Piece {
 	// Convert square into linear offset
 	let offset = s.to_offset();
@@ -37,16 +116,263 @@ impl Board {
 	self.squares[offset]
     }
 
+    /**
+     * Produce a copy of this board where a given square holds a given
+     * piece.
+     */
     pub fn set(&self, s:Square, p:Piece) -> Self {
 	// Convert square into linear offset
 	let offset = s.to_offset();	
 	// Copy myself
 	let mut nbrd = *self;
 	// Update copy
+	nbrd.key ^= zobrist::piece_key(nbrd.squares[offset],s) ^ zobrist::piece_key(p,s);
 	nbrd.squares[offset] = p;
 	// Done
 	nbrd
     }
+
+    /**
+     * Get the pieces on the board (i.e. ignoring blank squares), along
+     * with the square each is on.  Pieces are given in order of their
+     * square (i.e. a1, b1, ..., h8).
+     */
+    pub fn pieces(&self) -> impl Iterator<Item=(Square,Piece)> + '_ {
+	self.squares.iter().enumerate()
+	    .filter(|(_,p)| p.kind != Kind::Blank)
+	    .map(|(i,p)| (Square::new((i % 8) as u8,(i / 8) as u8),*p))
+    }
+
+    /**
+     * Get the player whose turn it is to move.
+     */
+    pub fn turn(&self) -> Player {
+	self.turn
+    }
+
+    /**
+     * Get the castling rights which remain on this board.
+     */
+    pub fn castling(&self) -> u8 {
+	self.castling
+    }
+
+    /**
+     * Get the square (if any) which can be taken en passant.  This is
+     * the square a pawn skipped over on the previous move.
+     */
+    pub fn en_passant(&self) -> Option<Square> {
+	self.en_passant
+    }
+
+    /**
+     * Get the number of half moves since the last capture or pawn
+     * advance.
+     */
+    pub fn halfmove(&self) -> u16 {
+	self.halfmove
+    }
+
+    /**
+     * Get the number of the current full move.
+     */
+    pub fn fullmove(&self) -> u16 {
+	self.fullmove
+    }
+
+    /**
+     * Get the Zobrist hash of this board.  This identifies positions
+     * by the pieces on the board, the player to move, the castling
+     * rights and the en passant column (but not the clocks).  The
+     * hash is compatible with that used in Polyglot opening books.
+     */
+    pub fn hash(&self) -> u64 {
+	match self.en_passant {
+	    Some(s) if zobrist::can_take_en_passant(self,s) => {
+		self.key ^ zobrist::en_passant_key(s.column())
+	    }
+	    _ => self.key
+	}
+    }
+
+    /**
+     * Check whether a given square is attacked by any piece belonging
+     * to a given player.
+     */
+    pub fn is_attacked(&self, s: Square, by: Player) -> bool {
+	for offset in 0..64 {
+	    let p = self.squares[offset];
+	    //
+	    if p != BLANK && p.player == by {
+		let from = Square::new((offset % 8) as u8, (offset / 8) as u8);
+		if p.can_attack(*self,from,s) {
+		    return true;
+		}
+	    }
+	}
+	false
+    }
+
+    /**
+     * Find the square holding the king of a given player (if any).
+     */
+    pub fn king(&self, player: Player) -> Option<Square> {
+	let king = Piece{player, kind: Kind::King};
+	self.squares.iter()
+	    .position(|&p| p == king)
+	    .map(|offset| Square::new((offset % 8) as u8, (offset / 8) as u8))
+    }
+
+    /**
+     * Check whether the king of a given player is in check.  If the
+     * player has no king, then they cannot be in check.
+     */
+    pub fn in_check(&self, player: Player) -> bool {
+	match self.king(player) {
+	    Some(s) => self.is_attacked(s,player.flip()),
+	    None => false
+	}
+    }
+
+    /**
+     * Update the state of this board after a given piece has moved
+     * between two squares.  This hands the turn to the other player,
+     * removes any castling rights invalidated by the move, records
+     * the square (if any) which can now be taken en passant and
+     * updates the clocks.  Note, the pieces on the board are not
+     * changed here.
+     */
+    pub fn advance(&mut self, piece: Piece, from: Square, to: Square, capture: bool) {
+	// Moving to or from a king or rook's home square loses rights
+	let castling = self.castling & !(castling_mask(from) | castling_mask(to));
+	self.key ^= zobrist::castling_key(self.castling ^ castling);
+	self.castling = castling;
+	// Determine whether pawn skipped over a square
+	let pawn = piece.kind == Kind::Pawn;
+	let skip = (from.row() as i8 - to.row() as i8).abs() == 2;
+	self.en_passant = if pawn && skip {
+	    Some(Square::new(from.column(), (from.row() + to.row()) / 2))
+	} else {
+	    None
+	};
+	// Update clocks
+	if pawn || capture {
+	    self.halfmove = 0;
+	} else {
+	    self.halfmove = self.halfmove.saturating_add(1);
+	}
+	if self.turn == Player::Black {
+	    self.fullmove = self.fullmove.saturating_add(1);
+	}
+	// Hand over to other player
+	self.key ^= zobrist::turn_key(self.turn) ^ zobrist::turn_key(self.turn.flip());
+	self.turn = self.turn.flip();
+    }
+
+    /**
+     * Begin updating this board in place.  This returns a record
+     * which will allow any changes subsequently made (via `put()` or
+     * `advance()`) to be undone.
+     */
+    pub fn checkpoint(&self) -> Undo {
+	Undo {
+	    squares: [(Square::new(0,0),BLANK);4],
+	    count: 0,
+	    turn: self.turn,
+	    castling: self.castling,
+	    en_passant: self.en_passant,
+	    halfmove: self.halfmove,
+	    fullmove: self.fullmove,
+	    key: self.key
+	}
+    }
+
+    /**
+     * Place a given piece on a given square of this board in place,
+     * recording the previous contents of that square in the undo
+     * record.
+     */
+    pub fn put(&mut self, s:Square, p:Piece, undo: &mut Undo) {
+	let offset = s.to_offset();
+	// Record original contents
+	undo.squares[undo.count] = (s,self.squares[offset]);
+	undo.count += 1;
+	// Update square
+	self.key ^= zobrist::piece_key(self.squares[offset],s) ^ zobrist::piece_key(p,s);
+	self.squares[offset] = p;
+    }
+
+    /**
+     * Reverse all changes made to this board since a given undo
+     * record was created.
+     */
+    pub fn restore(&mut self, undo: &Undo) {
+	// Restore squares in reverse order
+	for i in (0..undo.count).rev() {
+	    let (s,p) = undo.squares[i];
+	    self.squares[s.to_offset()] = p;
+	}
+	// Restore state
+	self.turn = undo.turn;
+	self.castling = undo.castling;
+	self.en_passant = undo.en_passant;
+	self.halfmove = undo.halfmove;
+	self.fullmove = undo.fullmove;
+	self.key = undo.key;
+    }
+}
+
+/**
+ * Determine the castling rights which are lost when a piece moves to
+ * or from a given square.  For example, moving anything to or from
+ * "h1" means white can no longer castle king side.
+ */
+fn castling_mask(s: Square) -> u8 {
+    match (s.column(),s.row()) {
+	(0,0) => WHITE_QUEENSIDE,
+	(4,0) => WHITE_KINGSIDE | WHITE_QUEENSIDE,
+	(7,0) => WHITE_KINGSIDE,
+	(0,7) => BLACK_QUEENSIDE,
+	(4,7) => BLACK_KINGSIDE | BLACK_QUEENSIDE,
+	(7,7) => BLACK_KINGSIDE,
+	_ => 0
+    }
+}
+
+// ======================================================
+// Undo
+// ======================================================
+
+/**
+ * Records the information needed to reverse a move which was made in
+ * place on a board.  At most four squares are changed by any move
+ * (e.g. castling moves both king and rook).
+ */
+#[derive(Copy, Clone, Debug)]
+pub struct Undo {
+    /**
+     * Squares changed along with their original contents
+     */
+    squares: [(Square,Piece);4],
+    /**
+     * Number of squares changed
+     */
+    count: usize,
+    turn: Player,
+    castling: u8,
+    en_passant: Option<Square>,
+    halfmove: u16,
+    fullmove: u16,
+    key: u64
+}
+
+/**
+ * Boards are hashed using their Zobrist hash.
+ */
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+	state.write_u64(Board::hash(self));
+    }
 }
 
 /** 
@@ -68,3 +394,15 @@ impl fmt::Display for Board {
         Ok(())
     }
 }
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[test]
+fn test_01() {
+    // Clocks which cannot be advanced further stay where they are
+    let mut board = Board{turn: Player::Black, halfmove: u16::MAX, fullmove: u16::MAX, ..INITIAL};
+    board.advance(BLACK_KNIGHT,Square::new(1,7),Square::new(2,5),false);
+    assert_eq!((board.halfmove(),board.fullmove()), (u16::MAX,u16::MAX));
+}
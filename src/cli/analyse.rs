@@ -0,0 +1,39 @@
+use chess::san;
+use chess::search::Search;
+use super::args::{Command, Matches, Opt};
+use super::{limits, position, weights, Outcome, PLY, POSITION, WEIGHTS};
+
+pub const ANALYSE: Command = Command {
+    name: "analyse",
+    usage: "[FILE]",
+    summary: "Search for the best move in a position",
+    options: &[Opt{name: "depth", value: Some("N"), help: "Number of plies to search (default: 4, unless --time is given)"},
+	       Opt{name: "time", value: Some("MS"), help: "Stop searching after MS milliseconds"},
+	       POSITION, PLY, WEIGHTS]
+};
+
+pub fn analyse(m: &Matches) -> Outcome {
+    let brd = match position(m)? {
+	Some(brd) => brd,
+	None => return Ok(false)
+    };
+    let limits = limits(m)?;
+    let mut search = Search::default();
+    search.weights = weights(m)?;
+    let result = search.run(&brd,limits,|a| {
+	let mut b = brd;
+	let mut pv = Vec::new();
+	for mv in &a.pv {
+	    pv.push(san::to_string(&b,mv.as_ref()));
+	    b = mv.apply(b).unwrap();
+	}
+	println!("depth {:<3} score {:<8} nodes {:<10} time {:.3}s  pv {}",
+		 a.depth,a.score.to_string(),a.nodes,a.time.as_secs_f64(),pv.join(" "));
+    });
+    match result.pv.first() {
+	Some(mv) => println!("best {}",san::to_string(&brd,mv.as_ref())),
+	None if brd.in_check(brd.turn()) => println!("checkmate"),
+	None => println!("stalemate")
+    }
+    Ok(true)
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+use chess::annotate;
+use chess::board;
+use chess::eco;
+use chess::game;
+use chess::uci;
+use chess::annotate::Thresholds;
+use chess::search::Search;
+use super::args::{Command, Matches, Opt};
+use super::{inputs, limits, load, replay, weights, Outcome, WEIGHTS};
+
+pub const ANNOTATE: Command = Command {
+    name: "annotate",
+    usage: "FILE...",
+    summary: "Mark inaccuracies, mistakes and blunders in games, writing PGN",
+    options: &[Opt{name: "depth", value: Some("N"), help: "Number of plies to search each position (default: 4, unless --time is given)"},
+	       Opt{name: "time", value: Some("MS"), help: "Stop searching each position after MS milliseconds"},
+	       Opt{name: "engine", value: Some("PROGRAM"), help: "Search with an external UCI engine instead (e.g. \"stockfish\")"},
+	       Opt{name: "timeout", value: Some("MS"), help: "Give up on an engine which takes MS milliseconds longer than expected to reply (default: 10000)"},
+	       WEIGHTS]
+};
+
+pub fn annotate(m: &Matches) -> Outcome {
+    let limits = limits(m)?;
+    let mut search = Search::default();
+    search.weights = weights(m)?;
+    let timeout = Duration::from_millis(m.parse_value::<u64>("timeout")?.unwrap_or(10000));
+    let mut engine = match m.value("engine") {
+	Some(program) => Some(uci::Engine::start(program,&[],timeout).map_err(|e| format!("{}: {}",program,e))?),
+	None => None
+    };
+    let mut valid = true;
+    for (i,name) in inputs(m)?.iter().enumerate() {
+	let g = match load(name)? {
+	    Some(g) => g,
+	    None => { valid = false; continue; }
+	};
+	let end = match g.apply(board::INITIAL) {
+	    Ok(end) => end,
+	    Err(_) => {
+		let (_,plies,invalid) = replay(&g);
+		eprintln!("{}: invalid move {} at ply {}",name,invalid.unwrap_or_default(),plies + 1);
+		valid = false;
+		continue;
+	    }
+	};
+	let notes = match engine.as_mut() {
+	    Some(e) => {
+		let analyses = e.evaluate(board::INITIAL,&g,limits).map_err(|e| format!("{}: {}",name,e))?;
+		annotate::notes(&g,board::INITIAL,&analyses,&Thresholds::default())
+	    }
+	    None => {
+		search.clear();
+		annotate::annotate(&g,board::INITIAL,&mut search,limits,&Thresholds::default())
+	    }
+	};
+	let result = g.result().unwrap_or(game::Outcome::of(&end));
+	let mut tags = vec![("Event","?".to_string()), ("Site","?".to_string()), ("Date","????.??.??".to_string()),
+			    ("Round","?".to_string()), ("White","?".to_string()), ("Black","?".to_string()),
+			    ("Result",result.to_string())];
+	if let Some(o) = eco::classify(&g,board::INITIAL) {
+	    tags.push(("ECO",o.code.to_string()));
+	    tags.push(("Opening",o.name.to_string()));
+	}
+	let depth = limits.depth.map(|d| format!("depth {}",d)).unwrap_or_else(|| "timed".to_string());
+	let annotator = match &engine {
+	    Some(e) => e.name().unwrap_or("unknown engine").to_string(),
+	    None => format!("chess {}",env!("CARGO_PKG_VERSION"))
+	};
+	tags.push(("Annotator",format!("{} ({})",annotator,depth)));
+	if i > 0 { println!(); }
+	print!("{}",annotate::to_pgn(&board::INITIAL,&notes,&tags,result));
+    }
+    Ok(valid)
+}
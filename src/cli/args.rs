@@ -0,0 +1,206 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+/**
+ * Describes an option accepted by a command, such as "--flip" or
+ * "--depth N".
+ */
+pub struct Opt {
+    /**
+     * Name of the option (without the leading dashes)
+     */
+    pub name: &'static str,
+    /**
+     * Name of the value taken by the option (if any), as shown in the
+     * help text
+     */
+    pub value: Option<&'static str>,
+    /**
+     * Description of the option, as shown in the help text
+     */
+    pub help: &'static str
+}
+
+/**
+ * Describes a subcommand (e.g. "check") along with the options and
+ * arguments it accepts.
+ */
+pub struct Command {
+    /**
+     * Name of the command, as given on the command line
+     */
+    pub name: &'static str,
+    /**
+     * Arguments accepted after the options (e.g. "FILE...")
+     */
+    pub usage: &'static str,
+    /**
+     * One line description of the command
+     */
+    pub summary: &'static str,
+    /**
+     * Options accepted by the command
+     */
+    pub options: &'static [Opt]
+}
+
+/**
+ * The options and arguments given for a command.
+ */
+pub struct Matches {
+    /**
+     * Options given, along with their values (if applicable)
+     */
+    options: Vec<(&'static str,Option<String>)>,
+    /**
+     * Remaining arguments (e.g. filenames), where "-" is kept as is
+     */
+    pub args: Vec<String>
+}
+
+impl Command {
+    /**
+     * Parse the arguments given for this command.  Options may be
+     * written as "--name value" or "--name=value", and an argument of
+     * "--" marks the end of options.  An error message is returned
+     * for unknown options or missing values.
+     */
+    pub fn parse(&self, args: &[String]) -> Result<Matches,String> {
+	let mut matches = Matches{options: Vec::new(), args: Vec::new()};
+	let mut iter = args.iter();
+	while let Some(arg) = iter.next() {
+	    if arg == "--" {
+		matches.args.extend(iter.by_ref().cloned());
+	    } else if let Some(body) = arg.strip_prefix("--") {
+		let (name,inline) = match body.split_once('=') {
+		    Some((n,v)) => (n,Some(v.to_string())),
+		    None => (body,None)
+		};
+		let opt = self.options.iter().chain(std::iter::once(&HELP)).find(|o| o.name == name)
+		    .ok_or_else(|| format!("unknown option --{} for {}",name,self.name))?;
+		let value = match (opt.value,inline) {
+		    (None,None) => None,
+		    (None,Some(_)) => return Err(format!("option --{} does not take a value",name)),
+		    (Some(_),Some(v)) => Some(v),
+		    (Some(v),None) => {
+			let next = iter.next().ok_or_else(|| format!("option --{} requires {}",name,v))?;
+			Some(next.clone())
+		    }
+		};
+		matches.options.push((opt.name,value));
+	    } else if arg == "-h" {
+		matches.options.push((HELP.name,None));
+	    } else if arg.len() > 1 && arg.starts_with('-') {
+		return Err(format!("unknown option {} for {}",arg,self.name));
+	    } else {
+		matches.args.push(arg.clone());
+	    }
+	}
+	Ok(matches)
+    }
+
+    /**
+     * Produce the help text for this command.
+     */
+    pub fn help(&self) -> String {
+	let mut out = String::new();
+	let usage = format!("chess {} [OPTIONS] {}",self.name,self.usage);
+	let _ = writeln!(out,"{}\n\nUsage: {}\n\nOptions:",self.summary,usage.trim_end());
+	for opt in self.options.iter().chain(std::iter::once(&HELP)) {
+	    let name = match opt.value {
+		Some(v) => format!("--{} {}",opt.name,v),
+		None => format!("--{}",opt.name)
+	    };
+	    let _ = writeln!(out,"  {:<20} {}",name,opt.help);
+	}
+	out
+    }
+}
+
+/**
+ * Option for requesting help, which every command accepts.
+ */
+pub const HELP: Opt = Opt{name: "help", value: None, help: "Print help"};
+
+impl Matches {
+    /**
+     * Check whether a given option (which takes no value) was given.
+     */
+    pub fn flag(&self, name: &str) -> bool {
+	self.options.iter().any(|(n,_)| *n == name)
+    }
+
+    /**
+     * Get the value of a given option, if it was given.  When given
+     * more than once, the last value is used.
+     */
+    pub fn value(&self, name: &str) -> Option<&str> {
+	self.options.iter().rev()
+	    .find(|(n,_)| *n == name)
+	    .and_then(|(_,v)| v.as_deref())
+    }
+
+    /**
+     * Get the value of a given option converted to some type (e.g. a
+     * number), producing an error message if it cannot be converted.
+     */
+    pub fn parse_value<T: FromStr>(&self, name: &str) -> Result<Option<T>,String> {
+	match self.value(name) {
+	    Some(v) => v.parse::<T>().map(Some).map_err(|_| format!("invalid value \"{}\" for --{}",v,name)),
+	    None => Ok(None)
+	}
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+const TEST: Command = Command {
+    name: "test",
+    usage: "FILE...",
+    summary: "Test command",
+    options: &[Opt{name: "flip", value: None, help: "Flip"},
+	       Opt{name: "depth", value: Some("N"), help: "Depth"}]
+};
+
+#[cfg(test)]
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_01() {
+    let m = TEST.parse(&strings(&["--flip","a.txt","--depth","3","-"])).unwrap();
+    assert!(m.flag("flip"));
+    assert_eq!(m.value("depth"),Some("3"));
+    assert_eq!(m.parse_value::<usize>("depth"),Ok(Some(3)));
+    assert_eq!(m.args,strings(&["a.txt","-"]));
+}
+
+#[test]
+fn test_02() {
+    let m = TEST.parse(&strings(&["--depth=5","--","--flip"])).unwrap();
+    assert!(!m.flag("flip"));
+    assert_eq!(m.value("depth"),Some("5"));
+    assert_eq!(m.args,strings(&["--flip"]));
+}
+
+#[test]
+fn test_03() {
+    assert!(TEST.parse(&strings(&["--depth"])).is_err());
+    assert!(TEST.parse(&strings(&["--colour"])).is_err());
+    assert!(TEST.parse(&strings(&["-x"])).is_err());
+    assert!(TEST.parse(&strings(&["--flip=yes"])).is_err());
+    let m = TEST.parse(&strings(&["--depth","x"])).unwrap();
+    assert!(m.parse_value::<usize>("depth").is_err());
+}
+
+#[test]
+fn test_04() {
+    let help = TEST.help();
+    assert!(help.contains("Usage: chess test [OPTIONS] FILE..."));
+    assert!(help.contains("--depth N"));
+    assert!(help.contains("--help"));
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use chess::board;
+use chess::pgn;
+use chess::polyglot;
+use chess::san;
+use super::args::{Command, Matches, Opt};
+use super::{inputs, load, read_input, replay, Outcome};
+
+pub const BOOK: Command = Command {
+    name: "book",
+    usage: "FILE...",
+    summary: "Check games against a Polyglot opening book, or build one from PGN databases",
+    options: &[Opt{name: "book", value: Some("BIN"), help: "Report which moves of the games are in book BIN"},
+	       Opt{name: "build", value: Some("BIN"), help: "Build book BIN from the games in the PGN files given"},
+	       Opt{name: "plies", value: Some("N"), help: "Number of plies of each game to add when building (default: 20)"}]
+};
+
+pub fn book(m: &Matches) -> Outcome {
+    match (m.value("book"),m.value("build")) {
+	(Some(bin),None) => probe(m,bin),
+	(None,Some(bin)) => build(m,bin),
+	(Some(_),Some(_)) => Err("cannot give both --book and --build".to_string()),
+	(None,None) => Err("a book must be given (--book BIN or --build BIN)".to_string())
+    }
+}
+
+/**
+ * Report, for each game given, which of its moves are in a given book
+ * and where the game left it.
+ */
+fn probe(m: &Matches, bin: &str) -> Outcome {
+    let bytes = fs::read(bin).map_err(|e| format!("error reading {}: {}",bin,e))?;
+    let book = polyglot::Book::from_bytes(&bytes).map_err(|e| format!("{}: {}",bin,e))?;
+    let mut valid = true;
+    for name in inputs(m)? {
+	let game = match load(name)? {
+	    Some(g) => g,
+	    None => {
+		valid = false;
+		continue;
+	    }
+	};
+	if game.apply(board::INITIAL).is_err() {
+	    let (_,plies,invalid) = replay(&game);
+	    eprintln!("{}: invalid move {} at ply {}",name,invalid.unwrap_or_default(),plies + 1);
+	    valid = false;
+	}
+	let (mut left, mut visited) = (None,0);
+	for (ply,mv,brd,_) in game.positions(board::INITIAL) {
+	    visited = ply;
+	    let moves = book.lookup(&brd);
+	    let total: u32 = moves.iter().map(|(_,w)| *w as u32).sum();
+	    let played = san::to_string(&brd,mv);
+	    match book.weight(&brd,mv) {
+		Some(w) => println!("{}: ply {} {} in book (weight {} of {})",name,ply,played,w,total),
+		None => {
+		    let known: Vec<String> = moves.iter().map(|(k,_)| san::to_string(&brd,k.as_ref())).collect();
+		    if known.is_empty() {
+			println!("{}: ply {} {} left book (no book moves)",name,ply,played);
+		    } else {
+			println!("{}: ply {} {} left book (book has {})",name,ply,played,known.join(", "));
+		    }
+		    left = Some(ply);
+		    break;
+		}
+	    }
+	}
+	if left.is_none() {
+	    println!("{}: all {} plies in book",name,visited);
+	}
+    }
+    Ok(valid)
+}
+
+/**
+ * Build a book from the games in the PGN databases given, skipping
+ * (and reporting) any which are invalid.
+ */
+fn build(m: &Matches, bin: &str) -> Outcome {
+    let plies = m.parse_value::<usize>("plies")?.unwrap_or(20);
+    let mut builder = polyglot::Builder::new();
+    let (mut games, mut skipped) = (0,0);
+    for name in inputs(m)? {
+	for record in pgn::read(&read_input(name)?) {
+	    match record {
+		Ok(r) => {
+		    builder.add(r.start,&r.game,plies);
+		    games += 1;
+		}
+		Err(e) => {
+		    println!("{}: {}",name,e);
+		    skipped += 1;
+		}
+	    }
+	}
+    }
+    let book = builder.finish();
+    fs::write(bin,book.to_bytes()).map_err(|e| format!("error writing {}: {}",bin,e))?;
+    println!("{}: {} entries from {} games ({} skipped)",bin,book.len(),games,skipped);
+    Ok(skipped == 0)
+}
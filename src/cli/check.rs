@@ -0,0 +1,51 @@
+use std::str::FromStr;
+use chess::board;
+use chess::game::Game;
+use chess::report::Report;
+use super::args::{Command, Matches, Opt};
+use super::{inputs, read_input, Outcome};
+
+pub const CHECK: Command = Command {
+    name: "check",
+    usage: "FILE...",
+    summary: "Check that games contain only valid moves",
+    options: &[Opt{name: "quiet", value: None, help: "Print nothing, only set the exit status"},
+	       Opt{name: "format", value: Some("FORMAT"), help: "Output format: text (default), json or jsonl"}]
+};
+
+pub fn check(m: &Matches) -> Outcome {
+    let quiet = m.flag("quiet");
+    let format = m.value("format").unwrap_or("text");
+    if !matches!(format,"text" | "json" | "jsonl") {
+	return Err(format!("unknown format \"{}\" (expected text, json or jsonl)",format));
+    }
+    let mut valid = true;
+    let mut objects = Vec::new();
+    for name in inputs(m)? {
+	let contents = read_input(name)?;
+	let r = match Game::from_str(&contents) {
+	    Ok(g) => Report::new(&g,board::INITIAL),
+	    Err(e) => Report::unparsed(&e)
+	};
+	valid &= r.is_valid();
+	if quiet { continue; }
+	match format {
+	    "json" => objects.push(r.to_json(Some(name))),
+	    "jsonl" => println!("{}",r.to_json(Some(name))),
+	    _ => match &r.failure {
+		None => match r.opening {
+		    Some(o) => println!("{}: valid ({} plies, {})",name,r.plies,o),
+		    None => println!("{}: valid ({} plies)",name,r.plies)
+		},
+		Some(f) => match (&f.text,f.ply) {
+		    (Some(mv),Some(ply)) => println!("{}: invalid move {} at ply {} ({})",name,mv,ply,f.reason),
+		    _ => println!("{}: invalid, {}",name,f.reason)
+		}
+	    }
+	}
+    }
+    if format == "json" && !quiet {
+	println!("[\n  {}\n]",objects.join(",\n  "));
+    }
+    Ok(valid)
+}
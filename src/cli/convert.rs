@@ -0,0 +1,46 @@
+use chess::board;
+use chess::san;
+use super::args::{Command, Matches, Opt};
+use super::{inputs, load, replay, Outcome};
+
+pub const CONVERT: Command = Command {
+    name: "convert",
+    usage: "FILE...",
+    summary: "Convert games into another notation",
+    options: &[Opt{name: "to", value: Some("NOTATION"), help: "Notation to write: san (default) or long"}]
+};
+
+pub fn convert(m: &Matches) -> Outcome {
+    let long = match m.value("to").unwrap_or("san") {
+	"san" => false,
+	"long" => true,
+	n => return Err(format!("unknown notation \"{}\" (expected san or long)",n))
+    };
+    let files = inputs(m)?;
+    let mut valid = true;
+    for (i,name) in files.iter().enumerate() {
+	if i > 0 { println!(); }
+	let g = match load(name)? {
+	    Some(g) => g,
+	    None => { valid = false; continue; }
+	};
+	let mut line = String::new();
+	for (ply,mv,before,_) in g.positions(board::INITIAL) {
+	    let text = if long { mv.to_string() } else { san::to_string(&before,mv) };
+	    if ply % 2 == 1 {
+		if !long { line.push_str(&format!("{}. ",ply.div_ceil(2))); }
+		line.push_str(&text);
+	    } else {
+		println!("{} {}",line,text);
+		line.clear();
+	    }
+	}
+	if !line.is_empty() { println!("{}",line); }
+	let (_,plies,invalid) = replay(&g);
+	if let Some(mv) = invalid {
+	    eprintln!("{}: invalid move {} at ply {}",name,mv,plies + 1);
+	    valid = false;
+	}
+    }
+    Ok(valid)
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use chess::epd;
+use chess::san;
+use chess::search::Search;
+use super::args::{Command, Matches, Opt};
+use super::{inputs, limits, read_input, weights, Outcome, WEIGHTS};
+
+pub const EPD: Command = Command {
+    name: "epd",
+    usage: "FILE...",
+    summary: "Run the search against a test suite in EPD, scoring it by the best moves given",
+    options: &[Opt{name: "depth", value: Some("N"), help: "Number of plies to search each position (default: 4, unless --time is given)"},
+	       Opt{name: "time", value: Some("MS"), help: "Stop searching each position after MS milliseconds"},
+	       WEIGHTS,
+	       Opt{name: "output", value: Some("OUT"), help: "Write the records to OUT with the results added (acd, ce, pm and pv)"}]
+};
+
+pub fn epd(m: &Matches) -> Outcome {
+    let limits = limits(m)?;
+    let mut search = Search::default();
+    search.weights = weights(m)?;
+    let mut valid = true;
+    let (mut solved, mut total) = (0,0);
+    let mut output = String::new();
+    for name in inputs(m)? {
+	for (i,record) in epd::read(&read_input(name)?).into_iter().enumerate() {
+	    let mut e = match record {
+		Ok(e) => e,
+		Err(err) => {
+		    println!("{}: {}",name,err);
+		    valid = false;
+		    continue;
+		}
+	    };
+	    search.clear();
+	    let (a,ok) = e.attempt(&mut search,limits);
+	    let line: Vec<String> = {
+		let mut b = e.board;
+		a.pv.iter().map(|mv| {
+		    let s = san::to_string(&b,mv.as_ref());
+		    b = mv.apply(b).unwrap();
+		    s
+		}).collect()
+	    };
+	    let label = e.id().map(|s| s.to_string()).unwrap_or_else(|| format!("{} record {}",name,i + 1));
+	    let played = line.first().cloned().unwrap_or_else(|| "none".to_string());
+	    if e.get("bm").is_some() || e.get("am").is_some() {
+		total += 1;
+		if ok { solved += 1; }
+		let expected = match (e.get("bm"),e.get("am")) {
+		    (Some(bm),_) => format!("bm {}",bm.join(" ")),
+		    (None,Some(am)) => format!("am {}",am.join(" ")),
+		    _ => unreachable!()
+		};
+		println!("{}: {} ({}, {})",label,if ok { "solved" } else { "failed" },played,expected);
+	    }
+	    e.set("acd",vec![a.depth.to_string()]);
+	    e.set("ce",vec![a.score.0.to_string()]);
+	    if let Some(mv) = line.first() {
+		e.set("pm",vec![mv.clone()]);
+		e.set("pv",line);
+	    }
+	    output.push_str(&format!("{}\n",e));
+	}
+    }
+    let limit = match limits.depth {
+	Some(d) => format!("depth {}",d),
+	None => format!("{}ms",limits.time.map(|t| t.as_millis()).unwrap_or(0))
+    };
+    println!("solved {}/{} at {}",solved,total,limit);
+    if let Some(out) = m.value("output") {
+	fs::write(out,output).map_err(|e| format!("error writing {}: {}",out,e))?;
+    }
+    Ok(valid)
+}
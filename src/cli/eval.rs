@@ -0,0 +1,32 @@
+use chess::piece::Player;
+use chess::eval;
+use chess::eval::TERMS;
+use super::args::{Command, Matches, Opt};
+use super::{position, weights, Outcome, PLY, POSITION, WEIGHTS};
+
+pub const EVAL: Command = Command {
+    name: "eval",
+    usage: "[FILE]",
+    summary: "Print the evaluation of a position term by term",
+    options: &[POSITION, PLY, WEIGHTS,
+	       Opt{name: "print-weights", value: None, help: "Print the weights used (e.g. to start tuning them)"}]
+};
+
+pub fn eval(m: &Matches) -> Outcome {
+    let weights = weights(m)?;
+    if m.flag("print-weights") {
+	print!("{}",weights);
+	return Ok(true);
+    }
+    let brd = match position(m)? {
+	Some(brd) => brd,
+	None => return Ok(false)
+    };
+    let b = eval::breakdown(&brd,&weights);
+    println!("{:<10} {:>7} {:>7} {:>7}","term","white","black","total");
+    for t in TERMS {
+	println!("{:<10} {:>7} {:>7} {:>+7}",t.to_string(),b.get(t,Player::White),b.get(t,Player::Black),b.term(t));
+    }
+    println!("{:<10} {:>7} {:>7} {:>+7}","total","","",b.total());
+    Ok(true)
+}
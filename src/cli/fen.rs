@@ -0,0 +1,28 @@
+use chess::board;
+use chess::fen;
+use super::args::{Command, Matches};
+use super::{board_at, inputs, load, Outcome, PLY};
+
+pub const FEN: Command = Command {
+    name: "fen",
+    usage: "FILE...",
+    summary: "Print the position reached in games as FEN",
+    options: &[PLY]
+};
+
+pub fn fen(m: &Matches) -> Outcome {
+    let ply = m.parse_value::<usize>("ply")?;
+    let mut valid = true;
+    for name in inputs(m)? {
+	let g = match load(name)? {
+	    Some(g) => g,
+	    None => { valid = false; continue; }
+	};
+	valid &= g.apply(board::INITIAL).is_ok();
+	match board_at(name,&g,ply) {
+	    Some((brd,_)) => println!("{}",fen::to_string(&brd)),
+	    None => valid = false
+	}
+    }
+    Ok(valid)
+}
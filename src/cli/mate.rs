@@ -0,0 +1,48 @@
+use chess::mate;
+use chess::san;
+use super::args::{Command, Matches, Opt};
+use super::{position, Outcome, PLY, POSITION};
+
+pub const MATE: Command = Command {
+    name: "mate",
+    usage: "[FILE]",
+    summary: "Check a position is a sound mate-in-N problem (i.e. has exactly one key)",
+    options: &[Opt{name: "moves", value: Some("N"), help: "Number of moves in which to mate (required)"},
+	       Opt{name: "verify", value: Some("LINE"), help: "Also verify a solution given in SAN (e.g. \"Ra7 Kg8 Rb8#\")"},
+	       POSITION, PLY]
+};
+
+pub fn mate(m: &Matches) -> Outcome {
+    let n = m.parse_value::<usize>("moves")?.ok_or("the number of moves must be given (--moves N)")?;
+    if n == 0 {
+	return Err("the number of moves must be at least one".to_string());
+    }
+    let brd = match position(m)? {
+	Some(brd) => brd,
+	None => return Ok(false)
+    };
+    let keys: Vec<String> = mate::solve(&brd,n).iter().map(|k| san::to_string(&brd,k.as_ref())).collect();
+    let mut valid = keys.len() == 1;
+    match keys.len() {
+	0 => println!("no mate in {}",n),
+	1 => println!("sound: key {}",keys[0]),
+	_ => println!("cooked: keys {}",keys.join(", "))
+    }
+    if let Some(line) = m.value("verify") {
+	let mut b = brd;
+	let mut moves = Vec::new();
+	for (i,s) in line.split_ascii_whitespace().enumerate() {
+	    let mv = san::from_str(&b,s).map_err(|e| format!("invalid move \"{}\" at ply {} ({})",s,i + 1,e))?;
+	    b = mv.apply(b).unwrap();
+	    moves.push(mv);
+	}
+	match mate::verify(&brd,n,&moves) {
+	    Ok(_) => println!("solution verified"),
+	    Err(e) => {
+		println!("solution rejected: {}",e);
+		valid = false;
+	    }
+	}
+    }
+    Ok(valid)
+}
@@ -0,0 +1,98 @@
+use chess::board;
+use chess::material;
+use chess::material::KINDS;
+use chess::piece::{Kind, Piece, Player};
+use super::args::{Command, Matches, Opt};
+use super::{inputs, load, Outcome};
+
+pub const MATERIAL: Command = Command {
+    name: "material",
+    usage: "FILE...",
+    summary: "Print the material on the board after each ply of games",
+    options: &[Opt{name: "format", value: Some("FORMAT"), help: "Output format: table (default) or csv"}]
+};
+
+pub fn material(m: &Matches) -> Outcome {
+    let csv = match m.value("format").unwrap_or("table") {
+	"table" => false,
+	"csv" => true,
+	f => return Err(format!("unknown format \"{}\" (expected table or csv)",f))
+    };
+    let mut valid = true;
+    if csv {
+	let mut header = vec!["file".to_string(),"ply".to_string(),"move".to_string()];
+	for player in ["white","black"] {
+	    for k in &KINDS[..5] {
+		header.push(format!("{}_{}",player,kind_name(*k)));
+	    }
+	}
+	for h in ["white_points","black_points","balance","captured","white_captures","black_captures"] {
+	    header.push(h.to_string());
+	}
+	println!("{}",header.join(","));
+    }
+    for (i,name) in inputs(m)?.iter().enumerate() {
+	let g = match load(name)? {
+	    Some(g) => g,
+	    None => { valid = false; continue; }
+	};
+	let rows = material::summarise(&g,board::INITIAL);
+	if rows.len() <= g.len() {
+	    valid = false;
+	    eprintln!("{}: invalid move {} at ply {}",name,g.get(rows.len() - 1).unwrap(),rows.len());
+	}
+	if !csv {
+	    if i > 0 { println!(); }
+	    println!("{}:",name);
+	    println!("  ply  move      P  N  B  R  Q    p  n  b  r  q   white  black  balance  captures  captured");
+	}
+	for r in rows {
+	    let counts = |p: Player| -> Vec<u8> {
+		KINDS[..5].iter().map(|&kind| r.material.count(Piece{player: p, kind})).collect()
+	    };
+	    let captured = r.captured.map(|p| p.kind.to_string(p.player)).unwrap_or("");
+	    let (white,black) = (r.material.points(Player::White),r.material.points(Player::Black));
+	    let san = r.san.as_deref().unwrap_or("");
+	    if csv {
+		let mut fields = vec![csv_field(name),r.ply.to_string(),san.to_string()];
+		fields.extend(counts(Player::White).iter().chain(&counts(Player::Black)).map(|n| n.to_string()));
+		fields.extend([white.to_string(),black.to_string(),r.material.balance().to_string(),captured.to_string(),
+			       r.captures.total(Player::Black).to_string(),r.captures.total(Player::White).to_string()]);
+		println!("{}",fields.join(","));
+	    } else {
+		let w : Vec<String> = counts(Player::White).iter().map(|n| format!("{:>2}",n)).collect();
+		let b : Vec<String> = counts(Player::Black).iter().map(|n| format!("{:>2}",n)).collect();
+		let captures = format!("{}/{}",r.captures.total(Player::Black),r.captures.total(Player::White));
+		let line = format!("  {:>3}  {:<8} {}   {}   {:>5}  {:>5}  {:>+7}  {:>8}  {}",r.ply,san,w.join(" "),b.join(" "),
+				   white,black,r.material.balance(),captures,captured);
+		println!("{}",line.trim_end());
+	    }
+	}
+    }
+    Ok(valid)
+}
+
+/**
+ * Get the (plural) name of a kind of piece, as used in CSV headers.
+ */
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+	Kind::Pawn => "pawns",
+	Kind::Knight => "knights",
+	Kind::Bishop => "bishops",
+	Kind::Rook => "rooks",
+	Kind::Queen => "queens",
+	_ => "kings"
+    }
+}
+
+/**
+ * Write a field of a CSV file, quoting it if necessary.
+ */
+fn csv_field(s: &str) -> String {
+    if s.contains([',','"','\n']) {
+	format!("\"{}\"",s.replace('"',"\"\""))
+    } else {
+	s.to_string()
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::str::FromStr;
+use std::time::Duration;
+use chess::board;
+use chess::board::Board;
+use chess::game::Game;
+use chess::eval::Weights;
+use chess::search::Limits;
+use args::{Matches, Opt};
+
+pub mod args;
+pub mod view;
+pub mod check;
+pub mod convert;
+pub mod show;
+pub mod perft;
+pub mod fen;
+pub mod stats;
+pub mod material;
+pub mod analyse;
+pub mod eval;
+pub mod annotate;
+pub mod mate;
+pub mod puzzles;
+pub mod epd;
+pub mod book;
+pub mod tablebase;
+pub mod uci;
+
+/**
+ * Result of running a command, which is either whether every game
+ * given was valid, or a message describing a usage or I/O error.
+ */
+pub type Outcome = Result<bool,String>;
+
+// ======================================================
+// Options
+// ======================================================
+
+pub const STYLE: Opt = Opt{name: "style", value: Some("NAME"), help: "Board style: ascii (default), unicode or colour"};
+pub const FLIP: Opt = Opt{name: "flip", value: None, help: "Show the board from black's perspective"};
+pub const PLY: Opt = Opt{name: "ply", value: Some("N"), help: "Use the position after N plies (default: the last)"};
+pub const POSITION: Opt = Opt{name: "fen", value: Some("FEN"), help: "Position to use, if no file is given (default: the initial position)"};
+pub const WEIGHTS: Opt = Opt{name: "weights", value: Some("FILE"), help: "Read evaluation weights from FILE (see \"eval --print-weights\")"};
+
+// ======================================================
+// Input
+// ======================================================
+
+/**
+ * Get the files given to a command, of which there must be at least
+ * one.
+ */
+pub fn inputs(m: &Matches) -> Result<&[String],String> {
+    if m.args.is_empty() {
+	Err("no input files given".to_string())
+    } else {
+	Ok(&m.args)
+    }
+}
+
+/**
+ * Read the contents of a given file, where "-" denotes standard
+ * input.
+ */
+pub fn read_input(name: &str) -> Result<String,String> {
+    if name == "-" {
+	let mut contents = String::new();
+	io::stdin().read_to_string(&mut contents)
+	    .map_err(|e| format!("error reading standard input: {}",e))?;
+	Ok(contents)
+    } else {
+	fs::read_to_string(name).map_err(|e| format!("error reading {}: {}",name,e))
+    }
+}
+
+/**
+ * Read and parse a game from a given file.  If the game cannot be
+ * parsed, this is reported and nothing is returned (as the game is
+ * invalid).
+ */
+pub fn load(name: &str) -> Result<Option<Game>,String> {
+    let contents = read_input(name)?;
+    match Game::from_str(&contents) {
+	Ok(g) => Ok(Some(g)),
+	Err(e) => {
+	    eprintln!("{}: {}",name,e);
+	    Ok(None)
+	}
+    }
+}
+
+/**
+ * Replay a game from the initial board, producing the last board
+ * reached, the number of moves applied and the first move (if any)
+ * which could not be applied.
+ */
+pub fn replay(game: &Game) -> (Board,usize,Option<String>) {
+    let mut board = board::INITIAL;
+    let mut plies = 0;
+    for (ply,_,_,after) in game.positions(board::INITIAL) {
+	board = after;
+	plies = ply;
+    }
+    (board,plies,game.get(plies).map(|m| m.to_string()))
+}
+
+/**
+ * Determine the board after a given number of plies (or the last
+ * board reached, if none is given).  If the game does not reach that
+ * ply, an explanation is reported and nothing is returned.
+ */
+pub fn board_at(name: &str, game: &Game, ply: Option<usize>) -> Option<(Board,usize)> {
+    let (last,plies,invalid) = replay(game);
+    if let Some(m) = &invalid {
+	eprintln!("{}: invalid move {} at ply {}",name,m,plies + 1);
+    }
+    match ply {
+	None => Some((last,plies)),
+	Some(n) if n <= plies => Some((game.position(board::INITIAL,n).unwrap(),n)),
+	Some(n) => {
+	    eprintln!("{}: ply {} is not reached (game has {} valid plies)",name,n,plies);
+	    None
+	}
+    }
+}
+
+// ======================================================
+// Position & Search
+// ======================================================
+
+/**
+ * Determine the position a command is given, which is either that
+ * reached in a file (see board_at), a FEN string or otherwise the
+ * initial position.  If the file is invalid, this is reported and
+ * nothing is returned.
+ */
+pub fn position(m: &Matches) -> Result<Option<Board>,String> {
+    match (m.args.as_slice(),m.value("fen")) {
+	([],Some(f)) => Ok(Some(chess::fen::from_str(f).map_err(|e| format!("invalid FEN: {}",e))?)),
+	([],None) => Ok(Some(board::INITIAL)),
+	([name],None) => {
+	    let ply = m.parse_value::<usize>("ply")?;
+	    Ok(load(name)?.and_then(|g| board_at(name,&g,ply)).map(|(brd,_)| brd))
+	}
+	([_],Some(_)) => Err("cannot give both a file and --fen".to_string()),
+	_ => Err("at most one file can be given".to_string())
+    }
+}
+
+/**
+ * Read the evaluation weights given to a command (or the defaults if
+ * none are given).
+ */
+pub fn weights(m: &Matches) -> Result<Weights,String> {
+    match m.value("weights") {
+	Some(name) => Weights::from_str(&read_input(name)?).map_err(|e| format!("{}: {}",name,e)),
+	None => Ok(Weights::default())
+    }
+}
+
+/**
+ * Determine the limits on searching given to a command, where the
+ * depth defaults to four plies unless a time is given.
+ */
+pub fn limits(m: &Matches) -> Result<Limits,String> {
+    let time = m.parse_value::<u64>("time")?.map(Duration::from_millis);
+    let depth = match m.parse_value::<usize>("depth")? {
+	None if time.is_none() => Some(4),
+	d => d
+    };
+    Ok(Limits{depth, time, nodes: None})
+}
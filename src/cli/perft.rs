@@ -0,0 +1,36 @@
+use chess::board;
+use chess::fen;
+use chess::movegen;
+use super::args::{Command, Matches, Opt};
+use super::Outcome;
+
+pub const PERFT: Command = Command {
+    name: "perft",
+    usage: "",
+    summary: "Count the move sequences of a given length from a position",
+    options: &[Opt{name: "depth", value: Some("N"), help: "Number of plies to search (default: 3)"},
+	       Opt{name: "fen", value: Some("FEN"), help: "Starting position (default: the initial position)"},
+	       Opt{name: "divide", value: None, help: "Print the count below each legal move"}]
+};
+
+pub fn perft(m: &Matches) -> Outcome {
+    if !m.args.is_empty() {
+	return Err("perft does not take any files".to_string());
+    }
+    let depth = m.parse_value::<usize>("depth")?.unwrap_or(3);
+    let brd = match m.value("fen") {
+	Some(f) => fen::from_str(f).map_err(|e| format!("invalid FEN: {}",e))?,
+	None => board::INITIAL
+    };
+    if m.flag("divide") {
+	let mut total = 0;
+	for (mv,n) in movegen::divide(&brd,depth) {
+	    println!("{}: {}",mv,n);
+	    total += n;
+	}
+	println!("\nTotal: {}",total);
+    } else {
+	println!("{}",movegen::perft(&brd,depth));
+    }
+    Ok(true)
+}
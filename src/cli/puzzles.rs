@@ -0,0 +1,27 @@
+use chess::puzzle;
+use super::args::{Command, Matches, Opt};
+use super::{inputs, read_input, Outcome};
+
+pub const PUZZLES: Command = Command {
+    name: "puzzles",
+    usage: "FILE...",
+    summary: "Check the solutions of puzzles (one \"FEN; moves; themes\" per line)",
+    options: &[Opt{name: "quiet", value: None, help: "Print nothing, only set the exit status"}]
+};
+
+pub fn puzzles(m: &Matches) -> Outcome {
+    let quiet = m.flag("quiet");
+    let mut valid = true;
+    for name in inputs(m)? {
+	let puzzles = puzzle::read(&read_input(name)?);
+	let failed: Vec<_> = puzzles.iter().filter_map(|p| p.as_ref().err()).collect();
+	if !quiet {
+	    for e in &failed {
+		println!("{}: {}",name,e);
+	    }
+	    println!("{}: {} puzzles, {} failed",name,puzzles.len(),failed.len());
+	}
+	valid &= failed.is_empty();
+    }
+    Ok(valid)
+}
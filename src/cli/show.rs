@@ -0,0 +1,90 @@
+use std::fs;
+use chess::board;
+use chess::render;
+use chess::board::Board;
+use chess::diagram::Diagram;
+use chess::muve::Move;
+use super::args::{Command, Matches, Opt};
+use super::view;
+use super::{board_at, inputs, load, Outcome, FLIP, PLY, STYLE};
+
+pub const SHOW: Command = Command {
+    name: "show",
+    usage: "FILE...",
+    summary: "Show the board reached in games",
+    options: &[PLY, STYLE, FLIP,
+	       Opt{name: "diagram", value: Some("OUT"), help: "Also write a diagram to OUT (PNG if it ends in .png, otherwise SVG)"}]
+};
+
+pub const VIEW: Command = Command {
+    name: "view",
+    usage: "FILE",
+    summary: "Step through a game interactively",
+    options: &[STYLE, FLIP]
+};
+
+pub fn show(m: &Matches) -> Outcome {
+    let renderer = render::from_style(m.value("style").unwrap_or("ascii"),m.flag("flip"))
+	.ok_or("unknown style (expected ascii, unicode or colour)")?;
+    let ply = m.parse_value::<usize>("ply")?;
+    let files = inputs(m)?;
+    if m.value("diagram").is_some() && files.len() > 1 {
+	return Err("a diagram can only be written for a single game".to_string());
+    }
+    let mut valid = true;
+    for name in files {
+	let g = match load(name)? {
+	    Some(g) => g,
+	    None => { valid = false; continue; }
+	};
+	valid &= g.apply(board::INITIAL).is_ok();
+	let (brd,n) = match board_at(name,&g,ply) {
+	    Some(r) => r,
+	    None => { valid = false; continue; }
+	};
+	// Highlight the last move made
+	let last = n.checked_sub(1).and_then(|i| g.get(i));
+	let highlight = last.map(|mv| vec![mv.from(),mv.to()]).unwrap_or_default();
+	if files.len() > 1 { println!("{}:",name); }
+	println!("{}\n",renderer.render(&brd,&highlight));
+	if let Some(out) = m.value("diagram") {
+	    write_diagram(out,&brd,last,m.flag("flip"))?;
+	}
+    }
+    Ok(valid)
+}
+
+/**
+ * Write a diagram of a given board, with the move leading to it (if
+ * any) shown as an arrow.  The format is PNG if the filename ends in
+ * ".png", and SVG otherwise.
+ */
+fn write_diagram(filename: &str, brd: &Board, last: Option<&dyn Move>, flipped: bool) -> Result<(),String> {
+    let mut d = Diagram{flipped, ..Diagram::default()};
+    if let Some(mv) = last {
+	d.highlight = vec![mv.from(),mv.to()];
+	d.arrows = vec![(mv.from(),mv.to())];
+    }
+    let bytes = if filename.ends_with(".png") {
+	d.to_png(brd)
+    } else {
+	d.to_svg(brd).into_bytes()
+    };
+    fs::write(filename,bytes).map_err(|e| format!("error writing {}: {}",filename,e))
+}
+
+pub fn view(m: &Matches) -> Outcome {
+    let renderer = render::from_style(m.value("style").unwrap_or("ascii"),m.flag("flip"))
+	.ok_or("unknown style (expected ascii, unicode or colour)")?;
+    let name = match inputs(m)? {
+	[name] => name,
+	_ => return Err("view expects a single game".to_string())
+    };
+    let g = match load(name)? {
+	Some(g) => g,
+	None => return Ok(false)
+    };
+    let replay = view::Replay::new(&g,board::INITIAL);
+    view::view(&replay,renderer.as_ref()).map_err(|e| format!("error viewing game: {}",e))?;
+    Ok(g.apply(board::INITIAL).is_ok())
+}
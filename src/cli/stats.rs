@@ -0,0 +1,58 @@
+use chess::board;
+use chess::material;
+use chess::movegen;
+use chess::piece::{Kind, Player, BLANK};
+use super::args::{Command, Matches};
+use super::{inputs, load, replay, Outcome};
+
+pub const STATS: Command = Command {
+    name: "stats",
+    usage: "FILE...",
+    summary: "Print statistics about games (e.g. captures and checks)",
+    options: &[]
+};
+
+pub fn stats(m: &Matches) -> Outcome {
+    let mut valid = true;
+    for name in inputs(m)? {
+	let g = match load(name)? {
+	    Some(g) => g,
+	    None => { valid = false; continue; }
+	};
+	let (mut captures, mut checks, mut castles, mut promotions, mut en_passant) = (0,0,0,0,0);
+	for (_,mv,before,after) in g.positions(board::INITIAL) {
+	    let piece = before.get(mv.from());
+	    if material::captured(&before,mv).is_some() {
+		captures += 1;
+		if before.get(mv.to()) == BLANK { en_passant += 1; }
+	    }
+	    if piece.kind == Kind::King && (mv.from().column() as i8 - mv.to().column() as i8).abs() == 2 {
+		castles += 1;
+	    }
+	    if mv.promotion().is_some() { promotions += 1; }
+	    if after.in_check(after.turn()) { checks += 1; }
+	}
+	let (last,plies,invalid) = replay(&g);
+	let result = match invalid {
+	    Some(mv) => {
+		valid = false;
+		format!("invalid move {} at ply {}",mv,plies + 1)
+	    }
+	    None if movegen::is_checkmate(&last) => {
+		let winner = match last.turn() { Player::White => "black", Player::Black => "white" };
+		format!("checkmate ({} wins)",winner)
+	    }
+	    None if movegen::is_stalemate(&last) => "stalemate".to_string(),
+	    None => "unfinished".to_string()
+	};
+	println!("{}:",name);
+	println!("  plies:      {}",plies);
+	println!("  captures:   {}",captures);
+	println!("  en passant: {}",en_passant);
+	println!("  checks:     {}",checks);
+	println!("  castles:    {}",castles);
+	println!("  promotions: {}",promotions);
+	println!("  result:     {}",result);
+    }
+    Ok(valid)
+}
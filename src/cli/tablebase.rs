@@ -0,0 +1,118 @@
+use std::fs;
+use std::str::FromStr;
+use chess::board;
+use chess::san;
+use chess::tablebase;
+use chess::tablebase::{Signature, Table, Tablebase};
+use super::args::{Command, Matches, Opt};
+use super::{load, position, replay, Outcome, PLY, POSITION};
+
+pub const TABLEBASE: Command = Command {
+    name: "tablebase",
+    usage: "[FILE]",
+    summary: "Look up the exact result of an endgame, or generate the tables for one",
+    options: &[Opt{name: "dir", value: Some("DIR"), help: "Directory holding the tables (default: the current directory)"},
+	       Opt{name: "generate", value: Some("LIST"), help: "Generate tables for material (e.g. \"KQK,KRKP\") and those they need, writing them to DIR"},
+	       Opt{name: "changes", value: None, help: "Report every move of the game in FILE which changes its exact result"},
+	       POSITION, PLY]
+};
+
+/**
+ * Extension of the files holding tables.
+ */
+const TABLE_EXTENSION: &str = "tb";
+
+pub fn tablebase(m: &Matches) -> Outcome {
+    let dir = m.value("dir").unwrap_or(".");
+    let mut tb = read_tables(dir)?;
+    if let Some(list) = m.value("generate") {
+	for name in list.split(',') {
+	    let material = Signature::from_str(name.trim()).map_err(|e| e.to_string())?;
+	    for made in tb.generate(&material).map_err(|e| e.to_string())? {
+		let table = tb.get(&made).unwrap();
+		let file = format!("{}/{}.{}",dir,made,TABLE_EXTENSION);
+		fs::write(&file,table.to_bytes()).map_err(|e| format!("error writing {}: {}",file,e))?;
+		let (wins,draws,losses) = table.counts();
+		println!("{}: {} wins, {} draws, {} losses, longest mate {} plies",file,wins,draws,losses,table.longest());
+	    }
+	}
+	if m.args.is_empty() && m.value("fen").is_none() {
+	    return Ok(true);
+	}
+    }
+    if m.flag("changes") {
+	return changes(m,&tb);
+    }
+    let brd = match position(m)? {
+	Some(brd) => brd,
+	None => return Ok(false)
+    };
+    let material = Signature::of(&brd);
+    match (tb.probe(&brd),tb.moves(&brd)) {
+	(Some(v),Some(moves)) => {
+	    println!("{}: {}",material,v);
+	    for (mv,v) in moves {
+		println!("  {:<8} {}",san::to_string(&brd,mv.as_ref()),v);
+	    }
+	}
+	_ if material.len() > tablebase::MAX_PIECES => return Err(format!("no tables for more than {} pieces",tablebase::MAX_PIECES)),
+	_ if brd.castling() != 0 => return Err("positions where castling is possible are not covered".to_string()),
+	_ => return Err(format!("tables needed for {} are missing (see --generate)",material))
+    }
+    Ok(true)
+}
+
+/**
+ * Read every table in a given directory.
+ */
+fn read_tables(dir: &str) -> Result<Tablebase,String> {
+    let mut tb = Tablebase::new();
+    let entries = fs::read_dir(dir).map_err(|e| format!("error reading {}: {}",dir,e))?;
+    for entry in entries {
+	let path = entry.map_err(|e| format!("error reading {}: {}",dir,e))?.path();
+	if path.extension().is_some_and(|e| e == TABLE_EXTENSION) {
+	    let name = path.display();
+	    let bytes = fs::read(&path).map_err(|e| format!("error reading {}: {}",name,e))?;
+	    tb.insert(Table::from_bytes(&bytes).map_err(|e| format!("{}: {}",name,e))?);
+	}
+    }
+    Ok(tb)
+}
+
+/**
+ * Report each move of a game which changes the exact result of the
+ * position (e.g. throwing away a win), where tables are available.
+ */
+fn changes(m: &Matches, tb: &Tablebase) -> Outcome {
+    let name = match m.args.as_slice() {
+	[name] => name,
+	_ => return Err("exactly one file must be given with --changes".to_string())
+    };
+    let game = match load(name)? {
+	Some(g) => g,
+	None => return Ok(false)
+    };
+    let valid = game.apply(board::INITIAL).is_ok();
+    if !valid {
+	let (_,plies,invalid) = replay(&game);
+	eprintln!("{}: invalid move {} at ply {}",name,invalid.unwrap_or_default(),plies + 1);
+    }
+    let (mut probed, mut changed) = (0,0);
+    for (ply,mv,before,after) in game.positions(board::INITIAL) {
+	let (best,played) = match (tb.moves(&before),tb.probe(&after)) {
+	    (Some(moves),Some(v)) if !moves.is_empty() => (moves,v.parent()),
+	    _ => continue
+	};
+	probed += 1;
+	let value = best[0].1;
+	if !value.same_result(played) {
+	    changed += 1;
+	    let alternatives: Vec<String> = best.iter().take_while(|(_,v)| v.same_result(value))
+		.map(|(b,_)| san::to_string(&before,b.as_ref())).collect();
+	    println!("{}: ply {} {} turns {} into {} (instead {})",name,ply,san::to_string(&before,mv),
+		     value,played,alternatives.join(", "));
+	}
+    }
+    println!("{}: {} positions found in tables, {} results changed",name,probed,changed);
+    Ok(valid)
+}
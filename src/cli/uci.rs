@@ -0,0 +1,19 @@
+use std::io;
+use chess::search::Search;
+use chess::uci;
+use super::args::{Command, Matches};
+use super::{weights, Outcome, WEIGHTS};
+
+pub const UCI: Command = Command {
+    name: "uci",
+    usage: "",
+    summary: "Play as an engine for a GUI, speaking the Universal Chess Interface",
+    options: &[WEIGHTS]
+};
+
+pub fn uci(m: &Matches) -> Outcome {
+    let mut search = Search::default();
+    search.weights = weights(m)?;
+    uci::serve(io::stdin().lock(),io::stdout(),search).map_err(|e| e.to_string())?;
+    Ok(true)
+}
@@ -0,0 +1,328 @@
+use std::io;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use chess::board::Board;
+use chess::game::Game;
+use chess::render::Renderer;
+use chess::square::Square;
+
+/**
+ * Escape sequence for clearing the terminal and moving the cursor to
+ * the top left.
+ */
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/**
+ * Escape sequence for starting highlighted (i.e. reverse video) text.
+ */
+const HIGHLIGHT: &str = "\x1b[7m";
+
+/**
+ * Escape sequence for ending highlighted text.
+ */
+const NORMAL: &str = "\x1b[0m";
+
+/**
+ * A game which has been replayed ready for viewing.
+ */
+pub struct Replay {
+    /**
+     * Board at each ply, starting from the initial board
+     */
+    boards: Vec<Board>,
+    /**
+     * Text of each move successfully applied
+     */
+    moves: Vec<String>,
+    /**
+     * Text of the first move which could not be applied (if any)
+     */
+    invalid: Option<String>
+}
+
+impl Replay {
+    /**
+     * Replay a given game from a given starting board.
+     */
+    pub fn new(game: &Game, start: Board) -> Replay {
+	let mut boards = vec![start];
+	let mut moves = Vec::new();
+	for (_,m,_,after) in game.positions(start) {
+	    boards.push(after);
+	    moves.push(m.to_string());
+	}
+	let invalid = game.get(moves.len()).map(|m| m.to_string());
+	Replay{boards,moves,invalid}
+    }
+
+    /**
+     * Get the number of plies which can be viewed.
+     */
+    pub fn len(&self) -> usize {
+	self.moves.len()
+    }
+
+    /**
+     * Determine the squares changed by the move at a given ply (e.g.
+     * the from and to squares of the piece moved).
+     */
+    fn changed(&self, ply: usize) -> Vec<Square> {
+	let mut squares = Vec::new();
+	if ply > 0 {
+	    for row in 0..8 {
+		for col in 0..8 {
+		    let s = Square::new(col,row);
+		    if self.boards[ply-1].get(s) != self.boards[ply].get(s) {
+			squares.push(s);
+		    }
+		}
+	    }
+	}
+	squares
+    }
+}
+
+/**
+ * Keys recognised by the viewer.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Key {
+    Forward,
+    Back,
+    First,
+    Last,
+    Quit
+}
+
+/**
+ * View a given game.  When connected to a terminal, this allows the
+ * user to step forwards and backwards through the moves using the
+ * arrow keys.  Otherwise, every position is written out in turn.
+ */
+pub fn view(replay: &Replay, renderer: &dyn Renderer) -> io::Result<()> {
+    if io::stdin().is_terminal() && io::stdout().is_terminal() {
+	interactive(replay,renderer)
+    } else {
+	plain(replay,renderer,&mut io::stdout())
+    }
+}
+
+/**
+ * Write out every position of a replayed game in turn as plain text.
+ */
+fn plain(replay: &Replay, renderer: &dyn Renderer, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out,"Start:\n{}\n",renderer.render(&replay.boards[0],&[]))?;
+    for ply in 1..=replay.len() {
+	let board = renderer.render(&replay.boards[ply],&[]);
+	writeln!(out,"{} {}:\n{}\n",number(ply),replay.moves[ply-1],board)?;
+    }
+    if let Some(m) = &replay.invalid {
+	writeln!(out,"Invalid move: {}",m)?;
+    }
+    Ok(())
+}
+
+/**
+ * Step interactively through a replayed game until the user quits.
+ */
+fn interactive(replay: &Replay, renderer: &dyn Renderer) -> io::Result<()> {
+    let _raw = RawMode::enable()?;
+    let mut stdin = BufReader::new(io::stdin());
+    let mut stdout = io::stdout();
+    let mut ply = 0;
+    loop {
+	draw(replay,ply,renderer,&mut stdout)?;
+	stdout.flush()?;
+	match read_key(&mut stdin)? {
+	    Some(Key::Forward) => { ply = (ply + 1).min(replay.len()); }
+	    Some(Key::Back) => { ply = ply.saturating_sub(1); }
+	    Some(Key::First) => { ply = 0; }
+	    Some(Key::Last) => { ply = replay.len(); }
+	    Some(Key::Quit) => { break; }
+	    None => {}
+	}
+    }
+    writeln!(stdout)
+}
+
+/**
+ * Draw the board at a given ply, along with the list of moves.
+ */
+fn draw(replay: &Replay, ply: usize, renderer: &dyn Renderer, out: &mut dyn Write) -> io::Result<()> {
+    write!(out,"{}",CLEAR)?;
+    // Draw board with highlighted squares
+    let changed = replay.changed(ply);
+    writeln!(out,"{}\n",renderer.render(&replay.boards[ply],&changed))?;
+    // Draw moves with current move highlighted
+    for (i,m) in replay.moves.iter().enumerate() {
+	if i % 2 == 0 {
+	    write!(out,"{}. ",(i / 2) + 1)?;
+	}
+	if i + 1 == ply {
+	    write!(out,"{}{}{} ",HIGHLIGHT,m,NORMAL)?;
+	} else {
+	    write!(out,"{} ",m)?;
+	}
+	if i % 2 == 1 {
+	    writeln!(out)?;
+	}
+    }
+    if let Some(m) = &replay.invalid {
+	write!(out,"\n(invalid move {})",m)?;
+    }
+    writeln!(out,"\n\nPly {}/{}: left/right to step, up/down for start/end, q to quit",
+	     ply,replay.len())
+}
+
+/**
+ * Determine the move number for a given ply (e.g. "1." for white's
+ * first move and "1..." for black's).
+ */
+fn number(ply: usize) -> String {
+    if ply % 2 == 1 {
+	format!("{}.",ply.div_ceil(2))
+    } else {
+	format!("{}...",ply / 2)
+    }
+}
+
+/**
+ * Read the next key pressed, where nothing is returned for keys which
+ * are not recognised.  Arrow keys are sent by the terminal as escape
+ * sequences (e.g. "ESC [ C" for right), which arrive together.
+ * Hence, the rest of a sequence is only looked for in what has
+ * already been read, so that pressing escape alone does not block.
+ */
+fn read_key<R: Read>(input: &mut BufReader<R>) -> io::Result<Option<Key>> {
+    let mut buf = [0u8;1];
+    if input.read(&mut buf)? == 0 {
+	// End of input
+	return Ok(Some(Key::Quit));
+    }
+    let key = match buf[0] {
+	b'q' | b'Q' | 0x03 | 0x04 => Some(Key::Quit),
+	b'n' | b' ' | b'l' => Some(Key::Forward),
+	b'p' | b'h' => Some(Key::Back),
+	0x1b => {
+	    let key = match input.buffer() {
+		[b'[',b'C',..] => Some(Key::Forward),
+		[b'[',b'D',..] => Some(Key::Back),
+		[b'[',b'A',..] | [b'[',b'H',..] => Some(Key::First),
+		[b'[',b'B',..] | [b'[',b'F',..] => Some(Key::Last),
+		_ => None
+	    };
+	    if key.is_some() {
+		input.consume(2);
+	    }
+	    key
+	}
+	_ => None
+    };
+    Ok(key)
+}
+
+/**
+ * Puts the terminal into a mode where keys are read immediately
+ * without being echoed.  The original mode is restored when this is
+ * dropped.
+ */
+struct RawMode {
+    /**
+     * Original terminal settings, as reported by stty
+     */
+    saved: String
+}
+
+impl RawMode {
+    /**
+     * Switch the terminal into raw mode, remembering how to switch
+     * back.
+     */
+    fn enable() -> io::Result<RawMode> {
+	let output = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output()?;
+	if !output.status.success() {
+	    return Err(io::Error::other("cannot read terminal settings with stty"));
+	}
+	let saved = String::from_utf8_lossy(&output.stdout).trim().to_string();
+	stty(&["-icanon","-echo","-isig","min","1"])?;
+	Ok(RawMode{saved})
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+	let _ = stty(&[&self.saved]);
+    }
+}
+
+/**
+ * Run stty with the given arguments on the current terminal.
+ */
+fn stty(args: &[&str]) -> io::Result<()> {
+    if Command::new("stty").args(args).stdin(Stdio::inherit()).status()?.success() {
+	Ok(())
+    } else {
+	Err(io::Error::other("cannot change terminal settings with stty"))
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use chess::board::INITIAL;
+#[cfg(test)]
+use chess::render::Ascii;
+
+#[test]
+fn test_01() {
+    let g = Game::from_str("e2-e4 e7-e5\nNg1-f3").unwrap();
+    let r = Replay::new(&g,INITIAL);
+    assert_eq!(r.len(),3);
+    assert!(r.invalid.is_none());
+    assert_eq!(r.changed(0),vec![]);
+    assert_eq!(r.changed(1),vec![Square::new(4,1),Square::new(4,3)]);
+}
+
+#[test]
+fn test_02() {
+    let g = Game::from_str("e2-e4 e7-e5\ne4-e6").unwrap();
+    let r = Replay::new(&g,INITIAL);
+    assert_eq!(r.len(),2);
+    assert_eq!(r.invalid,Some("e4-e6".to_string()));
+}
+
+#[test]
+fn test_03() {
+    let g = Game::from_str("e2-e4 e7-e5").unwrap();
+    let mut out = Vec::new();
+    plain(&Replay::new(&g,INITIAL),&Ascii{flipped: false},&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.starts_with("Start:\n8|r|n|b|q|k|b|n|r|"));
+    assert!(text.contains("1. e2-e4:\n"));
+    assert!(text.contains("1... e7-e5:\n"));
+}
+
+#[test]
+fn test_04() {
+    let mut input = BufReader::new(&b"\x1b[C\x1b[Dxq"[..]);
+    assert_eq!(read_key(&mut input).unwrap(),Some(Key::Forward));
+    assert_eq!(read_key(&mut input).unwrap(),Some(Key::Back));
+    assert_eq!(read_key(&mut input).unwrap(),None);
+    assert_eq!(read_key(&mut input).unwrap(),Some(Key::Quit));
+    assert_eq!(read_key(&mut input).unwrap(),Some(Key::Quit));
+}
+
+#[test]
+fn test_05() {
+    // Escape on its own does not wait for a sequence
+    let mut input = BufReader::new(&b"\x1b"[..]);
+    assert_eq!(read_key(&mut input).unwrap(),None);
+    assert_eq!(read_key(&mut input).unwrap(),Some(Key::Quit));
+    let mut input = BufReader::new(&b"\x1bq"[..]);
+    assert_eq!(read_key(&mut input).unwrap(),None);
+    assert_eq!(read_key(&mut input).unwrap(),Some(Key::Quit));
+}
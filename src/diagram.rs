@@ -0,0 +1,473 @@
+use std::fmt::Write;
+use super::board::Board;
+use super::piece::*;
+use super::raster::{Colour, Image, Polygon};
+use super::render::Renderer;
+use super::square::Square;
+
+/**
+ * Width (and height) of a square in the diagram.
+ */
+const SQUARE: f64 = 45.0;
+
+/**
+ * Width of the margin holding the coordinates, which is to the left
+ * of and below the board.
+ */
+const MARGIN: f64 = 20.0;
+
+/**
+ * Overall width (and height) of the diagram.
+ */
+const SIZE: f64 = MARGIN + (8.0 * SQUARE);
+
+const BACKGROUND: Colour = Colour::rgb(255,255,255);
+const LIGHT_SQUARE: Colour = Colour::rgb(240,217,181);
+const DARK_SQUARE: Colour = Colour::rgb(181,136,99);
+const HIGHLIGHT: Colour = Colour::rgba(255,255,0,100);
+const ARROW: Colour = Colour::rgba(21,120,27,170);
+const COORDINATE: Colour = Colour::rgb(64,64,64);
+const WHITE_PIECE: Colour = Colour::rgb(255,255,255);
+const BLACK_PIECE: Colour = Colour::rgb(0,0,0);
+const OUTLINE: Colour = Colour::rgb(0,0,0);
+
+/**
+ * Describes a diagram of a board suitable for publication (e.g. in a
+ * newsletter).  Diagrams include coordinates, and may highlight some
+ * squares and show arrows (e.g. for the last move).  They can be
+ * written as SVG or PNG, where the latter is rasterised from the same
+ * shapes as the former.
+ */
+pub struct Diagram {
+    /**
+     * Whether to show the board from black's perspective
+     */
+    pub flipped: bool,
+    /**
+     * Squares to highlight
+     */
+    pub highlight: Vec<Square>,
+    /**
+     * Arrows to draw, each going from one square to another
+     */
+    pub arrows: Vec<(Square,Square)>,
+    /**
+     * Width (and height) in pixels of the diagram when written as PNG
+     */
+    pub size: usize
+}
+
+/**
+ * A shape making up part of a diagram, with coordinates given in the
+ * units of the diagram (i.e. where a square is 45 units wide).
+ */
+enum Shape {
+    Rect{x: f64, y: f64, w: f64, h: f64, fill: Colour},
+    Circle{cx: f64, cy: f64, r: f64, fill: Colour, stroke: Option<f64>},
+    Polygon{points: Polygon, fill: Colour, stroke: Option<f64>},
+    Text{x: f64, y: f64, text: char, fill: Colour}
+}
+
+impl Default for Diagram {
+    fn default() -> Diagram {
+	Diagram{flipped: false, highlight: Vec::new(), arrows: Vec::new(), size: 380}
+    }
+}
+
+impl Diagram {
+    /**
+     * Write a diagram of a given board in the SVG format.
+     */
+    pub fn to_svg(&self, board: &Board) -> String {
+	let mut out = String::new();
+	let _ = writeln!(out,"<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\">",
+			 SIZE,SIZE,self.size,self.size);
+	for shape in self.shapes(board) {
+	    let _ = match shape {
+		Shape::Rect{x,y,w,h,fill} => {
+		    writeln!(out,"<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",x,y,w,h,svg_fill(fill))
+		}
+		Shape::Circle{cx,cy,r,fill,stroke} => {
+		    writeln!(out,"<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}{}/>",cx,cy,r,svg_fill(fill),svg_stroke(stroke))
+		}
+		Shape::Polygon{points,fill,stroke} => {
+		    let ps : Vec<String> = points.iter().map(|(x,y)| format!("{},{}",x,y)).collect();
+		    writeln!(out,"<polygon points=\"{}\" {}{}/>",ps.join(" "),svg_fill(fill),svg_stroke(stroke))
+		}
+		Shape::Text{x,y,text,fill} => {
+		    writeln!(out,"<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"12\" text-anchor=\"middle\" {}>{}</text>",
+			     x,y,svg_fill(fill),text)
+		}
+	    };
+	}
+	out.push_str("</svg>\n");
+	out
+    }
+
+    /**
+     * Write a diagram of a given board in the PNG format.
+     */
+    pub fn to_png(&self, board: &Board) -> Vec<u8> {
+	let scale = self.size as f64 / SIZE;
+	let mut img = Image::new(self.size,self.size,BACKGROUND);
+	let scaled = |ps: &Polygon| -> Polygon { ps.iter().map(|(x,y)| (x * scale, y * scale)).collect() };
+	for shape in self.shapes(board) {
+	    match shape {
+		Shape::Rect{x,y,w,h,fill} => {
+		    img.fill(&[scaled(&vec![(x,y),(x+w,y),(x+w,y+h),(x,y+h)])],fill);
+		}
+		Shape::Circle{cx,cy,r,fill,stroke} => {
+		    let points = circle(cx,cy,r);
+		    img.fill(&[scaled(&points)],fill);
+		    if let Some(width) = stroke {
+			let outline : Vec<Polygon> = stroke_polyline(&points,true,width).iter().map(scaled).collect();
+			img.fill(&outline,OUTLINE);
+		    }
+		}
+		Shape::Polygon{points,fill,stroke} => {
+		    img.fill(&[scaled(&points)],fill);
+		    if let Some(width) = stroke {
+			let outline : Vec<Polygon> = stroke_polyline(&points,true,width).iter().map(scaled).collect();
+			img.fill(&outline,OUTLINE);
+		    }
+		}
+		Shape::Text{x,y,text,fill} => {
+		    let strokes : Vec<Polygon> = text_strokes(x,y,text).iter().map(scaled).collect();
+		    img.fill(&strokes,fill);
+		}
+	    }
+	}
+	img.to_png()
+    }
+
+    /**
+     * Determine the position of the top left corner of a given square
+     * in the diagram.
+     */
+    fn corner(&self, s: Square) -> (f64,f64) {
+	let (col,row) = if self.flipped {
+	    (7 - s.column(), s.row())
+	} else {
+	    (s.column(), 7 - s.row())
+	};
+	(MARGIN + col as f64 * SQUARE, row as f64 * SQUARE)
+    }
+
+    /**
+     * Determine the shapes making up a diagram of a given board, in
+     * the order they should be drawn.
+     */
+    fn shapes(&self, board: &Board) -> Vec<Shape> {
+	let mut shapes = Vec::new();
+	// Squares and pieces
+	for row in 0..8 {
+	    for col in 0..8 {
+		let s = Square::new(col,row);
+		let (x,y) = self.corner(s);
+		let fill = if (row + col) % 2 == 0 { DARK_SQUARE } else { LIGHT_SQUARE };
+		shapes.push(Shape::Rect{x,y,w:SQUARE,h:SQUARE,fill});
+		if self.highlight.contains(&s) {
+		    shapes.push(Shape::Rect{x,y,w:SQUARE,h:SQUARE,fill:HIGHLIGHT});
+		}
+	    }
+	}
+	for row in 0..8 {
+	    for col in 0..8 {
+		let s = Square::new(col,row);
+		let (x,y) = self.corner(s);
+		piece_shapes(board.get(s),x,y,&mut shapes);
+	    }
+	}
+	// Coordinates
+	for i in 0..8 {
+	    let s = Square::new(i,i);
+	    let (x,y) = self.corner(s);
+	    let file = (b'a' + i) as char;
+	    let rank = (b'1' + i) as char;
+	    shapes.push(Shape::Text{x: x + SQUARE / 2.0, y: SIZE - 5.0, text: file, fill: COORDINATE});
+	    shapes.push(Shape::Text{x: MARGIN / 2.0, y: y + SQUARE / 2.0 + 4.0, text: rank, fill: COORDINATE});
+	}
+	// Arrows
+	for &(from,to) in &self.arrows {
+	    if let Some(points) = self.arrow(from,to) {
+		shapes.push(Shape::Polygon{points, fill: ARROW, stroke: None});
+	    }
+	}
+	shapes
+    }
+
+    /**
+     * Determine the outline of an arrow between the centres of two
+     * squares.
+     */
+    fn arrow(&self, from: Square, to: Square) -> Option<Polygon> {
+	let half = SQUARE / 2.0;
+	let (x0,y0) = self.corner(from);
+	let (x1,y1) = self.corner(to);
+	let (x0,y0,x1,y1) = (x0 + half, y0 + half, x1 + half, y1 + half);
+	let length = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+	if length == 0.0 { return None; }
+	// Unit vectors along and across the arrow
+	let (dx,dy) = ((x1 - x0) / length, (y1 - y0) / length);
+	let (nx,ny) = (-dy, dx);
+	let (shaft,head,head_length) = (4.0, 11.0, 18.0);
+	let at = |along: f64, across: f64| (x0 + dx * along + nx * across, y0 + dy * along + ny * across);
+	let base = length - head_length;
+	Some(vec![at(0.0,shaft),at(base,shaft),at(base,head),at(length,0.0),
+		  at(base,-head),at(base,-shaft),at(0.0,-shaft)])
+    }
+}
+
+/**
+ * Diagrams can be used in place of the textual renderers, in which
+ * case the SVG is produced.
+ */
+impl Renderer for Diagram {
+    fn render(&self, board: &Board, highlight: &[Square]) -> String {
+	let mut highlight = highlight.to_vec();
+	highlight.extend_from_slice(&self.highlight);
+	let d = Diagram{flipped: self.flipped, highlight, arrows: self.arrows.clone(), size: self.size};
+	d.to_svg(board)
+    }
+}
+
+/**
+ * Write out the fill attributes for a given colour in SVG.
+ */
+fn svg_fill(c: Colour) -> String {
+    if c.a == 255 {
+	format!("fill=\"#{:02x}{:02x}{:02x}\"",c.r,c.g,c.b)
+    } else {
+	format!("fill=\"#{:02x}{:02x}{:02x}\" fill-opacity=\"{:.2}\"",c.r,c.g,c.b,c.a as f64 / 255.0)
+    }
+}
+
+/**
+ * Write out the stroke attributes (if any) for an outline in SVG.
+ */
+fn svg_stroke(width: Option<f64>) -> String {
+    match width {
+	Some(w) => format!(" stroke=\"#{:02x}{:02x}{:02x}\" stroke-width=\"{}\" stroke-linejoin=\"round\"",
+			   OUTLINE.r,OUTLINE.g,OUTLINE.b,w),
+	None => String::new()
+    }
+}
+
+// ======================================================
+// Pieces
+// ======================================================
+
+/**
+ * Width of the outline drawn around pieces.
+ */
+const PIECE_OUTLINE: f64 = 1.5;
+
+/**
+ * Add the shapes for a given piece drawn on a square whose top left
+ * corner is at a given position.  Pieces are designed within the 45
+ * by 45 units of a square.
+ */
+fn piece_shapes(p: Piece, x: f64, y: f64, shapes: &mut Vec<Shape>) {
+    let fill = match p.player { Player::White => WHITE_PIECE, Player::Black => BLACK_PIECE };
+    let stroke = Some(PIECE_OUTLINE);
+    let polygon = |ps: &[(f64,f64)]| -> Shape {
+	Shape::Polygon{points: ps.iter().map(|(px,py)| (x + px, y + py)).collect(), fill, stroke}
+    };
+    let ball = |cx: f64, cy: f64, r: f64| -> Shape {
+	Shape::Circle{cx: x + cx, cy: y + cy, r, fill, stroke}
+    };
+    match p.kind {
+	Kind::Blank => {}
+	Kind::Pawn => {
+	    shapes.push(polygon(&[(18.0,19.0),(27.0,19.0),(29.0,28.0),(33.0,35.0),(35.0,39.0),
+				  (10.0,39.0),(12.0,35.0),(16.0,28.0)]));
+	    shapes.push(ball(22.5,14.0,5.5));
+	}
+	Kind::Knight => {
+	    shapes.push(polygon(&[(14.0,39.0),(36.0,39.0),(35.0,30.0),(33.0,20.0),(29.0,12.0),
+				  (25.0,9.0),(23.0,5.0),(21.0,9.0),(16.0,12.0),(10.0,19.0),
+				  (8.0,25.0),(11.0,28.0),(15.0,26.0),(21.0,22.0),(20.0,28.0),(15.0,33.0)]));
+	}
+	Kind::Bishop => {
+	    shapes.push(polygon(&[(22.5,9.0),(27.0,14.0),(29.0,20.0),(27.0,26.0),(26.0,30.0),
+				  (31.0,34.0),(34.0,39.0),(11.0,39.0),(14.0,34.0),(19.0,30.0),
+				  (18.0,26.0),(16.0,20.0),(18.0,14.0)]));
+	    shapes.push(ball(22.5,7.0,2.5));
+	}
+	Kind::Rook => {
+	    shapes.push(polygon(&[(9.0,39.0),(36.0,39.0),(36.0,35.0),(32.0,35.0),(31.0,17.0),
+				  (34.0,17.0),(34.0,9.0),(29.0,9.0),(29.0,12.0),(25.0,12.0),
+				  (25.0,9.0),(20.0,9.0),(20.0,12.0),(16.0,12.0),(16.0,9.0),
+				  (11.0,9.0),(11.0,17.0),(14.0,17.0),(13.0,35.0),(9.0,35.0)]));
+	}
+	Kind::Queen => {
+	    shapes.push(polygon(&[(10.0,39.0),(35.0,39.0),(33.0,32.0),(37.0,14.0),(30.0,26.0),
+				  (28.0,11.0),(25.0,24.0),(22.5,9.0),(20.0,24.0),(17.0,11.0),
+				  (15.0,26.0),(8.0,14.0),(12.0,32.0)]));
+	    for &(cx,cy) in &[(8.0,13.0),(17.0,10.0),(22.5,8.0),(28.0,10.0),(37.0,13.0)] {
+		shapes.push(ball(cx,cy,2.5));
+	    }
+	}
+	Kind::King => {
+	    shapes.push(polygon(&[(21.0,5.0),(24.0,5.0),(24.0,8.0),(27.0,8.0),(27.0,11.0),
+				  (24.0,11.0),(24.0,18.0),(21.0,18.0),(21.0,11.0),(18.0,11.0),
+				  (18.0,8.0),(21.0,8.0)]));
+	    shapes.push(polygon(&[(10.0,39.0),(35.0,39.0),(34.0,30.0),(37.0,23.0),(33.0,18.0),
+				  (27.0,18.0),(22.5,22.0),(18.0,18.0),(12.0,18.0),(8.0,23.0),(11.0,30.0)]));
+	}
+    }
+}
+
+// ======================================================
+// Rasterisation
+// ======================================================
+
+/**
+ * Width of the strokes used when rasterising text.
+ */
+const TEXT_STROKE: f64 = 1.2;
+
+/**
+ * Scale applied to the stroke font, whose characters are designed on
+ * a grid 4 units wide and 6 units high.
+ */
+const TEXT_SCALE: f64 = 1.5;
+
+/**
+ * Approximate a circle by a polygon.
+ */
+fn circle(cx: f64, cy: f64, r: f64) -> Polygon {
+    let n = 32;
+    (0..n).map(|i| {
+	let theta = (i as f64) * std::f64::consts::PI * 2.0 / n as f64;
+	(cx + r * theta.cos(), cy + r * theta.sin())
+    }).collect()
+}
+
+/**
+ * Determine the polygons which make up a line of a given width drawn
+ * through a sequence of points.  Joins between segments are rounded.
+ */
+fn stroke_polyline(points: &[(f64,f64)], closed: bool, width: f64) -> Vec<Polygon> {
+    let mut polygons = Vec::new();
+    let half = width / 2.0;
+    let n = if closed { points.len() } else { points.len().saturating_sub(1) };
+    for i in 0..n {
+	let (x0,y0) = points[i];
+	let (x1,y1) = points[(i + 1) % points.len()];
+	let length = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt();
+	if length == 0.0 { continue; }
+	let (nx,ny) = (-(y1 - y0) / length * half, (x1 - x0) / length * half);
+	polygons.push(vec![(x0 + nx, y0 + ny),(x1 + nx, y1 + ny),(x1 - nx, y1 - ny),(x0 - nx, y0 - ny)]);
+    }
+    for &(x,y) in points {
+	polygons.push(circle(x,y,half));
+    }
+    polygons
+}
+
+/**
+ * Determine the strokes making up a given character of the built-in
+ * stroke font, which covers only the coordinates (i.e. "a" to "h" and
+ * "1" to "8").  Each character is given as a set of lines through
+ * points on a grid 4 units wide and 6 units high (with descenders
+ * going below).
+ */
+fn glyph_lines(c: char) -> Vec<Vec<(f64,f64)>> {
+    let lines : &[&[(f64,f64)]] = match c {
+	'a' => &[&[(4.0,2.0),(4.0,6.0)],
+		 &[(4.0,3.0),(3.0,2.0),(1.0,2.0),(0.0,3.0),(0.0,5.0),(1.0,6.0),(3.0,6.0),(4.0,5.0)]],
+	'b' => &[&[(0.0,0.0),(0.0,6.0)],
+		 &[(0.0,3.0),(1.0,2.0),(3.0,2.0),(4.0,3.0),(4.0,5.0),(3.0,6.0),(1.0,6.0),(0.0,5.0)]],
+	'c' => &[&[(4.0,2.5),(3.0,2.0),(1.0,2.0),(0.0,3.0),(0.0,5.0),(1.0,6.0),(3.0,6.0),(4.0,5.5)]],
+	'd' => &[&[(4.0,0.0),(4.0,6.0)],
+		 &[(4.0,3.0),(3.0,2.0),(1.0,2.0),(0.0,3.0),(0.0,5.0),(1.0,6.0),(3.0,6.0),(4.0,5.0)]],
+	'e' => &[&[(0.0,4.0),(4.0,4.0),(4.0,3.0),(3.0,2.0),(1.0,2.0),(0.0,3.0),(0.0,5.0),(1.0,6.0),(4.0,6.0)]],
+	'f' => &[&[(3.5,0.0),(2.5,0.0),(1.5,1.0),(1.5,6.0)],&[(0.0,2.5),(3.5,2.5)]],
+	'g' => &[&[(4.0,2.0),(4.0,7.0),(3.0,8.0),(0.5,8.0)],
+		 &[(4.0,3.0),(3.0,2.0),(1.0,2.0),(0.0,3.0),(0.0,4.5),(1.0,5.5),(3.0,5.5),(4.0,4.5)]],
+	'h' => &[&[(0.0,0.0),(0.0,6.0)],&[(0.0,3.0),(1.0,2.0),(3.0,2.0),(4.0,3.0),(4.0,6.0)]],
+	'1' => &[&[(1.0,1.0),(2.0,0.0),(2.0,6.0)],&[(1.0,6.0),(3.0,6.0)]],
+	'2' => &[&[(0.0,1.0),(1.0,0.0),(3.0,0.0),(4.0,1.0),(4.0,2.0),(0.0,6.0),(4.0,6.0)]],
+	'3' => &[&[(0.0,0.0),(4.0,0.0),(2.0,2.5),(3.0,2.5),(4.0,3.5),(4.0,5.0),(3.0,6.0),(1.0,6.0),(0.0,5.0)]],
+	'4' => &[&[(3.0,6.0),(3.0,0.0),(0.0,4.0),(4.0,4.0)]],
+	'5' => &[&[(4.0,0.0),(0.0,0.0),(0.0,2.5),(3.0,2.5),(4.0,3.5),(4.0,5.0),(3.0,6.0),(0.0,6.0)]],
+	'6' => &[&[(3.5,0.0),(1.0,0.0),(0.0,1.0),(0.0,5.0),(1.0,6.0),(3.0,6.0),(4.0,5.0),(4.0,3.5),(3.0,2.5),(0.0,2.5)]],
+	'7' => &[&[(0.0,0.0),(4.0,0.0),(1.5,6.0)]],
+	'8' => &[&[(1.0,0.0),(3.0,0.0),(4.0,1.0),(4.0,2.0),(3.0,3.0),(1.0,3.0),(0.0,2.0),(0.0,1.0),(1.0,0.0)],
+		 &[(1.0,3.0),(0.0,4.0),(0.0,5.0),(1.0,6.0),(3.0,6.0),(4.0,5.0),(4.0,4.0),(3.0,3.0)]],
+	_ => &[]
+    };
+    lines.iter().map(|l| l.to_vec()).collect()
+}
+
+/**
+ * Determine the polygons for a character of text centred horizontally
+ * on a given position, with its baseline at that position.  This
+ * mirrors the SVG text written for coordinates.
+ */
+fn text_strokes(x: f64, y: f64, c: char) -> Vec<Polygon> {
+    let left = x - 2.0 * TEXT_SCALE;
+    let top = y - 6.0 * TEXT_SCALE;
+    let mut polygons = Vec::new();
+    for line in glyph_lines(c) {
+	let points : Polygon = line.iter().map(|(px,py)| (left + px * TEXT_SCALE, top + py * TEXT_SCALE)).collect();
+	polygons.extend(stroke_polyline(&points,false,TEXT_STROKE));
+    }
+    polygons
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::board::INITIAL;
+
+#[test]
+fn test_01() {
+    let svg = Diagram::default().to_svg(&INITIAL);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.ends_with("</svg>\n"));
+    // 64 squares, plus coordinates
+    assert_eq!(svg.matches("<rect ").count(), 64);
+    assert_eq!(svg.matches("<text ").count(), 16);
+    assert!(svg.contains(">a</text>"));
+    assert!(svg.contains(">8</text>"));
+}
+
+#[test]
+fn test_02() {
+    let d = Diagram{highlight: vec![Square::new(4,1),Square::new(4,3)],
+		    arrows: vec![(Square::new(4,1),Square::new(4,3))],
+		    ..Diagram::default()};
+    let svg = d.to_svg(&INITIAL);
+    assert_eq!(svg.matches("<rect ").count(), 66);
+    assert_eq!(svg.matches("fill-opacity").count(), 3);
+}
+
+#[test]
+fn test_03() {
+    // Square a1 is bottom left, or top right when flipped
+    let d = Diagram::default();
+    assert_eq!(d.corner(Square::new(0,0)), (MARGIN, 7.0 * SQUARE));
+    let d = Diagram{flipped: true, ..Diagram::default()};
+    assert_eq!(d.corner(Square::new(0,0)), (MARGIN + 7.0 * SQUARE, 0.0));
+}
+
+#[test]
+fn test_04() {
+    let d = Diagram{size: 90, ..Diagram::default()};
+    let png = d.to_png(&INITIAL);
+    assert_eq!(&png[1..4], b"PNG");
+    // Width and height in header
+    assert_eq!(&png[16..24], &[0,0,0,90,0,0,0,90]);
+}
+
+#[test]
+fn test_05() {
+    // Every coordinate has a glyph in the stroke font
+    for c in "abcdefgh12345678".chars() {
+	assert!(!glyph_lines(c).is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+use super::board::*;
+use super::game::Game;
+use super::san;
+
+/**
+ * The table of openings, which is compiled into the binary.  Each
+ * line gives the ECO code, the name of the opening and the moves (in
+ * SAN) reaching it, separated by tabs.  Lines starting with "#" are
+ * ignored.
+ */
+const TABLE: &str = include_str!("eco.tsv");
+
+/**
+ * An opening, as identified by its code in the Encyclopaedia of
+ * Chess Openings (ECO) along with its name.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    /**
+     * ECO code of the opening (e.g. "C20")
+     */
+    pub code: &'static str,
+    /**
+     * Name of the opening (e.g. "King's Pawn Game")
+     */
+    pub name: &'static str
+}
+
+/**
+ * Openings are written as their code followed by their name (e.g.
+ * "C20 King's Pawn Game").
+ */
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f,"{} {}",self.code,self.name)
+    }
+}
+
+/**
+ * Identify the opening reached by a given board (if any).  Positions
+ * are matched by hash, so it does not matter how the board was
+ * reached.
+ */
+pub fn lookup(board: &Board) -> Option<Opening> {
+    table().get(&board.hash()).copied()
+}
+
+/**
+ * Classify a game played from a given starting board, using the last
+ * position in the game which matches an opening.  Since positions
+ * (rather than moves) are matched, transpositions into an opening are
+ * recognised.  Moves after the first invalid move are ignored.
+ */
+pub fn classify(game: &Game, start: Board) -> Option<Opening> {
+    game.positions(start).filter_map(|(_,_,_,after)| lookup(&after)).last()
+}
+
+/**
+ * Get the openings indexed by the hash of the board they reach.  This
+ * is constructed the first time it is needed.  Where openings reach
+ * the same position, the first is used.
+ */
+fn table() -> &'static HashMap<u64,Opening> {
+    static OPENINGS: OnceLock<HashMap<u64,Opening>> = OnceLock::new();
+    OPENINGS.get_or_init(|| {
+	let mut map = HashMap::new();
+	for (opening,board) in entries() {
+	    if let Some(b) = board {
+		map.entry(b.hash()).or_insert(opening);
+	    }
+	}
+	map
+    })
+}
+
+/**
+ * Read each entry of the opening table, along with the board reached
+ * by its moves (or nothing if they are not legal).
+ */
+fn entries() -> impl Iterator<Item=(Opening,Option<Board>)> {
+    TABLE.lines().filter(|l| !l.is_empty() && !l.starts_with('#')).map(|l| {
+	let mut fields = l.split('\t');
+	let code = fields.next().unwrap_or("");
+	let name = fields.next().unwrap_or("");
+	let moves = fields.next().unwrap_or("");
+	let board = moves.split_ascii_whitespace().try_fold(INITIAL,|b,s| {
+	    san::from_str(&b,s).ok().and_then(|m| m.apply(b))
+	});
+	(Opening{code,name},board)
+    })
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+
+#[cfg(test)]
+fn check(game: &str, expected: Option<&str>) {
+    let g = Game::from_str(game).unwrap();
+    assert_eq!(classify(&g,INITIAL).map(|o| o.to_string()).as_deref(), expected);
+}
+
+#[test]
+fn test_01() {
+    // Every entry in the table is legal and has a valid code
+    for (opening,board) in entries() {
+	assert!(board.is_some(), "{}", opening);
+	let c = opening.code.as_bytes();
+	assert!(c.len() == 3 && (b'A'..=b'E').contains(&c[0]) && c[1..].iter().all(u8::is_ascii_digit), "{}", opening);
+	assert!(!opening.name.is_empty());
+    }
+}
+
+#[test]
+fn test_02() {
+    check("e2-e4 e7-e5", Some("C20 King's Pawn Game"));
+    check("e2-e4 e7-e5\nNg1-f3 Nb8-c6\nBf1-b5", Some("C60 Ruy Lopez"));
+    check("e2-e4 e7-e5\nNg1-f3 Nb8-c6\nBf1-b5 a7-a6\nBb5xNc6", Some("C68 Ruy Lopez: Exchange Variation"));
+}
+
+#[test]
+fn test_03() {
+    // Moves beyond the table keep the last opening reached
+    check("e2-e4 c7-c5\nNg1-f3 d7-d6\nh2-h3 h7-h6", Some("B50 Sicilian Defense: Modern Variations"));
+    check("a2-a3", None);
+    check("", None);
+}
+
+#[test]
+fn test_04() {
+    // Transpositions into the Queen's Gambit Declined
+    check("d2-d4 Ng8-f6\nc2-c4 e7-e6\nNb1-c3 d7-d5", Some("D35 Queen's Gambit Declined: Normal Defense"));
+    check("c2-c4 e7-e6\nd2-d4 d7-d5", Some("D30 Queen's Gambit Declined"));
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+use super::board::Board;
+use super::error::Located;
+use super::fen;
+use super::muve::Move;
+use super::san;
+use super::search::{Analysis, Limits, Search};
+
+/**
+ * Opcodes whose operands are alternative moves in SAN, each played
+ * from the position (unlike those of "pv", which form a line).
+ */
+const MOVES: [&str;4] = ["bm", "am", "pm", "sm"];
+
+/**
+ * Opcodes whose operand is an integer.
+ */
+const NUMBERS: [&str;6] = ["acd", "acn", "ce", "dm", "hmv", "fmvn"];
+
+/**
+ * An operation of an EPD record, which is an opcode (e.g. "bm")
+ * followed by zero or more operands (e.g. "Nf3" or "Qd2").
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operation {
+    /**
+     * Opcode of the operation
+     */
+    pub opcode: String,
+    /**
+     * Operands of the operation, with any quotes removed
+     */
+    pub operands: Vec<String>
+}
+
+/**
+ * A record in Extended Position Description (EPD), which is a
+ * position given by the first four fields of FEN along with a list of
+ * operations describing it (e.g. the best move).  For example:
+ *
+ * r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5; id "Ruy Lopez";
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Epd {
+    /**
+     * Position described, whose move clocks are taken from the "hmv"
+     * and "fmvn" operations (if given)
+     */
+    pub board: Board,
+    /**
+     * Operations in the order given
+     */
+    pub operations: Vec<Operation>
+}
+
+/**
+ * Identifies what is wrong with an EPD record.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /** The position is invalid (i.e. why) */
+    Fen(fen::Error),
+    /** An opcode is malformed */
+    Opcode(String),
+    /** A string operand is missing its closing quote */
+    Quote,
+    /** An operation is not terminated by ";" (i.e. its opcode) */
+    Unterminated(String),
+    /** A move is not legal SAN for the position (i.e. opcode, move and why) */
+    Move(String,String,san::Error),
+    /** An operand should be an integer (i.e. opcode and operand) */
+    Number(String,String)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    ErrorKind::Fen(e) => write!(f,"invalid position ({})",e),
+	    ErrorKind::Opcode(s) => write!(f,"malformed opcode \"{}\"",s),
+	    ErrorKind::Quote => write!(f,"missing closing quote"),
+	    ErrorKind::Unterminated(s) => write!(f,"operation \"{}\" is not terminated by \";\"",s),
+	    ErrorKind::Move(o,s,e) => write!(f,"invalid move \"{}\" for {} ({})",s,o,e),
+	    ErrorKind::Number(o,s) => write!(f,"expected integer for {}, found \"{}\"",o,s)
+	}
+    }
+}
+
+/**
+ * Describes why a record in an EPD file is invalid, including where
+ * it was found.
+ */
+pub type Error = Located<ErrorKind>;
+
+impl Epd {
+    /**
+     * Construct a record for a given position with no operations.
+     */
+    pub fn new(board: Board) -> Epd {
+	Epd{board, operations: Vec::new()}
+    }
+
+    /**
+     * Get the operands of the operation with a given opcode (if
+     * there is one).
+     */
+    pub fn get(&self, opcode: &str) -> Option<&[String]> {
+	self.operations.iter().find(|o| o.opcode == opcode).map(|o| o.operands.as_slice())
+    }
+
+    /**
+     * Set the operands of the operation with a given opcode, which
+     * replaces the operation if there is one already.
+     */
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+	match self.operations.iter_mut().find(|o| o.opcode == opcode) {
+	    Some(o) => o.operands = operands,
+	    None => self.operations.push(Operation{opcode: opcode.to_string(), operands})
+	}
+    }
+
+    /**
+     * Get the identifier of this record (i.e. the "id" operation).
+     */
+    pub fn id(&self) -> Option<&str> {
+	self.get("id").and_then(|o| o.first()).map(|s| s.as_str())
+    }
+
+    /**
+     * Get the moves given by an operation (e.g. "bm").  For "pv",
+     * these form a line played from the position; otherwise, each is
+     * played from the position itself.
+     */
+    pub fn moves(&self, opcode: &str) -> Vec<Box<dyn Move>> {
+	let mut b = self.board;
+	let mut moves = Vec::new();
+	for s in self.get(opcode).unwrap_or(&[]) {
+	    match san::from_str(&b,s) {
+		Ok(m) => {
+		    if opcode == "pv" { b = m.apply(b).unwrap(); }
+		    moves.push(m);
+		}
+		Err(_) => break
+	    }
+	}
+	moves
+    }
+
+    /**
+     * Check whether a given move solves this record, meaning it is
+     * one of the best moves ("bm") if any are given, and is not one of
+     * the moves to avoid ("am").
+     */
+    pub fn is_solved_by(&self, m: &dyn Move) -> bool {
+	let same = |o: &dyn Move| o.from() == m.from() && o.to() == m.to() && o.promotion() == m.promotion();
+	let best = self.moves("bm");
+	(best.is_empty() || best.iter().any(|o| same(o.as_ref()))) && !self.moves("am").iter().any(|o| same(o.as_ref()))
+    }
+
+    /**
+     * Search the position of this record within some limits, to see
+     * whether the move found solves it.
+     */
+    pub fn attempt(&self, search: &mut Search, limits: Limits) -> (Analysis,bool) {
+	let a = search.run(&self.board,limits,|_| {});
+	let solved = a.pv.first().is_some_and(|m| self.is_solved_by(m.as_ref()));
+	(a,solved)
+    }
+}
+
+/**
+ * Records are written with the first four fields of FEN and then each
+ * operation terminated by ";".  Operands are quoted when they are not
+ * single words, or are given to "id" or a comment ("c0" to "c9").
+ */
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let fen = fen::to_string(&self.board);
+	let fields: Vec<&str> = fen.split(' ').take(4).collect();
+	write!(f,"{}",fields.join(" "))?;
+	for o in &self.operations {
+	    let quoted = o.opcode == "id" || (o.opcode.len() == 2 && o.opcode.starts_with('c')
+						 && o.opcode.as_bytes()[1].is_ascii_digit());
+	    write!(f," {}",o.opcode)?;
+	    for s in &o.operands {
+		if quoted || s.is_empty() || s.contains(|c: char| c.is_whitespace() || c == ';') {
+		    write!(f," \"{}\"",s)?;
+		} else {
+		    write!(f," {}",s)?;
+		}
+	    }
+	    write!(f,";")?;
+	}
+	Ok(())
+    }
+}
+
+/**
+ * Read a record in Extended Position Description.  The moves of
+ * "bm", "am", "pm", "sm" and "pv" must be legal SAN for the position,
+ * and the operands of "acd", "acn", "ce", "dm", "hmv" and "fmvn" must
+ * be integers.
+ */
+pub fn from_str(s: &str) -> Result<Epd,ErrorKind> {
+    // The first four fields give the position
+    let mut rest = s.trim();
+    let mut fields = Vec::new();
+    for _ in 0..4 {
+	let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+	fields.push(&rest[..end]);
+	rest = rest[end..].trim_start();
+    }
+    let board = fen::from_str(&fields.join(" ")).map_err(ErrorKind::Fen)?;
+    let mut epd = Epd{board, operations: parse_operations(rest)?};
+    for o in &epd.operations {
+	if NUMBERS.contains(&o.opcode.as_str()) {
+	    if let Some(s) = o.operands.iter().find(|s| s.parse::<i64>().is_err()) {
+		return Err(ErrorKind::Number(o.opcode.clone(),s.clone()));
+	    }
+	}
+    }
+    // Move clocks
+    if epd.get("hmv").is_some() || epd.get("fmvn").is_some() {
+	let clock = |op: &str, default: u16| epd.get(op).and_then(|o| o.first()).map(|s| s.to_string())
+	    .unwrap_or(default.to_string());
+	let fen = format!("{} {} {}",fields.join(" "),clock("hmv",0),clock("fmvn",1));
+	epd.board = fen::from_str(&fen).map_err(ErrorKind::Fen)?;
+    }
+    // Moves
+    for o in &epd.operations {
+	if o.opcode == "pv" || MOVES.contains(&o.opcode.as_str()) {
+	    let mut b = epd.board;
+	    for s in &o.operands {
+		let m = san::from_str(&b,s).map_err(|e| ErrorKind::Move(o.opcode.clone(),s.clone(),e))?;
+		if o.opcode == "pv" { b = m.apply(b).unwrap(); }
+	    }
+	}
+    }
+    Ok(epd)
+}
+
+/**
+ * Read every record in an EPD file, where each record is on its own
+ * line.  Blank lines are skipped.
+ */
+pub fn read(text: &str) -> Vec<Result<Epd,Error>> {
+    text.lines().enumerate()
+	.filter(|(_,l)| !l.trim().is_empty())
+	.map(|(i,l)| from_str(l).map_err(|kind| Error{line: i + 1, kind}))
+	.collect()
+}
+
+/**
+ * Parse the operations following the position, each of which is an
+ * opcode followed by its operands and terminated by ";".
+ */
+fn parse_operations(s: &str) -> Result<Vec<Operation>,ErrorKind> {
+    let mut operations = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+	let end = rest.find(|c: char| c.is_whitespace() || c == ';').unwrap_or(rest.len());
+	let opcode = &rest[..end];
+	let mut chars = opcode.chars();
+	if !chars.next().is_some_and(|c| c.is_ascii_alphabetic()) || opcode.len() > 15
+	    || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+	    return Err(ErrorKind::Opcode(opcode.to_string()));
+	}
+	rest = rest[end..].trim_start();
+	let mut operands = Vec::new();
+	loop {
+	    if let Some(r) = rest.strip_prefix(';') {
+		rest = r.trim_start();
+		break;
+	    } else if let Some(r) = rest.strip_prefix('"') {
+		let close = r.find('"').ok_or(ErrorKind::Quote)?;
+		operands.push(r[..close].to_string());
+		rest = r[close + 1..].trim_start();
+	    } else if rest.is_empty() {
+		return Err(ErrorKind::Unterminated(opcode.to_string()));
+	    } else {
+		let end = rest.find(|c: char| c.is_whitespace() || c == ';').unwrap_or(rest.len());
+		operands.push(rest[..end].to_string());
+		rest = rest[end..].trim_start();
+	    }
+	}
+	operations.push(Operation{opcode: opcode.to_string(), operands});
+    }
+    Ok(operations)
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::board::INITIAL;
+
+#[test]
+fn test_01() {
+    let e = from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4 d4; id \"start; 1\"; c0 \"The initial position\";").unwrap();
+    assert_eq!(e.board, INITIAL);
+    assert_eq!(e.id(), Some("start; 1"));
+    assert_eq!(e.get("bm").unwrap(), ["e4","d4"]);
+    assert_eq!(e.get("c0").unwrap(), ["The initial position"]);
+    assert_eq!(e.get("am"), None);
+    assert_eq!(e.moves("bm").len(), 2);
+    // Writing round trips
+    assert_eq!(e.to_string(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4 d4; id \"start; 1\"; c0 \"The initial position\";");
+    assert_eq!(from_str(&e.to_string()), Ok(e));
+}
+
+#[test]
+fn test_02() {
+    // Clocks and a line of moves
+    let e = from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - hmv 3; fmvn 12; pv e4 e5 Nf3; acd 8; ce -15;").unwrap();
+    assert_eq!(e.board.halfmove(), 3);
+    assert_eq!(e.board.fullmove(), 12);
+    assert_eq!(e.moves("pv").len(), 3);
+    // No operations
+    let e = from_str("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert!(e.operations.is_empty());
+    assert_eq!(e.to_string(), "4k3/8/8/8/8/8/8/4K3 b - -");
+    let mut e = e;
+    e.set("acd",vec!["4".to_string()]);
+    e.set("acd",vec!["5".to_string()]);
+    assert_eq!(e.to_string(), "4k3/8/8/8/8/8/8/4K3 b - - acd 5;");
+}
+
+#[test]
+fn test_03() {
+    let err = |s: &str| from_str(s).unwrap_err();
+    let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+    assert_eq!(err(&format!("{} bm e5;",start)), ErrorKind::Move("bm".to_string(),"e5".to_string(),san::Error::Illegal));
+    assert_eq!(err(&format!("{} am Nz3;",start)), ErrorKind::Move("am".to_string(),"Nz3".to_string(),san::Error::Syntax));
+    assert_eq!(err(&format!("{} pv e4 e4;",start)), ErrorKind::Move("pv".to_string(),"e4".to_string(),san::Error::Illegal));
+    assert_eq!(err(&format!("{} acd deep;",start)), ErrorKind::Number("acd".to_string(),"deep".to_string()));
+    assert_eq!(err(&format!("{} bm e4",start)), ErrorKind::Unterminated("bm".to_string()));
+    assert_eq!(err(&format!("{} id \"open;",start)), ErrorKind::Quote);
+    assert_eq!(err(&format!("{} 1bm e4;",start)), ErrorKind::Opcode("1bm".to_string()));
+    assert_eq!(err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -"), ErrorKind::Fen(fen::Error::Turn));
+    let records = read("\n4k3/8/8/8/8/8/8/4K3 w - - bm Kd1;\n4k3/8/8/8/8/8/8/4K3 w - - bm Kd3;\n");
+    assert!(records[0].is_ok());
+    assert_eq!(records[1].as_ref().unwrap_err().line, 3);
+}
+
+#[test]
+fn test_04() {
+    // Running the search against a record
+    let e = from_str("4k3/8/8/3q4/8/8/8/3RK3 w - - bm Rxd5; id \"queen\";").unwrap();
+    let (a,solved) = e.attempt(&mut Search::default(),Limits{depth: Some(2), ..Limits::default()});
+    assert!(solved);
+    assert_eq!(a.depth, 2);
+    let e = from_str("4k3/8/8/3q4/8/8/8/3RK3 w - - am Rxd5;").unwrap();
+    assert!(!e.attempt(&mut Search::default(),Limits{depth: Some(2), ..Limits::default()}).1);
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+/**
+ * Describes a problem found when reading something made up of lines
+ * (e.g. a PGN database), along with the line on which it was found.
+ * What kind of problem it is depends on what is being read.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Located<K> {
+    /**
+     * Line on which the problem was found (starting from 1)
+     */
+    pub line: usize,
+    /**
+     * What the problem was
+     */
+    pub kind: K
+}
+
+impl<K: fmt::Display> fmt::Display for Located<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f,"line {}: {}",self.line,self.kind)
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[test]
+fn test_01() {
+    let e = Located{line: 12, kind: "unexpected token"};
+    assert_eq!(e.to_string(), "line 12: unexpected token");
+}
@@ -0,0 +1,651 @@
+use std::fmt;
+use std::str::FromStr;
+use super::board::Board;
+use super::error::Located;
+use super::material::{index, kind_index, KINDS};
+use super::movegen::{offset, DIAGONALS, DIRECTIONS, KNIGHT, STRAIGHTS};
+use super::piece::*;
+use super::square::Square;
+
+/**
+ * Phase of a board with every piece (other than pawns) still on it,
+ * where knights and bishops count one, rooks two and queens four.
+ */
+const OPENING: i32 = 24;
+
+/**
+ * A term of the evaluation, which is scored separately for each
+ * player.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Term {
+    /** Value of the pieces on the board */
+    Material,
+    /** Value of the squares the pieces stand on */
+    Tables,
+    /** Number of squares the pieces can move to */
+    Mobility,
+    /** Pawns on the same file as another of their pawns */
+    Doubled,
+    /** Pawns with no pawns of their own on adjacent files */
+    Isolated,
+    /** Pawns with no opposing pawns in front of them or on adjacent files */
+    Passed,
+    /** Pawns sheltering the king, and attacks on the squares around it */
+    King
+}
+
+/**
+ * Every term of the evaluation, in the order they are listed.
+ */
+pub const TERMS: [Term;7] = [Term::Material, Term::Tables, Term::Mobility, Term::Doubled,
+			     Term::Isolated, Term::Passed, Term::King];
+
+/**
+ * Terms are written as short names (e.g. "material").
+ */
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let s = match self {
+	    Term::Material => "material",
+	    Term::Tables => "tables",
+	    Term::Mobility => "mobility",
+	    Term::Doubled => "doubled",
+	    Term::Isolated => "isolated",
+	    Term::Passed => "passed",
+	    Term::King => "king"
+	};
+	write!(f,"{}",s)
+    }
+}
+
+/**
+ * The weights used to evaluate a board, all in centipawns.  Arrays
+ * indexed by kind follow the order of KINDS (i.e. pawn first and king
+ * last).  Penalties (e.g. for doubled pawns) are negative weights.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Weights {
+    /**
+     * Value of each kind of piece
+     */
+    pub values: [i32;6],
+    /**
+     * Value of each kind of piece on each square, from white's
+     * perspective with the eighth rank first (as the board is
+     * normally drawn).  Black uses the same tables mirrored.
+     */
+    pub tables: [[i32;64];6],
+    /**
+     * Table used for the king instead when few pieces remain, which
+     * is blended with the normal table as pieces are exchanged
+     */
+    pub king_endgame: [i32;64],
+    /**
+     * Value of each square that each kind of piece can move to
+     */
+    pub mobility: [i32;6],
+    /**
+     * Value of each pawn on a file beyond the first
+     */
+    pub doubled: i32,
+    /**
+     * Value of each isolated pawn
+     */
+    pub isolated: i32,
+    /**
+     * Value of a passed pawn by the number of ranks it has advanced
+     */
+    pub passed: [i32;8],
+    /**
+     * Value of each pawn directly in front of the king (or in front
+     * of an adjacent square), when all pieces are on the board
+     */
+    pub shield: i32,
+    /**
+     * Value of each square around the king (including its own) which
+     * is attacked, when all pieces are on the board
+     */
+    pub attacks: i32
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+	Weights {
+	    values: [100, 320, 330, 500, 900, 0],
+	    tables: [PAWN_TABLE, KNIGHT_TABLE, BISHOP_TABLE, ROOK_TABLE, QUEEN_TABLE, KING_TABLE],
+	    king_endgame: KING_ENDGAME_TABLE,
+	    mobility: [0, 4, 5, 2, 1, 0],
+	    doubled: -15,
+	    isolated: -12,
+	    passed: [0, 5, 10, 20, 35, 60, 100, 0],
+	    shield: 10,
+	    attacks: -8
+	}
+    }
+}
+
+/**
+ * Weights are written in the format they are read from, which lists
+ * every weight (so the result can be edited to tune them).
+ */
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	writeln!(f,"# Values by kind: pawn, knight, bishop, rook, queen, king")?;
+	writeln!(f,"values = {}",join(&self.values))?;
+	writeln!(f,"mobility = {}",join(&self.mobility))?;
+	writeln!(f,"doubled = {}",self.doubled)?;
+	writeln!(f,"isolated = {}",self.isolated)?;
+	writeln!(f,"# Passed pawns by ranks advanced")?;
+	writeln!(f,"passed = {}",join(&self.passed))?;
+	writeln!(f,"shield = {}",self.shield)?;
+	writeln!(f,"attacks = {}",self.attacks)?;
+	writeln!(f,"# Piece-square tables from white's side, eighth rank first")?;
+	for (k,t) in KINDS.iter().zip(&self.tables).map(|(k,t)| (table_name(*k),t))
+	    .chain([("king_endgame",&self.king_endgame)]) {
+	    writeln!(f,"table.{} =",k)?;
+	    for row in t.chunks(8) {
+		let cells: Vec<String> = row.iter().map(|n| format!("{:4}",n)).collect();
+		writeln!(f,"   {}",cells.join(""))?;
+	    }
+	}
+	Ok(())
+    }
+}
+
+/**
+ * Identifies why weights could not be read.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /** Numbers were given before any weight was named */
+    Syntax,
+    /** A weight was named which does not exist */
+    Unknown(String),
+    /** Something other than a number was given */
+    Number(String),
+    /** A weight was given the wrong number of values (i.e. name, expected, found) */
+    Count(String,usize,usize)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    ErrorKind::Syntax => write!(f,"expected \"name = value\""),
+	    ErrorKind::Unknown(s) => write!(f,"unknown weight \"{}\"",s),
+	    ErrorKind::Number(s) => write!(f,"malformed number \"{}\"",s),
+	    ErrorKind::Count(s,e,n) => write!(f,"\"{}\" needs {} values, but {} given",s,e,n)
+	}
+    }
+}
+
+/**
+ * Describes why weights could not be read, including where the
+ * problem was found.
+ */
+pub type Error = Located<ErrorKind>;
+
+/**
+ * Read weights from a configuration file.  Each weight is given as a
+ * name followed by "=" and its values, which can continue onto the
+ * following lines (e.g. for tables).  Anything after "#" is ignored.
+ * Weights which are not given keep their default values.
+ */
+impl FromStr for Weights {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self,Self::Err> {
+	let mut weights = Weights::default();
+	// Weight being read (if any), with its line and values so far
+	let mut current: Option<(usize,String,Vec<i32>)> = None;
+	for (i,l) in s.lines().enumerate() {
+	    let line = i + 1;
+	    let l = l.split('#').next().unwrap_or("");
+	    let values = match l.split_once('=') {
+		Some((name,rest)) => {
+		    if let Some((n,name,vs)) = current.take() {
+			weights.set(n,&name,&vs)?;
+		    }
+		    current = Some((line,name.trim().to_string(),Vec::new()));
+		    rest
+		}
+		None if l.trim().is_empty() => continue,
+		None if current.is_none() => return Err(Error{line, kind: ErrorKind::Syntax}),
+		None => l
+	    };
+	    let vs = &mut current.as_mut().unwrap().2;
+	    for t in values.split_ascii_whitespace() {
+		let n = t.parse().map_err(|_| Error{line, kind: ErrorKind::Number(t.to_string())})?;
+		vs.push(n);
+	    }
+	}
+	if let Some((n,name,vs)) = current {
+	    weights.set(n,&name,&vs)?;
+	}
+	Ok(weights)
+    }
+}
+
+impl Weights {
+    /**
+     * Set the weight with a given name (read on a given line) to some
+     * values.
+     */
+    fn set(&mut self, line: usize, name: &str, values: &[i32]) -> Result<(),Error> {
+	let kind = KINDS.iter().position(|&k| name.strip_prefix("table.") == Some(table_name(k)));
+	let target: &mut [i32] = match name {
+	    "values" => &mut self.values,
+	    "mobility" => &mut self.mobility,
+	    "doubled" => std::slice::from_mut(&mut self.doubled),
+	    "isolated" => std::slice::from_mut(&mut self.isolated),
+	    "passed" => &mut self.passed,
+	    "shield" => std::slice::from_mut(&mut self.shield),
+	    "attacks" => std::slice::from_mut(&mut self.attacks),
+	    "table.king_endgame" => &mut self.king_endgame,
+	    _ => match kind {
+		Some(k) => &mut self.tables[k],
+		None => return Err(Error{line, kind: ErrorKind::Unknown(name.to_string())})
+	    }
+	};
+	if target.len() != values.len() {
+	    return Err(Error{line, kind: ErrorKind::Count(name.to_string(),target.len(),values.len())});
+	}
+	target.copy_from_slice(values);
+	Ok(())
+    }
+}
+
+/**
+ * The evaluation of a board broken down into its terms, each scored
+ * separately for each player.
+ */
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Breakdown {
+    /**
+     * Scores indexed by term (see TERMS) and player (white first)
+     */
+    scores: [[i32;2];7]
+}
+
+impl Breakdown {
+    /**
+     * Get the score of a given term for a given player.
+     */
+    pub fn get(&self, term: Term, player: Player) -> i32 {
+	self.scores[term as usize][index(player)]
+    }
+
+    /**
+     * Get the score of a given term for white less that for black.
+     */
+    pub fn term(&self, term: Term) -> i32 {
+	self.get(term,Player::White) - self.get(term,Player::Black)
+    }
+
+    /**
+     * Get the overall score, which is positive when white is better
+     * and negative when black is better.
+     */
+    pub fn total(&self) -> i32 {
+	TERMS.iter().map(|&t| self.term(t)).sum()
+    }
+
+    fn add(&mut self, term: Term, player: Player, score: i32) {
+	self.scores[term as usize][index(player)] += score;
+    }
+}
+
+/**
+ * Evaluate a board term by term using given weights.
+ */
+pub fn breakdown(board: &Board, weights: &Weights) -> Breakdown {
+    let mut b = Breakdown::default();
+    // Count the pawns on each file for each player
+    let mut files = [[0i32;8];2];
+    for (s,p) in board.pieces() {
+	if p.kind == Kind::Pawn {
+	    files[index(p.player)][s.column() as usize] += 1;
+	}
+    }
+    let phase = phase(board);
+    for (s,p) in board.pieces() {
+	let k = kind_index(p.kind).unwrap();
+	let i = table_index(p.player,s);
+	b.add(Term::Material,p.player,weights.values[k]);
+	let placement = if p.kind == Kind::King {
+	    blend(weights.tables[k][i],weights.king_endgame[i],phase)
+	} else {
+	    weights.tables[k][i]
+	};
+	b.add(Term::Tables,p.player,placement);
+	b.add(Term::Mobility,p.player,weights.mobility[k] * mobility(board,p,s));
+	if p.kind == Kind::Pawn {
+	    let col = s.column() as usize;
+	    let own = &files[index(p.player)];
+	    if (col == 0 || own[col - 1] == 0) && (col == 7 || own[col + 1] == 0) {
+		b.add(Term::Isolated,p.player,weights.isolated);
+	    }
+	    if is_passed(board,p,s) {
+		b.add(Term::Passed,p.player,weights.passed[advanced(p.player,s)]);
+	    }
+	}
+    }
+    for player in [Player::White, Player::Black] {
+	let doubled: i32 = files[index(player)].iter().map(|&n| (n - 1).max(0)).sum();
+	b.add(Term::Doubled,player,weights.doubled * doubled);
+	let safety = king_safety(board,player,weights);
+	b.add(Term::King,player,safety * phase / OPENING);
+    }
+    b
+}
+
+/**
+ * Evaluate a board using given weights, from the perspective of the
+ * player to move (i.e. positive when they are better).
+ */
+pub fn evaluate(board: &Board, weights: &Weights) -> i32 {
+    let total = breakdown(board,weights).total();
+    match board.turn() {
+	Player::White => total,
+	Player::Black => -total
+    }
+}
+
+/**
+ * Determine how many pieces (other than pawns and kings) remain on a
+ * given board, where OPENING means all of them and zero means none.
+ */
+fn phase(board: &Board) -> i32 {
+    let n: i32 = board.pieces().map(|(_,p)| match p.kind {
+	Kind::Knight | Kind::Bishop => 1,
+	Kind::Rook => 2,
+	Kind::Queen => 4,
+	_ => 0
+    }).sum();
+    n.min(OPENING)
+}
+
+/**
+ * Blend a score for when all pieces are on the board with one for
+ * when none remain, according to a given phase.
+ */
+fn blend(opening: i32, endgame: i32, phase: i32) -> i32 {
+    (opening * phase + endgame * (OPENING - phase)) / OPENING
+}
+
+/**
+ * Count the squares a given piece (other than a pawn or king) can
+ * move to, ignoring whether its own king would be left in check.
+ */
+fn mobility(board: &Board, piece: Piece, from: Square) -> i32 {
+    let (directions,slides): (&[(i8,i8)],bool) = match piece.kind {
+	Kind::Knight => (&KNIGHT,false),
+	Kind::Bishop => (&DIAGONALS,true),
+	Kind::Rook => (&STRAIGHTS,true),
+	Kind::Queen => (&DIRECTIONS,true),
+	_ => return 0
+    };
+    let mut n = 0;
+    for &(dc,dr) in directions {
+	let mut s = from;
+	while let Some(to) = offset(s,dc,dr) {
+	    let t = board.get(to);
+	    if t == BLANK || t.player != piece.player { n += 1; }
+	    if t != BLANK || !slides { break; }
+	    s = to;
+	}
+    }
+    n
+}
+
+/**
+ * Check whether a pawn on a given square is passed (i.e. no opposing
+ * pawn can block or capture it on its way to promotion).  The rear
+ * pawn of a doubled pair is not passed, since it is blocked.
+ */
+fn is_passed(board: &Board, pawn: Piece, s: Square) -> bool {
+    let enemy = Piece{player: pawn.player.flip(), kind: Kind::Pawn};
+    let col = s.column() as i8;
+    let rows: Vec<u8> = match pawn.player {
+	Player::White => (s.row() + 1..8).collect(),
+	Player::Black => (0..s.row()).collect()
+    };
+    !rows.iter().any(|&r| {
+	board.get(Square::new(col as u8,r)) == pawn ||
+	    (col - 1..=col + 1).filter(|c| (0..8).contains(c)).any(|c| board.get(Square::new(c as u8,r)) == enemy)
+    })
+}
+
+/**
+ * Score the safety of a given player's king, before it is scaled by
+ * the phase.
+ */
+fn king_safety(board: &Board, player: Player, weights: &Weights) -> i32 {
+    let king = match board.king(player) {
+	Some(k) => k,
+	None => return 0
+    };
+    let pawn = Piece{player, kind: Kind::Pawn};
+    let forward = if player == Player::White { 1 } else { -1 };
+    let mut score = 0;
+    for dc in -1..=1 {
+	for dr in 1..=2 {
+	    if offset(king,dc,dr * forward).is_some_and(|s| board.get(s) == pawn) {
+		score += weights.shield;
+	    }
+	}
+	for dr in -1..=1 {
+	    if let Some(s) = offset(king,dc,dr) {
+		if board.is_attacked(s,player.flip()) {
+		    score += weights.attacks;
+		}
+	    }
+	}
+    }
+    score
+}
+
+/**
+ * Determine how many ranks a piece of a given player on a given
+ * square has advanced from its own back rank.
+ */
+fn advanced(player: Player, s: Square) -> usize {
+    match player {
+	Player::White => s.row() as usize,
+	Player::Black => 7 - s.row() as usize
+    }
+}
+
+/**
+ * Determine the index into a table (which is written from white's
+ * side with the eighth rank first) for a piece of a given player.
+ */
+fn table_index(player: Player, s: Square) -> usize {
+    (7 - advanced(player,s)) * 8 + s.column() as usize
+}
+
+/**
+ * Get the name of the table for a kind of piece.
+ */
+fn table_name(kind: Kind) -> &'static str {
+    match kind {
+	Kind::Pawn => "pawn",
+	Kind::Knight => "knight",
+	Kind::Bishop => "bishop",
+	Kind::Rook => "rook",
+	Kind::Queen => "queen",
+	_ => "king"
+    }
+}
+
+fn join(values: &[i32]) -> String {
+    values.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+// ======================================================
+// Tables
+// ======================================================
+
+const PAWN_TABLE: [i32;64] = [
+      0,  0,  0,  0,  0,  0,  0,  0,
+     50, 50, 50, 50, 50, 50, 50, 50,
+     10, 10, 20, 30, 30, 20, 10, 10,
+      5,  5, 10, 25, 25, 10,  5,  5,
+      0,  0,  0, 20, 20,  0,  0,  0,
+      5, -5,-10,  0,  0,-10, -5,  5,
+      5, 10, 10,-20,-20, 10, 10,  5,
+      0,  0,  0,  0,  0,  0,  0,  0];
+
+const KNIGHT_TABLE: [i32;64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50];
+
+const BISHOP_TABLE: [i32;64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20];
+
+const ROOK_TABLE: [i32;64] = [
+      0,  0,  0,  0,  0,  0,  0,  0,
+      5, 10, 10, 10, 10, 10, 10,  5,
+     -5,  0,  0,  0,  0,  0,  0, -5,
+     -5,  0,  0,  0,  0,  0,  0, -5,
+     -5,  0,  0,  0,  0,  0,  0, -5,
+     -5,  0,  0,  0,  0,  0,  0, -5,
+     -5,  0,  0,  0,  0,  0,  0, -5,
+      0,  0,  0,  5,  5,  0,  0,  0];
+
+const QUEEN_TABLE: [i32;64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20];
+
+const KING_TABLE: [i32;64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20];
+
+const KING_ENDGAME_TABLE: [i32;64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50];
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::board::INITIAL;
+#[cfg(test)]
+use super::fen;
+#[cfg(test)]
+use super::testing;
+
+#[cfg(test)]
+fn check(fen: &str) -> Breakdown {
+    breakdown(&fen::from_str(fen).unwrap(),&Weights::default())
+}
+
+#[test]
+fn test_01() {
+    // The initial board is balanced
+    let b = breakdown(&INITIAL,&Weights::default());
+    assert_eq!(b.total(), 0);
+    assert_eq!(b.get(Term::Material,Player::White), 4000);
+    assert_eq!(b.get(Term::Mobility,Player::White), 4 * 4);
+    assert_eq!(b.get(Term::Doubled,Player::Black), 0);
+    assert_eq!(evaluate(&INITIAL,&Weights::default()), 0);
+}
+
+#[test]
+fn test_02() {
+    // Mirroring a board negates its evaluation
+    for b in testing::random_boards(42,10,40) {
+	let f = fen::to_string(&b);
+	let mut fields: Vec<String> = f.split(' ').map(|s| s.to_string()).collect();
+	let rows: Vec<String> = fields[0].split('/').rev().map(|r| {
+	    r.chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect()
+	}).collect();
+	fields[0] = rows.join("/");
+	fields[1] = if fields[1] == "w" { "b".to_string() } else { "w".to_string() };
+	fields[2] = "-".to_string();
+	fields[3] = "-".to_string();
+	let m = fen::from_str(&fields.join(" ")).unwrap();
+	assert_eq!(breakdown(&b,&Weights::default()).total(), -breakdown(&m,&Weights::default()).total(), "{}", f);
+	assert_eq!(evaluate(&b,&Weights::default()), evaluate(&m,&Weights::default()), "{}", f);
+    }
+}
+
+#[test]
+fn test_03() {
+    // Pawn structure: doubled and isolated pawns on the c-file (of
+    // which the front one is passed), and a passed pawn on the h-file
+    let b = check("4k3/p7/8/8/7P/2P5/2P5/4K3 w - - 0 1");
+    assert_eq!(b.get(Term::Doubled,Player::White), -15);
+    assert_eq!(b.get(Term::Isolated,Player::White), -36);
+    assert_eq!(b.get(Term::Passed,Player::White), 20 + 10);
+    assert_eq!(b.get(Term::Passed,Player::Black), 5);
+    // Blocked pawns are not passed
+    let b = check("4k3/8/8/3p4/3P4/8/8/4K3 w - - 0 1");
+    assert_eq!(b.get(Term::Passed,Player::White), 0);
+    assert_eq!(b.get(Term::Passed,Player::Black), 0);
+}
+
+#[test]
+fn test_04() {
+    // A sheltered king is safer than an exposed one
+    let sheltered = check("rnbqk2r/pppppppp/8/8/8/8/5PPP/RNBQ1RK1 w kq - 0 1");
+    let exposed = check("rnbqk2r/pppppppp/8/8/8/8/8/RNBQ1RK1 w kq - 0 1");
+    assert!(sheltered.get(Term::King,Player::White) > exposed.get(Term::King,Player::White));
+    // King safety does not matter without pieces
+    assert_eq!(check("4k3/8/8/8/8/8/8/4K3 w - - 0 1").get(Term::King,Player::White), 0);
+}
+
+#[test]
+fn test_05() {
+    // Weights round trip through their configuration format
+    let w = Weights::default();
+    assert_eq!(Weights::from_str(&w.to_string()), Ok(w));
+    let w = Weights::from_str("# Tuned\nvalues = 100 300 300 500 950 0\ndoubled = -20 # more\n").unwrap();
+    assert_eq!(w.values[4], 950);
+    assert_eq!(w.doubled, -20);
+    assert_eq!(w.isolated, Weights::default().isolated);
+    let rows: Vec<String> = (0..8).map(|r| (0..8).map(|c| (r * 8 + c).to_string()).collect::<Vec<_>>().join(" ")).collect();
+    let w = Weights::from_str(&format!("table.knight =\n{}\n",rows.join("\n"))).unwrap();
+    assert_eq!(w.tables[1][63], 63);
+}
+
+#[test]
+fn test_06() {
+    let err = |s: &str| Weights::from_str(s).unwrap_err().to_string();
+    assert_eq!(err("10 20"), "line 1: expected \"name = value\"");
+    assert_eq!(err("doubled = 1\nfoo = 2"), "line 2: unknown weight \"foo\"");
+    assert_eq!(err("doubled = x"), "line 1: malformed number \"x\"");
+    assert_eq!(err("\npassed = 1 2"), "line 2: \"passed\" needs 8 values, but 2 given");
+}
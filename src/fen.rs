@@ -0,0 +1,294 @@
+use std::fmt;
+use super::board::*;
+use super::piece::*;
+use super::square;
+use super::square::Square;
+
+/**
+ * The starting position in Forsyth-Edwards Notation (FEN).
+ */
+pub const STARTING: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/**
+ * Identifies which part of a FEN string is invalid.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** Wrong number of space-separated fields */
+    Fields,
+    /** Piece placement is malformed (e.g. a rank has nine squares) */
+    Placement,
+    /** Pieces are not legally placed (e.g. two white kings) */
+    Pieces,
+    /** Active colour is not "w" or "b" */
+    Turn,
+    /** Castling availability is malformed */
+    Castling,
+    /** En passant square is malformed or impossible */
+    EnPassant,
+    /** Halfmove or fullmove clock is not a number */
+    Clock
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let msg = match self {
+	    Error::Fields => "expected four to six fields",
+	    Error::Placement => "invalid piece placement",
+	    Error::Pieces => "invalid pieces (need one king each, no pawns on first or last rank)",
+	    Error::Turn => "invalid active colour",
+	    Error::Castling => "invalid castling availability",
+	    Error::EnPassant => "invalid en passant square",
+	    Error::Clock => "invalid move clock"
+	};
+	write!(f,"{}",msg)
+    }
+}
+
+/**
+ * Parse a board from a string in Forsyth-Edwards Notation (e.g.
+ * "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").  The
+ * move clocks may be omitted (as in EPD), in which case they default
+ * to 0 and 1 respectively.
+ */
+pub fn from_str(s: &str) -> Result<Board,Error> {
+    let fields : Vec<&str> = s.split_ascii_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+	return Err(Error::Fields);
+    }
+    let squares = parse_placement(fields[0])?;
+    let turn = match fields[1] {
+	"w" => Player::White,
+	"b" => Player::Black,
+	_ => return Err(Error::Turn)
+    };
+    let castling = parse_castling(fields[2])?;
+    let en_passant = parse_en_passant(fields[3],turn)?;
+    let halfmove = match fields.get(4) {
+	Some(f) => f.parse::<u16>().map_err(|_| Error::Clock)?,
+	None => 0
+    };
+    let fullmove = match fields.get(5) {
+	Some(f) => f.parse::<u16>().map_err(|_| Error::Clock)?,
+	None => 1
+    };
+    if fullmove == 0 {
+	return Err(Error::Clock);
+    }
+    Ok(Board::new(squares,turn,castling,en_passant,halfmove,fullmove))
+}
+
+/**
+ * Parse the piece placement field, which lists the ranks from eight
+ * down to one, separated by "/".  Within a rank, digits give the
+ * number of consecutive blank squares.
+ */
+fn parse_placement(s: &str) -> Result<[Piece;64],Error> {
+    let mut squares = [BLANK;64];
+    let ranks : Vec<&str> = s.split('/').collect();
+    if ranks.len() != 8 {
+	return Err(Error::Placement);
+    }
+    for (i,rank) in ranks.iter().enumerate() {
+	let row = 7 - i;
+	let mut col = 0;
+	for c in rank.chars() {
+	    if let Some(n) = c.to_digit(10) {
+		if n == 0 || n > 8 { return Err(Error::Placement); }
+		col += n as usize;
+	    } else {
+		if col >= 8 { return Err(Error::Placement); }
+		squares[row * 8 + col] = parse_piece(c).ok_or(Error::Placement)?;
+		col += 1;
+	    }
+	    if col > 8 { return Err(Error::Placement); }
+	}
+	if col != 8 {
+	    return Err(Error::Placement);
+	}
+    }
+    // Sanity check pieces
+    let count = |p: Piece| squares.iter().filter(|&&q| q == p).count();
+    if count(WHITE_KING) != 1 || count(BLACK_KING) != 1 {
+	return Err(Error::Pieces);
+    }
+    for col in 0..8 {
+	for row in [0,7] {
+	    if squares[row * 8 + col].kind == Kind::Pawn {
+		return Err(Error::Pieces);
+	    }
+	}
+    }
+    Ok(squares)
+}
+
+/**
+ * Parse a piece written as a single letter, where white's pieces are
+ * uppercase and black's are lowercase.
+ */
+fn parse_piece(c: char) -> Option<Piece> {
+    let player = if c.is_ascii_uppercase() { Player::White } else { Player::Black };
+    let kind = match c.to_ascii_uppercase() {
+	'P' => Kind::Pawn,
+	'N' => Kind::Knight,
+	'B' => Kind::Bishop,
+	'R' => Kind::Rook,
+	'Q' => Kind::Queen,
+	'K' => Kind::King,
+	_ => return None
+    };
+    Some(Piece{player,kind})
+}
+
+/**
+ * Parse the castling availability field (e.g. "KQkq" or "-").
+ */
+fn parse_castling(s: &str) -> Result<u8,Error> {
+    if s == "-" {
+	return Ok(0);
+    }
+    let mut rights = 0;
+    for c in s.chars() {
+	let right = match c {
+	    'K' => WHITE_KINGSIDE,
+	    'Q' => WHITE_QUEENSIDE,
+	    'k' => BLACK_KINGSIDE,
+	    'q' => BLACK_QUEENSIDE,
+	    _ => return Err(Error::Castling)
+	};
+	if rights & right != 0 {
+	    return Err(Error::Castling);
+	}
+	rights |= right;
+    }
+    Ok(rights)
+}
+
+/**
+ * Parse the en passant field (e.g. "e3" or "-").  The square must be
+ * one which the opponent's pawn could have just skipped over.
+ */
+fn parse_en_passant(s: &str, turn: Player) -> Result<Option<Square>,Error> {
+    if s == "-" {
+	return Ok(None);
+    }
+    if s.len() != 2 {
+	return Err(Error::EnPassant);
+    }
+    let sq = square::from_str(s).map_err(|_| Error::EnPassant)?;
+    let row = match turn { Player::White => 5, Player::Black => 2 };
+    if sq.row() != row {
+	return Err(Error::EnPassant);
+    }
+    Ok(Some(sq))
+}
+
+/**
+ * Write a board in Forsyth-Edwards Notation.
+ */
+pub fn to_string(board: &Board) -> String {
+    let mut out = String::new();
+    // Piece placement
+    for row in (0..8).rev() {
+	let mut blanks = 0;
+	for col in 0..8 {
+	    let p = board.get(Square::new(col,row));
+	    if p == BLANK {
+		blanks += 1;
+	    } else {
+		if blanks > 0 {
+		    out.push_str(&blanks.to_string());
+		    blanks = 0;
+		}
+		out.push_str(&p.to_string());
+	    }
+	}
+	if blanks > 0 {
+	    out.push_str(&blanks.to_string());
+	}
+	if row > 0 {
+	    out.push('/');
+	}
+    }
+    // Active colour
+    out.push_str(match board.turn() { Player::White => " w ", Player::Black => " b " });
+    // Castling availability
+    let rights = [(WHITE_KINGSIDE,'K'),(WHITE_QUEENSIDE,'Q'),(BLACK_KINGSIDE,'k'),(BLACK_QUEENSIDE,'q')];
+    if board.castling() == 0 {
+	out.push('-');
+    }
+    for (right,c) in rights {
+	if board.castling() & right != 0 {
+	    out.push(c);
+	}
+    }
+    // En passant and clocks
+    match board.en_passant() {
+	Some(s) => out.push_str(&format!(" {}",s)),
+	None => out.push_str(" -")
+    }
+    out.push_str(&format!(" {} {}",board.halfmove(),board.fullmove()));
+    out
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::muve;
+
+#[cfg(test)]
+use super::testing::random_boards;
+
+#[test]
+fn test_01() {
+    assert_eq!(from_str(STARTING).unwrap(), INITIAL);
+    assert_eq!(to_string(&INITIAL), STARTING);
+}
+
+#[test]
+fn test_02() {
+    // Position after 1.e4, including en passant square
+    let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+    let m = muve::from_str("e2-e4",Player::White).unwrap();
+    let b = m.apply(INITIAL).unwrap();
+    assert_eq!(to_string(&b), fen);
+    assert_eq!(from_str(fen).unwrap(), b);
+}
+
+#[test]
+fn test_03() {
+    // Clocks are optional
+    let b = from_str("4k3/8/8/8/8/8/8/4K2R w K -").unwrap();
+    assert_eq!(b.castling(), WHITE_KINGSIDE);
+    assert_eq!(b.get(Square::new(7,0)), WHITE_ROOK);
+    assert_eq!(to_string(&b), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+}
+
+#[test]
+fn test_04() {
+    assert_eq!(from_str(""), Err(Error::Fields));
+    assert_eq!(from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"), Err(Error::Placement));
+    assert_eq!(from_str("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), Err(Error::Placement));
+    assert_eq!(from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1"), Err(Error::Placement));
+    assert_eq!(from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1"), Err(Error::Placement));
+}
+
+#[test]
+fn test_05() {
+    assert_eq!(from_str("8/8/8/8/8/8/8/4K3 w - - 0 1"), Err(Error::Pieces));
+    assert_eq!(from_str("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"), Err(Error::Pieces));
+    assert_eq!(from_str("4k3/8/8/8/8/8/8/4K3 x - - 0 1"), Err(Error::Turn));
+    assert_eq!(from_str("4k3/8/8/8/8/8/8/4K3 w KK - 0 1"), Err(Error::Castling));
+    assert_eq!(from_str("4k3/8/8/8/8/8/8/4K3 w - e3 0 1"), Err(Error::EnPassant));
+    assert_eq!(from_str("4k3/8/8/8/8/8/8/4K3 w - - x 1"), Err(Error::Clock));
+}
+
+#[test]
+fn test_06() {
+    // Boards reached in random games round-trip through FEN
+    for board in random_boards(3,10,120) {
+	assert_eq!(from_str(&to_string(&board)), Ok(board));
+    }
+}
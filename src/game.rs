@@ -6,49 +6,248 @@ use super::muve;
 use super::muve::Move;
 use super::piece::Player;
 use super::board::*;
+use super::movegen;
 
+/**
+ * Represents a game of chess as the sequence of moves made.
+ */
 pub struct Game {
     // Sequence of moves begginning with White's and alternating
     // between White and Black.
     moves: Vec<Box<dyn Move>>,
+    // Result given at the end of the game (if any)
+    result: Option<Outcome>
+}
+
+/**
+ * The outcome of a game, as written at the end of a game in PGN.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /** White checkmated black */
+    WhiteWins,
+    /** Black checkmated white */
+    BlackWins,
+    /** The game ended in stalemate */
+    Draw,
+    /** The game has not finished */
+    Unfinished
+}
+
+impl Outcome {
+    /**
+     * Determine the outcome of a game which reached a given board.
+     */
+    pub fn of(board: &Board) -> Outcome {
+	if movegen::is_checkmate(board) {
+	    match board.turn() {
+		Player::White => Outcome::BlackWins,
+		Player::Black => Outcome::WhiteWins
+	    }
+	} else if movegen::is_stalemate(board) {
+	    Outcome::Draw
+	} else {
+	    Outcome::Unfinished
+	}
+    }
 }
 
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Outcome::WhiteWins => write!(f,"1-0"),
+	    Outcome::BlackWins => write!(f,"0-1"),
+	    Outcome::Draw => write!(f,"1/2-1/2"),
+	    Outcome::Unfinished => write!(f,"*")
+	}
+    }
+}
+
+/**
+ * Identifies what is wrong with a game which cannot be parsed.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /** A move is malformed (i.e. why) */
+    Move(muve::Error),
+    /** A move number is malformed (e.g. "12..") */
+    Number,
+    /** A move number does not match the move it precedes (i.e. expected, then found) */
+    MoveNumber(usize,usize),
+    /** A move number for white ("12.") precedes black's move */
+    WhiteNumber,
+    /** A move number for black ("12...") precedes white's move */
+    BlackNumber,
+    /** A move number is not followed by a move */
+    MissingMove,
+    /** Something follows the result of the game */
+    AfterResult
+}
+
+/**
+ * Describes why a game cannot be parsed, including where the problem
+ * was found.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /**
+     * Line on which the problem was found (starting from 1)
+     */
+    pub line: usize,
+    /**
+     * Token at which the problem was found
+     */
+    pub token: String,
+    /**
+     * What the problem is
+     */
+    pub kind: ErrorKind
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f,"line {}: ",self.line)?;
+	match &self.kind {
+	    ErrorKind::Move(e) => write!(f,"invalid move \"{}\" ({})",self.token,e),
+	    ErrorKind::Number => write!(f,"malformed move number \"{}\"",self.token),
+	    ErrorKind::MoveNumber(expected,found) => {
+		write!(f,"move number {} found where {} expected (\"{}\")",found,expected,self.token)
+	    }
+	    ErrorKind::WhiteNumber => write!(f,"white's move number precedes black's move (\"{}\")",self.token),
+	    ErrorKind::BlackNumber => write!(f,"black's move number precedes white's move (\"{}\")",self.token),
+	    ErrorKind::MissingMove => write!(f,"move number not followed by a move (\"{}\")",self.token),
+	    ErrorKind::AfterResult => write!(f,"unexpected \"{}\" after result",self.token)
+	}
+    }
+}
+
+/**
+ * Parse a game given as a sequence of moves in long algebraic
+ * notation, separated by any whitespace (including line breaks).
+ * Moves may be preceded by move numbers, written as "12." before
+ * white's move or "12..." before black's (with or without a space
+ * before the move).  These are checked against the moves they
+ * precede.  The game may end with its result (e.g. "1-0").
+ */
 impl FromStr for Game {
-    type Err = (); // for now
+    type Err = ParseError;
     
     fn from_str(s: &str) -> Result<Self,Self::Err> {
 	let mut ms = Vec::<Box<dyn Move>>::new();
+	let mut result = None;
+	// Move number waiting for its move (if any)
+	let mut number : Option<(usize,String)> = None;
 	// Read line-by-line
-	for l in s.lines() {
-            // Split moves
-            let v: Vec<&str> = l.split_ascii_whitespace().collect();
-            //
-            match v.len() {
-		1 => {
-                    // Append white's move
-                    ms.push(muve::from_str(v[0],Player::White)?);
+	for (i,l) in s.lines().enumerate() {
+	    let error = |token: &str, kind: ErrorKind| ParseError{line: i + 1, token: token.to_string(), kind};
+            // Split moves, where a separate "e.p." belongs to the
+            // move before it (e.g. "e5xd6 e.p.")
+	    let mut v = Vec::<String>::new();
+	    for t in l.split_ascii_whitespace() {
+		match v.last_mut() {
+		    Some(m) if t.starts_with("e.p.") => m.push_str(t),
+		    _ => v.push(t.to_string())
 		}
-		2 => {
-                    // Append white's move
-                    ms.push(muve::from_str(v[0],Player::White)?);
-                    // Append black's move
-                    ms.push(muve::from_str(v[1],Player::Black)?);
+	    }
+	    for token in &v {
+		if result.is_some() {
+		    return Err(error(token,ErrorKind::AfterResult));
 		}
-		_ => return Err(()),
-            }
+		// Check for result
+		if let Some(r) = parse_result(token) {
+		    if let Some((_,n)) = &number {
+			return Err(error(n,ErrorKind::MissingMove));
+		    }
+		    result = Some(r);
+		    continue;
+		}
+		// Check for move number
+		let (prefix,rest) = split_number(token);
+		if !prefix.is_empty() {
+		    if let Some((_,n)) = &number {
+			return Err(error(n,ErrorKind::MissingMove));
+		    }
+		    check_number(prefix,ms.len()).map_err(|k| error(token,k))?;
+		    number = Some((ms.len(),prefix.to_string()));
+		    if rest.is_empty() { continue; }
+		}
+		// Parse move for the player whose turn it is
+		let player = if ms.len().is_multiple_of(2) { Player::White } else { Player::Black };
+		let m = muve::from_str(rest,player).map_err(|e| error(rest,ErrorKind::Move(e)))?;
+		ms.push(m);
+		number = None;
+	    }
+	}
+	if let Some((_,n)) = number {
+	    let line = s.lines().count();
+	    return Err(ParseError{line, token: n, kind: ErrorKind::MissingMove});
 	}
-	// Dummy for now
-	Ok(Game { moves: ms })
+	Ok(Game { moves: ms, result })
+    }
+}
+
+/**
+ * Parse the result written at the end of a game.
+ */
+fn parse_result(token: &str) -> Option<Outcome> {
+    match token {
+	"1-0" => Some(Outcome::WhiteWins),
+	"0-1" => Some(Outcome::BlackWins),
+	"1/2-1/2" => Some(Outcome::Draw),
+	"*" => Some(Outcome::Unfinished),
+	_ => None
+    }
+}
+
+/**
+ * Split a token into the move number it begins with (e.g. "12." or
+ * "12...") and the remainder.  If it does not begin with a move
+ * number, then the prefix is empty.
+ */
+fn split_number(token: &str) -> (&str,&str) {
+    let digits = token.bytes().take_while(|b| b.is_ascii_digit()).count();
+    let dots = token[digits..].bytes().take_while(|&b| b == b'.').count();
+    if digits > 0 && dots > 0 {
+	token.split_at(digits + dots)
+    } else {
+	("",token)
+    }
+}
+
+/**
+ * Check a move number (e.g. "12." or "12...") is consistent with
+ * the ply of the move it precedes (starting from 0).
+ */
+fn check_number(prefix: &str, ply: usize) -> Result<(),ErrorKind> {
+    let digits = prefix.trim_end_matches('.');
+    let dots = prefix.len() - digits.len();
+    // Treat numbers too large to parse as mismatched
+    let n = digits.parse::<usize>().unwrap_or(usize::MAX);
+    let expected = (ply / 2) + 1;
+    match dots {
+	1 if ply % 2 == 1 => Err(ErrorKind::WhiteNumber),
+	3 if ply.is_multiple_of(2) => Err(ErrorKind::BlackNumber),
+	1 | 3 if n != expected => Err(ErrorKind::MoveNumber(expected,n)),
+	1 | 3 => Ok(()),
+	_ => Err(ErrorKind::Number)
     }
 }
 
 impl Game {
+    /**
+     * Construct a game from the moves made and the result given at
+     * the end (if any).
+     */
+    pub fn new(moves: Vec<Box<dyn Move>>, result: Option<Outcome>) -> Game {
+	Game{moves, result}
+    }
+
     /**
      * Apply this game to a given board, producing a board
      * representing the state of the game after all the moves have
      * been applied.
      */
-    #[allow(dead_code)]
+    #[allow(clippy::result_large_err)]
     pub fn apply(&self, mut board: Board) -> Result<Board,Board> {
 	for m in &self.moves {
 	    let b = m.apply(board);
@@ -61,6 +260,104 @@ impl Game {
 	}
 	Ok(board)
     }
+
+    /**
+     * Get the result given at the end of this game (if any).  Note,
+     * this is not checked against the moves of the game, since games
+     * can end in other ways (e.g. resignation).
+     */
+    pub fn result(&self) -> Option<Outcome> {
+	self.result
+    }
+
+    /**
+     * Get the number of moves (i.e. plies) in this game.
+     */
+    pub fn len(&self) -> usize {
+	self.moves.len()
+    }
+
+    /**
+     * Check whether this game has no moves.
+     */
+    pub fn is_empty(&self) -> bool {
+	self.moves.is_empty()
+    }
+
+    /**
+     * Get the move at a given index in this game (where index 0 is
+     * white's first move).
+     */
+    pub fn get(&self, index: usize) -> Option<&dyn Move> {
+	self.moves.get(index).map(|m| m.as_ref())
+    }
+
+    /**
+     * Iterate every position in this game from a given starting
+     * board.  Each item gives the ply of the move (starting from 1),
+     * the move itself, and the boards before and after the move was
+     * applied.  Iteration stops at the end of the game, or at the
+     * first move which cannot be applied.
+     */
+    pub fn positions(&self, start: Board) -> Positions<'_> {
+	Positions{moves: self.moves.iter(), board: start, ply: 0}
+    }
+
+    /**
+     * Get the board after a given number of plies have been applied
+     * to a given starting board (where ply 0 is the starting board
+     * itself).  If the game has fewer plies, or a move before then
+     * cannot be applied, then nothing is returned.
+     */
+    pub fn position(&self, start: Board, ply: usize) -> Option<Board> {
+	if ply == 0 {
+	    Some(start)
+	} else if ply > self.moves.len() {
+	    None
+	} else {
+	    self.positions(start).nth(ply - 1).map(|(_,_,_,after)| after)
+	}
+    }
+}
+
+/**
+ * Iterator over the positions of a game, as produced by
+ * `Game::positions()`.
+ */
+pub struct Positions<'a> {
+    /**
+     * Moves remaining to be applied
+     */
+    moves: std::slice::Iter<'a,Box<dyn Move>>,
+    /**
+     * Board before the next move is applied
+     */
+    board: Board,
+    /**
+     * Number of moves applied so far
+     */
+    ply: usize
+}
+
+impl<'a> Iterator for Positions<'a> {
+    type Item = (usize, &'a dyn Move, Board, Board);
+
+    fn next(&mut self) -> Option<Self::Item> {
+	let m = self.moves.next()?;
+	match m.apply(self.board) {
+	    Some(after) => {
+		let before = self.board;
+		self.board = after;
+		self.ply += 1;
+		Some((self.ply, m.as_ref(), before, after))
+	    }
+	    None => {
+		// Stop iteration for good
+		self.moves = [].iter();
+		None
+	    }
+	}
+    }
 }
 
 impl fmt::Display for Game {
@@ -74,6 +371,9 @@ impl fmt::Display for Game {
             }
             w = !w;
         }
+	if let Some(r) = self.result {
+	    write!(f, "{}", r)?;
+	}
         Ok(())
     }
 }
@@ -1492,11 +1792,424 @@ fn test_king_20() {
 		   Ke1-e2 c7-c6\n\
 		   Ke2xb5");
 }
+
+// ======================================================
+// Special Moves
+// ======================================================
+
+#[test]
+fn test_special_01() {
+    check_valid("e2-e4 e7-e5\n\
+		 Ng1-f3 Nb8-c6\n\
+		 Bf1-c4 Bf8-c5\n\
+		 O-O",
+		//
+		"8|r|_|b|q|k|_|n|r|\n\
+		 7|p|p|p|p|_|p|p|p|\n\
+		 6|_|_|n|_|_|_|_|_|\n\
+		 5|_|_|b|_|p|_|_|_|\n\
+		 4|_|_|B|_|P|_|_|_|\n\
+		 3|_|_|_|_|_|N|_|_|\n\
+		 2|P|P|P|P|_|P|P|P|\n\
+		 1|R|N|B|Q|_|R|K|_|\n\
+		 -|a b c d e f g h");
+}
+
+#[test]
+fn test_special_02() {
+    check_valid("d2-d4 d7-d5\n\
+		 Nb1-c3 Nb8-c6\n\
+		 Bc1-f4 Bc8-f5\n\
+		 Qd1-d2 Qd8-d7\n\
+		 O-O-O O-O-O",
+		//
+		"8|_|_|k|r|_|b|n|r|\n\
+		 7|p|p|p|q|p|p|p|p|\n\
+		 6|_|_|n|_|_|_|_|_|\n\
+		 5|_|_|_|p|_|b|_|_|\n\
+		 4|_|_|_|P|_|B|_|_|\n\
+		 3|_|_|N|_|_|_|_|_|\n\
+		 2|P|P|P|Q|P|P|P|P|\n\
+		 1|_|_|K|R|_|B|N|R|\n\
+		 -|a b c d e f g h");
+}
+
+#[test]
+fn test_special_03() {
+    check_valid("e2-e4 a7-a6\n\
+		 e4-e5 d7-d5\n\
+		 e5xd6",
+		//
+		"8|r|n|b|q|k|b|n|r|\n\
+		 7|_|p|p|_|p|p|p|p|\n\
+		 6|p|_|_|P|_|_|_|_|\n\
+		 5|_|_|_|_|_|_|_|_|\n\
+		 4|_|_|_|_|_|_|_|_|\n\
+		 3|_|_|_|_|_|_|_|_|\n\
+		 2|P|P|P|P|_|P|P|P|\n\
+		 1|R|N|B|Q|K|B|N|R|\n\
+		 -|a b c d e f g h");
+}
+
+#[test]
+fn test_special_04() {
+    check_valid("h2-h4 g7-g5\n\
+		 h4xg5 Ng8-f6\n\
+		 g5-g6 Rh8-g8\n\
+		 g6-g7 Nf6-e4\n\
+		 g7xBf8=Q",
+		//
+		"8|r|n|b|q|k|Q|r|_|\n\
+		 7|p|p|p|p|p|p|_|p|\n\
+		 6|_|_|_|_|_|_|_|_|\n\
+		 5|_|_|_|_|_|_|_|_|\n\
+		 4|_|_|_|_|n|_|_|_|\n\
+		 3|_|_|_|_|_|_|_|_|\n\
+		 2|P|P|P|P|P|P|P|_|\n\
+		 1|R|N|B|Q|K|B|N|R|\n\
+		 -|a b c d e f g h");
+}
+
+#[test]
+fn test_special_10() {
+    // Pieces in the way
+    check_invalid("e2-e4 e7-e5\n\
+		   O-O");
+}
+
+#[test]
+fn test_special_11() {
+    // King has already moved
+    check_invalid("e2-e4 e7-e5\n\
+		   Ng1-f3 Nb8-c6\n\
+		   Bf1-c4 Bf8-c5\n\
+		   Ke1-e2 d7-d6\n\
+		   Ke2-e1 d6-d5\n\
+		   O-O");
+}
+
+#[test]
+fn test_special_12() {
+    // King passes through check
+    check_invalid("e2-e4 e7-e5\n\
+		   Ng1-h3 Nb8-c6\n\
+		   Bf1-e2 Bf8-c5\n\
+		   f2-f4 d7-d6\n\
+		   O-O");
+}
+
+#[test]
+fn test_special_13() {
+    // En passant only immediately after pawn advances
+    check_invalid("e2-e4 a7-a6\n\
+		   e4-e5 d7-d5\n\
+		   a2-a3 a6-a5\n\
+		   e5xd6");
+}
+
+#[test]
+fn test_special_14() {
+    // Pawn must be promoted on final row
+    check_invalid("h2-h4 g7-g5\n\
+		   h4xg5 Ng8-f6\n\
+		   g5-g6 Rh8-g8\n\
+		   g6-g7 Nf6-e4\n\
+		   g7xBf8");
+}
+
+#[test]
+fn test_special_15() {
+    // Pawn cannot be promoted before final row
+    check_invalid("e2-e4=Q");
+}
+
+// ======================================================
+// Annotations
+// ======================================================
+
+#[test]
+fn test_annotation_01() {
+    // Glyphs and en passant written separately
+    check_valid("e2-e4! a7-a6?!\n\
+		 e4-e5 d7-d5??\n\
+		 e5xd6 e.p.!!",
+		//
+		"8|r|n|b|q|k|b|n|r|\n\
+		 7|_|p|p|_|p|p|p|p|\n\
+		 6|p|_|_|P|_|_|_|_|\n\
+		 5|_|_|_|_|_|_|_|_|\n\
+		 4|_|_|_|_|_|_|_|_|\n\
+		 3|_|_|_|_|_|_|_|_|\n\
+		 2|P|P|P|P|_|P|P|P|\n\
+		 1|R|N|B|Q|K|B|N|R|\n\
+		 -|a b c d e f g h");
+}
+
+#[test]
+fn test_annotation_02() {
+    // Fool's mate
+    check_valid("f2-f3 e7-e5\n\
+		 g2-g4? Qd8-h4#",
+		//
+		"8|r|n|b|_|k|b|n|r|\n\
+		 7|p|p|p|p|_|p|p|p|\n\
+		 6|_|_|_|_|_|_|_|_|\n\
+		 5|_|_|_|_|p|_|_|_|\n\
+		 4|_|_|_|_|_|_|P|q|\n\
+		 3|_|_|_|_|_|P|_|_|\n\
+		 2|P|P|P|P|P|_|_|P|\n\
+		 1|R|N|B|Q|K|B|N|R|\n\
+		 -|a b c d e f g h");
+}
+
+#[test]
+fn test_annotation_03() {
+    // Round trip with annotations intact
+    let text = "e2-e4! e7-e5?!\nQd1-h5 Ke8-e7??\nQh5xe5#";
+    let g = Game::from_str(text).unwrap();
+    assert!(g.apply(INITIAL).is_ok());
+    assert_eq!(g.to_string(), "e2-e4! e7-e5?! \nQd1-h5 Ke8-e7?? \nQh5xe5# ");
+    assert_eq!(g.get(4).unwrap().annotation().check, Some(Check::Mate));
+    assert_eq!(g.get(3).unwrap().annotation().glyph, Some(Glyph::Blunder));
+}
+
+#[test]
+fn test_annotation_10() {
+    // Not check
+    check_invalid("e2-e4+");
+}
+
+#[test]
+fn test_annotation_11() {
+    // Check but not checkmate
+    check_invalid("e2-e4 f7-f5\n\
+		   Qd1-h5#");
+}
+
+#[test]
+fn test_annotation_12() {
+    // Not en passant
+    check_invalid("e2-e4 d7-d5\n\
+		   e4xd5e.p.");
+}
+
+#[test]
+fn test_annotation_13() {
+    // Unknown annotations
+    assert!(Game::from_str("e2-e4!!!").is_err());
+    assert!(Game::from_str("e2-e4x").is_err());
+    assert!(Game::from_str("O-O-O?+").is_err());
+}
+
+// ======================================================
+// Move Numbers
+// ======================================================
+
+#[test]
+fn test_numbers_01() {
+    // Move numbers on one line, and moves wrapped across lines
+    let g1 = Game::from_str("1. e2-e4 e7-e5 2. Ng1-f3\nNb8-c6 3.Bf1-c4").unwrap();
+    let g2 = Game::from_str("e2-e4 e7-e5\nNg1-f3 Nb8-c6\nBf1-c4").unwrap();
+    assert_eq!(g1.len(), 5);
+    assert_eq!(g1.to_string(), g2.to_string());
+    assert_eq!(g1.apply(INITIAL).unwrap(), g2.apply(INITIAL).unwrap());
+}
+
+#[test]
+fn test_numbers_02() {
+    // Black's move numbers, free whitespace and blank lines
+    let g = Game::from_str("  1.  e2-e4\n\n\t1... e7-e5  \n2. d2-d4 2...e5xd4\n").unwrap();
+    assert_eq!(g.len(), 4);
+    assert!(g.apply(INITIAL).is_ok());
+}
+
+#[test]
+fn test_numbers_03() {
+    // Result token
+    let g = Game::from_str("1. f2-f3 e7-e5 2. g2-g4 Qd8-h4# 0-1").unwrap();
+    assert_eq!(g.len(), 4);
+    assert_eq!(g.result(), Some(Outcome::BlackWins));
+    assert!(g.to_string().ends_with("0-1"));
+    let g = Game::from_str("e2-e4 *").unwrap();
+    assert_eq!(g.result(), Some(Outcome::Unfinished));
+    let g = Game::from_str("e2-e4 e7-e5\n1/2-1/2\n").unwrap();
+    assert_eq!(g.result(), Some(Outcome::Draw));
+    assert_eq!(Game::from_str("e2-e4").unwrap().result(), None);
+}
+
+#[test]
+fn test_numbers_10() {
+    // Numbers must be consecutive
+    let e = Game::from_str("1. e2-e4 e7-e5\n3. d2-d4").err().unwrap();
+    assert_eq!(e.line, 2);
+    assert_eq!(e.token, "3.");
+    assert_eq!(e.kind, ErrorKind::MoveNumber(2,3));
+    assert_eq!(e.to_string(), "line 2: move number 3 found where 2 expected (\"3.\")");
+}
+
+#[test]
+fn test_numbers_11() {
+    // Numbers must match side to move
+    let e = Game::from_str("1. e2-e4 2. e7-e5").err().unwrap();
+    assert_eq!(e.kind, ErrorKind::WhiteNumber);
+    let e = Game::from_str("1... e2-e4").err().unwrap();
+    assert_eq!(e.kind, ErrorKind::BlackNumber);
+    let e = Game::from_str("1. e2-e4 2... e7-e5").err().unwrap();
+    assert_eq!(e.kind, ErrorKind::MoveNumber(1,2));
+}
+
+#[test]
+fn test_numbers_12() {
+    // Numbers must be followed by moves
+    assert_eq!(Game::from_str("1. e2-e4 e7-e5 2.").err().unwrap().kind, ErrorKind::MissingMove);
+    assert_eq!(Game::from_str("1. 2. e2-e4").err().unwrap().kind, ErrorKind::MissingMove);
+    assert_eq!(Game::from_str("1. e2-e4 1... 1-0").err().unwrap().kind, ErrorKind::MissingMove);
+    assert_eq!(Game::from_str("1.. e2-e4").err().unwrap().kind, ErrorKind::Number);
+}
+
+#[test]
+fn test_numbers_13() {
+    // Nothing after result
+    let e = Game::from_str("e2-e4 1-0\ne7-e5").err().unwrap();
+    assert_eq!(e.kind, ErrorKind::AfterResult);
+    assert_eq!(e.line, 2);
+}
+
+// ======================================================
+// Malformed Moves
+// ======================================================
+
+#[test]
+fn test_malformed_01() {
+    // Truncated moves
+    for s in ["N","e","Nb1-","e2-","e2-e","e2"] {
+	assert_eq!(Game::from_str(s).err().unwrap().kind, ErrorKind::Move(muve::Error::Truncated));
+    }
+}
+
+#[test]
+fn test_malformed_02() {
+    assert_eq!(Game::from_str("i2-i4").err().unwrap().kind, ErrorKind::Move(muve::Error::Square));
+    assert_eq!(Game::from_str("e2-e9").err().unwrap().kind, ErrorKind::Move(muve::Error::Square));
+    assert_eq!(Game::from_str("e2/e4").err().unwrap().kind, ErrorKind::Move(muve::Error::Separator));
+    assert_eq!(Game::from_str("e7-e8=").err().unwrap().kind, ErrorKind::Move(muve::Error::Promotion));
+    assert_eq!(Game::from_str("e2-e4x").err().unwrap().kind, ErrorKind::Move(muve::Error::Annotation(annotation::Error::Text)));
+    assert_eq!(Game::from_str("e2-e4!!!").err().unwrap().kind, ErrorKind::Move(muve::Error::Annotation(annotation::Error::Glyph)));
+}
+
+#[test]
+fn test_malformed_03() {
+    // Multibyte characters anywhere in a move
+    for s in ["é","Né1-c3","e2é-e4","e2-é4","e2-e4é","e2-e4+é","Oé-O","é2-e4"] {
+	assert!(matches!(Game::from_str(s).err().unwrap().kind, ErrorKind::Move(_)));
+    }
+}
+
+#[test]
+fn test_malformed_04() {
+    // Random strings built from fragments of moves never cause a
+    // panic (using a simple xorshift generator for repeatability)
+    let fragments = ["N","K","x","-","=","e","2","9","O","0",".","+","#","!","?"," ","\n","é","♞","1-0"];
+    let mut rng = Random(0x2545F4914F6CDD1D);
+    for _ in 0..20000 {
+	let mut s = String::new();
+	for _ in 0..rng.next(12) {
+	    s.push_str(fragments[rng.next(fragments.len())]);
+	}
+	let _ = Game::from_str(&s);
+	let _ = muve::from_str(&s,Player::Black);
+    }
+}
+
+// ======================================================
+// Positions
+// ======================================================
+
+#[test]
+fn test_positions_01() {
+    let g = Game::from_str("e2-e4 e7-e5\nNg1-f3").unwrap();
+    let ps : Vec<_> = g.positions(INITIAL).collect();
+    assert_eq!(ps.len(), 3);
+    // Check plies and moves
+    assert_eq!(ps[0].0, 1);
+    assert_eq!(ps[2].0, 3);
+    assert_eq!(ps[1].1.to_string(), "e7-e5");
+    // Check boards chain together
+    assert_eq!(ps[0].2, INITIAL);
+    assert_eq!(ps[0].3, ps[1].2);
+    assert_eq!(ps[1].3, ps[2].2);
+    assert_eq!(ps[2].3, g.apply(INITIAL).unwrap());
+}
+
+#[test]
+fn test_positions_02() {
+    // Iteration stops at first invalid move
+    let g = Game::from_str("e2-e4 e7-e5\ne4-e5 d7-d6").unwrap();
+    let ps : Vec<_> = g.positions(INITIAL).collect();
+    assert_eq!(ps.len(), 2);
+    assert_eq!(ps[1].3, g.apply(INITIAL).unwrap_err());
+}
+
+#[test]
+fn test_positions_03() {
+    let g = Game::from_str("e2-e4 e7-e5\nNg1-f3").unwrap();
+    assert_eq!(g.position(INITIAL,0), Some(INITIAL));
+    for (ply,_,_,after) in g.positions(INITIAL) {
+	assert_eq!(g.position(INITIAL,ply), Some(after));
+    }
+    assert_eq!(g.position(INITIAL,4), None);
+}
+
+#[test]
+fn test_positions_04() {
+    // Position unavailable after invalid move
+    let g = Game::from_str("e2-e4 e7-e5\ne4-e5 d7-d6").unwrap();
+    assert!(g.position(INITIAL,2).is_some());
+    assert_eq!(g.position(INITIAL,3), None);
+    assert_eq!(g.position(INITIAL,4), None);
+}
+
+#[test]
+fn test_check_01() {
+    // Pinned pawn cannot expose its king
+    check_invalid("e2-e4 e7-e5\nQd1-h5 f7-f6");
+}
+
+#[test]
+fn test_check_02() {
+    // King cannot be left in check
+    check_invalid("e2-e4 f7-f5\nQd1-h5 a7-a6");
+    check_invalid("e2-e4 f7-f5\nQd1-h5 Ke8-f7");
+}
+
+#[test]
+fn test_check_03() {
+    // Check can be blocked
+    let g = Game::from_str("e2-e4 f7-f5\nQd1-h5 g7-g6").unwrap();
+    assert!(g.apply(INITIAL).is_ok());
+}
+
+#[test]
+fn test_check_04() {
+    // Making a move which exposes the king leaves the board unchanged
+    let mut board = Game::from_str("e2-e4 e7-e5\nQd1-h5").unwrap().apply(INITIAL).unwrap();
+    let before = board;
+    let m = muve::from_str("f7-f6",Player::Black).unwrap();
+    assert!(m.make(&mut board).is_none());
+    assert_eq!(board, before);
+}
     
 // ======================================================
 // Helpers
 // ======================================================
 
+#[cfg(test)]
+use super::annotation;
+#[cfg(test)]
+use super::annotation::{Check, Glyph};
+#[cfg(test)]
+use super::testing::Random;
+
 /**
  * Check that a given game (i.e. sequence of moves) produce an
  * expected board when applied to the initial board.
@@ -1518,7 +2231,7 @@ fn check_valid(game: &str, expected: &str) {
 	Err(b) => b
     };
     // Print actual board
-    println!("Actual:\n{}",brd.to_string());
+    println!("Actual:\n{}",brd);
     // Check whether they match
     assert!(brd.to_string() == expected);       
 }
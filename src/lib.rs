@@ -0,0 +1,62 @@
+#![warn(missing_docs)]
+
+//! A simple crate for checking games in algebraic notation are
+//! correct.
+
+/** Pieces and the rules governing how they move */
+pub mod piece;
+/** Board representation */
+pub mod board;
+/** Annotations written after moves (e.g. "+" or "!?") */
+pub mod annotation;
+/** Moves and their notation */
+pub mod muve;
+/** Squares on the board */
+pub mod square;
+/** Errors found on a given line when reading */
+pub mod error;
+/** Games as sequences of moves */
+pub mod game;
+/** Positions updated in place with moves which can be unmade */
+pub mod position;
+/** Zobrist hashing of boards */
+pub mod zobrist;
+/** Reading and writing boards in Forsyth-Edwards Notation */
+pub mod fen;
+/** Reading and writing positions in Extended Position Description */
+pub mod epd;
+/** Generating the legal moves of a board */
+pub mod movegen;
+/** Writing moves in Standard Algebraic Notation */
+pub mod san;
+/** Reading games from PGN databases */
+pub mod pgn;
+/** Reading and writing Polyglot opening books */
+pub mod polyglot;
+/** Reports on the validity of games, which can be written as JSON */
+pub mod report;
+/** Rendering boards as text in different styles */
+pub mod render;
+/** Summaries of the material in a game */
+pub mod material;
+/** Classification of openings by ECO code */
+pub mod eco;
+/** Publication-quality diagrams of boards as SVG or PNG */
+pub mod diagram;
+/** Evaluating positions with tunable weights */
+pub mod eval;
+/** Searching for the best move in a position */
+pub mod search;
+/** Marking mistakes in games by comparing moves with the best */
+pub mod annotate;
+/** Solving and verifying mate-in-N problems */
+pub mod mate;
+/** Collections of puzzles and checking their solutions */
+pub mod puzzle;
+/** Endgame tablebases generated by retrograde analysis */
+pub mod tablebase;
+/** Playing as, or driving, an engine over the Universal Chess Interface */
+pub mod uci;
+mod raster;
+#[cfg(test)]
+mod testing;
@@ -1,35 +1,102 @@
-#![warn(missing_docs)]
+mod cli;
 
-//! A simple crate for checking games in algebraic notation are
-//! correct.
+use std::env;
+use std::process::ExitCode;
+use cli::args::Command;
+use cli::{analyse, annotate, book, check, convert, epd, eval, fen, mate, material, perft, puzzles, show, stats, tablebase, uci};
+use cli::Outcome;
 
-mod piece;
-mod board;
-mod muve;
-mod square;
-mod game;
+const COMMANDS: [&Command;17] = [&check::CHECK, &convert::CONVERT, &show::SHOW, &show::VIEW, &perft::PERFT, &fen::FEN,
+				 &stats::STATS, &material::MATERIAL, &analyse::ANALYSE, &eval::EVAL, &annotate::ANNOTATE,
+				 &mate::MATE, &puzzles::PUZZLES, &epd::EPD, &book::BOOK, &tablebase::TABLEBASE, &uci::UCI];
 
-use std::env;
-use std::fs;
-use std::str::FromStr;
-use game::Game;
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(&args) {
+	Ok(true) => ExitCode::SUCCESS,
+	Ok(false) => ExitCode::from(1),
+	Err(msg) => {
+	    eprintln!("chess: {}",msg);
+	    ExitCode::from(2)
+	}
+    }
+}
+
+/**
+ * Run the command given by a set of command-line arguments.
+ */
+fn run(args: &[String]) -> Outcome {
+    let name = match args.first() {
+	Some(name) => name.as_str(),
+	None => {
+	    eprint!("{}",usage());
+	    return Err("no command given".to_string());
+	}
+    };
+    match name {
+	"help" | "--help" | "-h" => return help(args.get(1)),
+	"--version" => {
+	    println!("chess {}",env!("CARGO_PKG_VERSION"));
+	    return Ok(true);
+	}
+	_ => {}
+    }
+    let cmd = COMMANDS.iter().find(|c| c.name == name)
+	.ok_or_else(|| format!("unknown command \"{}\" (see \"chess help\")",name))?;
+    let m = cmd.parse(&args[1..])?;
+    if m.flag("help") {
+	print!("{}",cmd.help());
+	return Ok(true);
+    }
+    match cmd.name {
+	"check" => check::check(&m),
+	"convert" => convert::convert(&m),
+	"show" => show::show(&m),
+	"view" => show::view(&m),
+	"perft" => perft::perft(&m),
+	"fen" => fen::fen(&m),
+	"stats" => stats::stats(&m),
+	"material" => material::material(&m),
+	"analyse" => analyse::analyse(&m),
+	"eval" => eval::eval(&m),
+	"annotate" => annotate::annotate(&m),
+	"mate" => mate::mate(&m),
+	"puzzles" => puzzles::puzzles(&m),
+	"epd" => epd::epd(&m),
+	"book" => book::book(&m),
+	"tablebase" => tablebase::tablebase(&m),
+	"uci" => uci::uci(&m),
+	_ => unreachable!("no handler for command \"{}\"",cmd.name)
+    }
+}
+
+/**
+ * Produce the overall help text, listing the available commands.
+ */
+fn usage() -> String {
+    let mut out = String::from("Check and convert chess games written in long algebraic notation.\n\n\
+				Usage: chess COMMAND [OPTIONS] [FILE...]\n\nCommands:\n");
+    for cmd in COMMANDS {
+	out.push_str(&format!("  {:<10} {}\n",cmd.name,cmd.summary));
+    }
+    out.push_str(&format!("  {:<10} {}\n","help","Print help for a command"));
+    out.push_str("\nA FILE of \"-\" is read from standard input.  The exit status is 0 when\n\
+		  every game is valid, 1 when any game is invalid, and 2 for usage or I/O\n\
+		  errors.\n");
+    out
+}
 
-fn main() {
-    // Extract command-line arguments
-    let args: Vec<String> = env::args().collect();
-    // Determine game filename
-    let filename = &args[1];
-    println!("Reading file {}", filename);
-    // Read the game file!
-    let contents = fs::read_to_string(filename)
-        .expect("error reading game file");
-    // Parse game string
-    let g = Game::from_str(&contents).unwrap();
-    // Apply each move to initial board producing a potentially
-    // updated board.    
-    let brd = g.apply(board::INITIAL).unwrap();
-    // Print game
-    println!("Game:\n{}\n",g);
-    // Print actual board
-    println!("Actual:\n{}",brd.to_string());
+/**
+ * Print help, either for a given command or overall.
+ */
+fn help(name: Option<&String>) -> Outcome {
+    match name {
+	None => print!("{}",usage()),
+	Some(name) => {
+	    let cmd = COMMANDS.iter().find(|c| c.name == name)
+		.ok_or_else(|| format!("unknown command \"{}\"",name))?;
+	    print!("{}",cmd.help());
+	}
+    }
+    Ok(true)
 }
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::fmt;
+use super::board::Board;
+use super::movegen;
+use super::muve::Move;
+
+/**
+ * Identifies why a solution to a mate-in-N problem is wrong, where
+ * plies are counted from 1 for the first move of the solution.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** A move cannot be played (i.e. its ply) */
+    Illegal(usize),
+    /** A move by the attacker does not force mate in the moves remaining (i.e. its ply) */
+    Unforced(usize),
+    /** The solution has more moves than the problem allows */
+    Long,
+    /** The solution ends without checkmate */
+    Unfinished
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Error::Illegal(p) => write!(f,"illegal move at ply {}",p),
+	    Error::Unforced(p) => write!(f,"move at ply {} does not force mate",p),
+	    Error::Long => write!(f,"too many moves"),
+	    Error::Unfinished => write!(f,"does not end in checkmate")
+	}
+    }
+}
+
+/**
+ * Exhaustively searches for forced mates, remembering which positions
+ * have been proved (or disproved) to be mate in a given number of
+ * moves.
+ */
+struct Solver {
+    /**
+     * Whether the player to move on a board (given by its hash) forces
+     * mate within a given number of moves
+     */
+    memo: HashMap<(u64,usize),bool>
+}
+
+impl Solver {
+    fn new() -> Solver {
+	Solver{memo: HashMap::new()}
+    }
+
+    /**
+     * Check whether a given move by the player to move forces mate
+     * within a given number of moves (including this one).
+     */
+    fn forces(&mut self, board: &Board, m: &dyn Move, n: usize) -> bool {
+	let after = match m.apply(*board) {
+	    Some(after) => after,
+	    None => return false
+	};
+	// Only checks can mate
+	if n == 1 && !after.in_check(after.turn()) {
+	    return false;
+	}
+	let replies = movegen::legal_moves(&after);
+	if replies.is_empty() {
+	    // Checkmate or stalemate
+	    return after.in_check(after.turn());
+	}
+	n > 1 && replies.iter().all(|r| self.mates(&r.apply(after).unwrap(),n - 1))
+    }
+
+    /**
+     * Check whether the player to move on a given board forces mate
+     * within a given number of moves.
+     */
+    fn mates(&mut self, board: &Board, n: usize) -> bool {
+	let key = (board.hash(),n);
+	if let Some(&r) = self.memo.get(&key) {
+	    return r;
+	}
+	let r = movegen::legal_moves(board).iter().any(|m| self.forces(board,m.as_ref(),n));
+	self.memo.insert(key,r);
+	r
+    }
+}
+
+/**
+ * Find every move with which the player to move on a given board
+ * forces checkmate within a given number of moves (i.e. the key
+ * moves of a mate-in-N problem).  A problem is sound when there is
+ * exactly one key; any others are "cooks".  Every defence is
+ * considered, and stalemate is not mate.
+ */
+pub fn solve(board: &Board, n: usize) -> Vec<Box<dyn Move>> {
+    let mut solver = Solver::new();
+    movegen::legal_moves(board).into_iter().filter(|m| solver.forces(board,m.as_ref(),n)).collect()
+}
+
+/**
+ * Verify a solution to a mate-in-N problem on a given board, which
+ * alternates between the attacker's moves and the defender's replies.
+ * Every move must be legal, every move by the attacker must still
+ * force mate within the moves remaining (whatever the defence), and
+ * the solution must end in checkmate within N moves.  This produces
+ * the final board if the solution is correct.
+ */
+pub fn verify(board: &Board, n: usize, solution: &[Box<dyn Move>]) -> Result<Board,Error> {
+    if solution.len() > (2 * n).saturating_sub(1) {
+	return Err(Error::Long);
+    }
+    let mut solver = Solver::new();
+    let mut b = *board;
+    for (i,m) in solution.iter().enumerate() {
+	let after = m.apply(b).ok_or(Error::Illegal(i + 1))?;
+	if i.is_multiple_of(2) && !solver.forces(&b,m.as_ref(),n - i / 2) {
+	    return Err(Error::Unforced(i + 1));
+	}
+	b = after;
+    }
+    if movegen::is_checkmate(&b) {
+	Ok(b)
+    } else {
+	Err(Error::Unfinished)
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::fen;
+#[cfg(test)]
+use super::san;
+
+#[cfg(test)]
+fn keys(fen: &str, n: usize) -> Vec<String> {
+    let board = fen::from_str(fen).unwrap();
+    let mut keys: Vec<String> = solve(&board,n).iter().map(|m| san::to_string(&board,m.as_ref())).collect();
+    keys.sort();
+    keys
+}
+
+#[cfg(test)]
+fn check(fen: &str, n: usize, line: &str) -> Result<Board,Error> {
+    let mut b = fen::from_str(fen).unwrap();
+    let start = b;
+    let mut moves = Vec::new();
+    for s in line.split_ascii_whitespace() {
+	let m = san::from_str(&b,s).unwrap();
+	b = m.apply(b).unwrap();
+	moves.push(m);
+    }
+    verify(&start,n,&moves)
+}
+
+#[test]
+fn test_01() {
+    // Mate in one with a single key
+    assert_eq!(keys("k7/8/1K6/8/8/8/8/7R w - - 0 1",1), vec!["Rh8#"]);
+    // Cooked: either rook mates
+    assert_eq!(keys("k7/8/1K6/8/8/8/8/6RR w - - 0 1",1), vec!["Rg8#","Rh8#"]);
+    // No mate
+    assert!(keys("4k3/8/8/8/8/8/8/4K3 w - - 0 1",3).is_empty());
+}
+
+#[test]
+fn test_02() {
+    // Mate in two with the rook ladder, where mate in one is impossible
+    assert!(keys("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1",1).is_empty());
+    let k = keys("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1",2);
+    assert!(k.contains(&"Ra7".to_string()), "{:?}", k);
+    // Stalemate is not mate (e.g. Qc7 leaves black no moves)
+    let k = keys("k7/7Q/1K6/8/8/8/8/8 w - - 0 1",1);
+    assert!(k.contains(&"Qb7#".to_string()) && !k.contains(&"Qc7".to_string()));
+    assert_eq!(check("k7/7Q/1K6/8/8/8/8/8 w - - 0 1",2,"Qc7"), Err(Error::Unforced(1)));
+}
+
+#[test]
+fn test_03() {
+    let fen = "7k/8/8/8/8/8/R7/1R4K1 w - - 0 1";
+    assert!(check(fen,2,"Ra7 Kg8 Rb8#").is_ok());
+    assert_eq!(check(fen,2,"Ra7 Kg8"), Err(Error::Unfinished));
+    assert_eq!(check(fen,2,"Rb7 Kg8 Ra8# "), Ok(fen::from_str("R5k1/1R6/8/8/8/8/8/6K1 b - - 3 2").unwrap()));
+    assert_eq!(check(fen,2,"Ra3 Kg8 Rb8+ Kf7"), Err(Error::Long));
+    assert_eq!(check(fen,2,"Kf2 Kg8 Rb8+"), Err(Error::Unforced(1)));
+    let b = fen::from_str(fen).unwrap();
+    let m = san::from_str(&fen::from_str("7k/8/8/8/8/8/8/R5K1 w - - 0 1").unwrap(),"Ra8#").unwrap();
+    assert_eq!(verify(&b,2,&[m]), Err(Error::Illegal(1)));
+}
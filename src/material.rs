@@ -0,0 +1,209 @@
+use super::board::Board;
+use super::game::Game;
+use super::muve::Move;
+use super::piece::*;
+use super::san;
+
+/**
+ * The kinds of piece which are counted, in the order they are
+ * normally listed.
+ */
+pub const KINDS: [Kind;6] = [Kind::Pawn, Kind::Knight, Kind::Bishop, Kind::Rook, Kind::Queen, Kind::King];
+
+/**
+ * Counts of each kind of piece for each player, such as the pieces on
+ * a board or the pieces captured during a game.
+ */
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Material {
+    /**
+     * Counts indexed by player (white first) and kind (see KINDS)
+     */
+    counts: [[u8;6];2]
+}
+
+impl Material {
+    /**
+     * Count the pieces on a given board.
+     */
+    pub fn of(board: &Board) -> Material {
+	let mut m = Material::default();
+	for (_,p) in board.pieces() {
+	    m.add(p);
+	}
+	m
+    }
+
+    /**
+     * Add a given piece to the counts.  Blank pieces are ignored.
+     */
+    pub fn add(&mut self, piece: Piece) {
+	if let Some(k) = kind_index(piece.kind) {
+	    self.counts[index(piece.player)][k] += 1;
+	}
+    }
+
+    /**
+     * Get the number of a given piece (e.g. white knights).
+     */
+    pub fn count(&self, piece: Piece) -> u8 {
+	match kind_index(piece.kind) {
+	    Some(k) => self.counts[index(piece.player)][k],
+	    None => 0
+	}
+    }
+
+    /**
+     * Get the number of pieces (of any kind) for a given player.
+     */
+    pub fn total(&self, player: Player) -> u32 {
+	self.counts[index(player)].iter().map(|&n| n as u32).sum()
+    }
+
+    /**
+     * Get the standard point value of the pieces for a given player.
+     */
+    pub fn points(&self, player: Player) -> u32 {
+	KINDS.iter().zip(self.counts[index(player)]).map(|(k,n)| k.value() * n as u32).sum()
+    }
+
+    /**
+     * Get the material balance in points, which is positive when
+     * white is ahead and negative when black is ahead.
+     */
+    pub fn balance(&self) -> i32 {
+	self.points(Player::White) as i32 - self.points(Player::Black) as i32
+    }
+}
+
+/**
+ * Summary of the material in a game after a given number of plies.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    /**
+     * Number of plies played (where zero is the starting board)
+     */
+    pub ply: usize,
+    /**
+     * Move played to reach this ply in SAN (if any)
+     */
+    pub san: Option<String>,
+    /**
+     * Pieces on the board
+     */
+    pub material: Material,
+    /**
+     * Piece captured by the move played to reach this ply (if any)
+     */
+    pub captured: Option<Piece>,
+    /**
+     * Pieces captured so far, where (for example) the black pieces are
+     * those captured by white
+     */
+    pub captures: Material
+}
+
+/**
+ * Summarise the material after each ply of a game played from a given
+ * starting board, beginning with the starting board itself.  This
+ * stops at the first invalid move (if any).
+ */
+pub fn summarise(game: &Game, start: Board) -> Vec<Row> {
+    let mut captures = Material::default();
+    let mut rows = vec![Row{ply: 0, san: None, material: Material::of(&start), captured: None, captures}];
+    for (ply,m,before,after) in game.positions(start) {
+	let captured = captured(&before,m);
+	if let Some(p) = captured {
+	    captures.add(p);
+	}
+	let san = Some(san::to_string(&before,m));
+	rows.push(Row{ply, san, material: Material::of(&after), captured, captures});
+    }
+    rows
+}
+
+/**
+ * Determine the piece (if any) captured by a given move on the board
+ * it is played on.  This includes pawns taken en passant, which are
+ * not on the square moved to.
+ */
+pub fn captured(board: &Board, m: &dyn Move) -> Option<Piece> {
+    let (from,to) = (m.from(),m.to());
+    let piece = board.get(from);
+    let target = board.get(to);
+    if target != BLANK {
+	Some(target)
+    } else if piece.kind == Kind::Pawn && from.column() != to.column() {
+	Some(Piece{player: piece.player.flip(), kind: Kind::Pawn})
+    } else {
+	None
+    }
+}
+
+/**
+ * Determine the index used for a given player.
+ */
+pub(crate) fn index(player: Player) -> usize {
+    match player {
+	Player::White => 0,
+	Player::Black => 1
+    }
+}
+
+/**
+ * Determine the index of a kind of piece in KINDS (which blank is not).
+ */
+pub(crate) fn kind_index(kind: Kind) -> Option<usize> {
+    KINDS.iter().position(|&k| k == kind)
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use super::board::INITIAL;
+#[cfg(test)]
+use super::fen;
+
+#[test]
+fn test_01() {
+    let m = Material::of(&INITIAL);
+    assert_eq!(m.count(WHITE_PAWN), 8);
+    assert_eq!(m.count(BLACK_QUEEN), 1);
+    assert_eq!(m.count(BLANK), 0);
+    assert_eq!(m.total(Player::Black), 16);
+    assert_eq!(m.points(Player::White), 39);
+    assert_eq!(m.balance(), 0);
+}
+
+#[test]
+fn test_02() {
+    let b = fen::from_str("4k3/8/8/8/8/8/8/RN2K3 w - - 0 1").unwrap();
+    let m = Material::of(&b);
+    assert_eq!(m.points(Player::White), 8);
+    assert_eq!(m.balance(), 8);
+    assert_eq!(b.pieces().count(), 4);
+}
+
+#[test]
+fn test_03() {
+    // Captures, including en passant
+    let g = Game::from_str("e2-e4 d7-d5\ne4xd5 Qd8xd5\ne2-e4").unwrap();
+    let rows = summarise(&g,INITIAL);
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[0].san, None);
+    assert_eq!(rows[3].san.as_deref(), Some("exd5"));
+    assert_eq!(rows[3].captured, Some(BLACK_PAWN));
+    assert_eq!(rows[4].captured, Some(WHITE_PAWN));
+    assert_eq!(rows[4].captures.total(Player::White), 1);
+    assert_eq!(rows[4].captures.total(Player::Black), 1);
+    assert_eq!(rows[4].material.balance(), 0);
+    let g = Game::from_str("e2-e4 a7-a6\ne4-e5 d7-d5\ne5xd6").unwrap();
+    let rows = summarise(&g,INITIAL);
+    assert_eq!(rows[5].captured, Some(BLACK_PAWN));
+    assert_eq!(rows[5].material.balance(), 1);
+}
@@ -0,0 +1,323 @@
+use super::board::*;
+use super::muve::*;
+use super::piece::*;
+use super::square::Square;
+
+/**
+ * Offsets (in columns and rows) of the squares a knight can jump to.
+ */
+pub(crate) const KNIGHT: [(i8,i8);8] = [(1,2),(2,1),(2,-1),(1,-2),(-1,-2),(-2,-1),(-2,1),(-1,2)];
+
+/**
+ * Directions (in columns and rows) along which a bishop slides.
+ */
+pub(crate) const DIAGONALS: [(i8,i8);4] = [(1,1),(1,-1),(-1,-1),(-1,1)];
+
+/**
+ * Directions (in columns and rows) along which a rook slides.
+ */
+pub(crate) const STRAIGHTS: [(i8,i8);4] = [(1,0),(0,-1),(-1,0),(0,1)];
+
+/**
+ * Directions (in columns and rows) along which a queen slides, or a
+ * king steps, which are the diagonals followed by the straights.
+ */
+pub(crate) const DIRECTIONS: [(i8,i8);8] = [(1,1),(1,-1),(-1,-1),(-1,1),(1,0),(0,-1),(-1,0),(0,1)];
+
+/**
+ * Kinds of piece which a pawn can be promoted to, most valuable first.
+ */
+pub(crate) const PROMOTIONS: [Kind;4] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight];
+
+/**
+ * Generate every legal move for the player whose turn it is on a
+ * given board.  A move is legal if it can be applied to the board,
+ * which means (amongst other things) that it does not leave the
+ * player's own king in check.
+ */
+pub fn legal_moves(board: &Board) -> Vec<Box<dyn Move>> {
+    let mut scratch = *board;
+    candidate_moves(board).into_iter()
+	.filter(|m| match m.make(&mut scratch) {
+	    Some(undo) => { scratch.restore(&undo); true }
+	    None => false
+	})
+	.collect()
+}
+
+/**
+ * Check whether the player to move on a given board has been
+ * checkmated (i.e. is in check and has no legal moves).
+ */
+pub fn is_checkmate(board: &Board) -> bool {
+    board.in_check(board.turn()) && legal_moves(board).is_empty()
+}
+
+/**
+ * Check whether the player to move on a given board has been
+ * stalemated (i.e. is not in check but has no legal moves).
+ */
+pub fn is_stalemate(board: &Board) -> bool {
+    !board.in_check(board.turn()) && legal_moves(board).is_empty()
+}
+
+/**
+ * Count the number of distinct move sequences of a given length
+ * (i.e. the leaf nodes of the game tree at that depth) from a given
+ * board.  This is the standard "perft" test for move generators, as
+ * the counts are well known for many positions.
+ */
+pub fn perft(board: &Board, depth: usize) -> u64 {
+    let mut b = *board;
+    count(&mut b,depth)
+}
+
+/**
+ * Count the leaf nodes below each legal move from a given board.
+ * This is useful for finding which move a move generator gets wrong,
+ * by comparing against a reference engine.
+ */
+pub fn divide(board: &Board, depth: usize) -> Vec<(Box<dyn Move>,u64)> {
+    let mut b = *board;
+    legal_moves(board).into_iter().map(|m| {
+	let undo = m.make(&mut b).unwrap();
+	let n = count(&mut b,depth.saturating_sub(1));
+	b.restore(&undo);
+	(m,n)
+    }).collect()
+}
+
+/**
+ * Recursively count leaf nodes, making and unmaking moves in place.
+ */
+fn count(board: &mut Board, depth: usize) -> u64 {
+    if depth == 0 {
+	return 1;
+    }
+    let mut n = 0;
+    for m in candidate_moves(board) {
+	if let Some(undo) = m.make(board) {
+	    n += if depth == 1 { 1 } else { count(board,depth - 1) };
+	    board.restore(&undo);
+	}
+    }
+    n
+}
+
+/**
+ * Generate candidate moves for the player to move on a given board.
+ * These follow the movement patterns of each piece, but may not be
+ * legal (e.g. they may leave the king in check).
+ */
+fn candidate_moves(board: &Board) -> Vec<Box<dyn Move>> {
+    let player = board.turn();
+    let mut moves : Vec<Box<dyn Move>> = Vec::new();
+    for row in 0..8 {
+	for col in 0..8 {
+	    let from = Square::new(col,row);
+	    let p = board.get(from);
+	    if p == BLANK || p.player != player { continue; }
+	    match p.kind {
+		Kind::Pawn => pawn_moves(board,p,from,&mut moves),
+		Kind::Knight => jump_moves(board,p,from,&KNIGHT,&mut moves),
+		Kind::Bishop => slide_moves(board,p,from,&DIAGONALS,&mut moves),
+		Kind::Rook => slide_moves(board,p,from,&STRAIGHTS,&mut moves),
+		Kind::Queen => slide_moves(board,p,from,&DIRECTIONS,&mut moves),
+		Kind::King => jump_moves(board,p,from,&DIRECTIONS,&mut moves),
+		Kind::Blank => {}
+	    }
+	}
+    }
+    moves.push(Box::new(Castle::new(player,true)));
+    moves.push(Box::new(Castle::new(player,false)));
+    moves
+}
+
+/**
+ * Determine the square at a given offset from another, if it is on
+ * the board.
+ */
+pub(crate) fn offset(s: Square, dc: i8, dr: i8) -> Option<Square> {
+    let col = s.column() as i8 + dc;
+    let row = s.row() as i8 + dr;
+    if (0..8).contains(&col) && (0..8).contains(&row) {
+	Some(Square::new(col as u8, row as u8))
+    } else {
+	None
+    }
+}
+
+/**
+ * Add a move of a given piece to a given square, which is either a
+ * simple move or a take depending on what occupies the square.
+ * Nothing is added if the square holds one of the player's own
+ * pieces.
+ */
+fn add_move(board: &Board, p: Piece, from: Square, to: Square, promote: Option<Piece>,
+	    moves: &mut Vec<Box<dyn Move>>) {
+    let t = board.get(to);
+    if t == BLANK {
+	moves.push(Box::new(SimpleMove::new(p,from,to,promote)));
+    } else if t.player != p.player {
+	moves.push(Box::new(SimpleTake::new(p,from,to,t,promote)));
+    }
+}
+
+/**
+ * Add moves for a piece which jumps by given offsets (i.e. knights
+ * and kings).
+ */
+fn jump_moves(board: &Board, p: Piece, from: Square, offsets: &[(i8,i8)], moves: &mut Vec<Box<dyn Move>>) {
+    for &(dc,dr) in offsets {
+	if let Some(to) = offset(from,dc,dr) {
+	    add_move(board,p,from,to,None,moves);
+	}
+    }
+}
+
+/**
+ * Add moves for a piece which slides in given directions until
+ * obstructed (i.e. bishops, rooks and queens).
+ */
+fn slide_moves(board: &Board, p: Piece, from: Square, directions: &[(i8,i8)], moves: &mut Vec<Box<dyn Move>>) {
+    for &(dc,dr) in directions {
+	let mut s = from;
+	while let Some(to) = offset(s,dc,dr) {
+	    add_move(board,p,from,to,None,moves);
+	    if board.get(to) != BLANK { break; }
+	    s = to;
+	}
+    }
+}
+
+/**
+ * Add moves for a pawn, including double steps, en passant and
+ * promotions.
+ */
+fn pawn_moves(board: &Board, p: Piece, from: Square, moves: &mut Vec<Box<dyn Move>>) {
+    let (dir,start,last) = match p.player {
+	Player::White => (1,1,7),
+	Player::Black => (-1,6,0)
+    };
+    // Add a pawn move, expanding promotions where necessary
+    let mut add = |to: Square, taken: Option<Piece>| {
+	let promotions : Vec<Option<Piece>> = if to.row() == last {
+	    PROMOTIONS.iter().map(|&kind| Some(Piece{player: p.player, kind})).collect()
+	} else {
+	    vec![None]
+	};
+	for promote in promotions {
+	    match taken {
+		Some(t) => moves.push(Box::new(SimpleTake::new(p,from,to,t,promote))),
+		None => moves.push(Box::new(SimpleMove::new(p,from,to,promote)))
+	    }
+	}
+    };
+    // Forward moves
+    if let Some(to) = offset(from,0,dir) {
+	if board.get(to) == BLANK {
+	    add(to,None);
+	    if from.row() == start {
+		if let Some(to2) = offset(to,0,dir) {
+		    if board.get(to2) == BLANK { add(to2,None); }
+		}
+	    }
+	}
+    }
+    // Diagonal takes
+    for dc in [-1,1] {
+	if let Some(to) = offset(from,dc,dir) {
+	    let t = board.get(to);
+	    if t != BLANK && t.player != p.player {
+		add(to,Some(t));
+	    } else if board.en_passant() == Some(to) {
+		add(to,Some(Piece{player: p.player.flip(), kind: Kind::Pawn}));
+	    }
+	}
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::fen;
+
+#[cfg(test)]
+use super::testing::random_boards;
+
+#[test]
+fn test_01() {
+    assert_eq!(legal_moves(&INITIAL).len(), 20);
+    assert_eq!(perft(&INITIAL,0), 1);
+    assert_eq!(perft(&INITIAL,1), 20);
+    assert_eq!(perft(&INITIAL,2), 400);
+    assert_eq!(perft(&INITIAL,3), 8902);
+}
+
+#[test]
+fn test_02() {
+    // "Kiwipete" which exercises castling, en passant and promotion
+    let b = fen::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    assert_eq!(perft(&b,1), 48);
+    assert_eq!(perft(&b,2), 2039);
+}
+
+#[test]
+fn test_03() {
+    // Pins and en passant revealing check along a rank
+    let b = fen::from_str("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+    assert_eq!(perft(&b,1), 14);
+    assert_eq!(perft(&b,2), 191);
+    assert_eq!(perft(&b,3), 2812);
+}
+
+#[test]
+fn test_04() {
+    // Promotions and castling through check
+    let b = fen::from_str("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+    assert_eq!(perft(&b,1), 6);
+    assert_eq!(perft(&b,2), 264);
+    assert_eq!(perft(&b,3), 9467);
+}
+
+#[test]
+fn test_05() {
+    let b = fen::from_str("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+    assert_eq!(perft(&b,1), 44);
+    assert_eq!(perft(&b,2), 1486);
+}
+
+#[test]
+fn test_06() {
+    // Fool's mate
+    let b = fen::from_str("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+    assert!(is_checkmate(&b));
+    assert!(!is_stalemate(&b));
+    // Stalemate
+    let b = fen::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert!(is_stalemate(&b));
+    assert!(!is_checkmate(&b));
+    assert!(!is_checkmate(&INITIAL));
+}
+
+#[test]
+fn test_07() {
+    let total : u64 = divide(&INITIAL,2).iter().map(|(_,n)| n).sum();
+    assert_eq!(total, 400);
+}
+
+#[test]
+fn test_08() {
+    // Each side always has exactly one king, and no legal move leaves
+    // the mover's king in check
+    for board in random_boards(11,20,150) {
+	for p in [WHITE_KING,BLACK_KING] {
+	    assert_eq!((0..64).filter(|&i| board.get(Square::new(i % 8, i / 8)) == p).count(), 1);
+	}
+	for m in legal_moves(&board) {
+	    assert!(!m.apply(board).unwrap().in_check(board.turn()));
+	}
+    }
+}
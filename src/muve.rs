@@ -1,10 +1,11 @@
 use std::fmt;
 use std::str;
 
+use super::annotation;
+use super::annotation::{Annotation, Check};
 use super::board::*;
-use super::piece;
+use super::movegen;
 use super::piece::*;
-use super::square;
 use super::square::Square;
 
 /**
@@ -14,82 +15,324 @@ use super::square::Square;
 pub trait Move : fmt::Display {
     /**
      * Apply a given move to the board, either producing an updated
-     * board (success) or nothing (failure).
+     * board (success) or nothing (failure).  This makes the move on a
+     * copy of the board.
      */
-    fn apply(&self, board: Board) -> Option<Board>;
+    fn apply(&self, board: Board) -> Option<Board> {
+	let mut board = board;
+	self.make(&mut board).map(|_| board)
+    }
+
+    /**
+     * Apply a given move to the board in place, either producing a
+     * record from which the move can be undone (success) or nothing
+     * (failure).  In the event of failure, the board is unchanged.
+     */
+    fn make(&self, board: &mut Board) -> Option<Undo>;
+
+    /**
+     * Get the starting position of the piece being moved.  For
+     * castling, this is the king.
+     */
+    fn from(&self) -> Square;
+
+    /**
+     * Get the ending position of the piece being moved.  For
+     * castling, this is the king.
+     */
+    fn to(&self) -> Square;
+
+    /**
+     * Get the piece which a pawn is promoted to by this move (if
+     * any).
+     */
+    fn promotion(&self) -> Option<Piece> {
+	None
+    }
+
+    /**
+     * Determine why this move cannot be applied to a given board.  If
+     * the move can be applied, then nothing is returned.
+     */
+    fn reason(&self, board: &Board) -> Option<Invalid>;
+
+    /**
+     * Get the annotations written after this move (e.g. "+" or "!?").
+     */
+    fn annotation(&self) -> Annotation {
+	Annotation::default()
+    }
+}
+
+/**
+ * Identifies why a move cannot be applied to a board.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Invalid {
+    /** The piece to move is not on the starting square */
+    Piece,
+    /** The piece to move belongs to the player not moving */
+    Turn,
+    /** The ending square of a (non-taking) move is not blank */
+    Occupied,
+    /** The piece to be taken is not on the ending square */
+    Capture,
+    /** The piece cannot move that way, or is obstructed */
+    Movement,
+    /** A pawn reaching the last row is not promoted, or is promoted incorrectly */
+    Promotion,
+    /** The king or rook has already moved, so castling is not allowed */
+    CastlingRights,
+    /** Pieces stand between the king and rook */
+    CastlingBlocked,
+    /** The king would castle out of, through or into check */
+    CastlingCheck,
+    /** The move leaves the player's own king in check */
+    Check,
+    /** The move is marked as giving check, but does not */
+    CheckClaim,
+    /** The move is marked as giving checkmate, but does not */
+    MateClaim,
+    /** The move is marked as taking en passant, but does not */
+    EnPassantClaim
+}
+
+impl Invalid {
+    /**
+     * Get a short identifier for this reason (e.g. "movement"), which
+     * is stable and suitable for use by other programs.
+     */
+    pub fn code(&self) -> &'static str {
+	match self {
+	    Invalid::Piece => "piece",
+	    Invalid::Turn => "turn",
+	    Invalid::Occupied => "occupied",
+	    Invalid::Capture => "capture",
+	    Invalid::Movement => "movement",
+	    Invalid::Promotion => "promotion",
+	    Invalid::CastlingRights => "castling_rights",
+	    Invalid::CastlingBlocked => "castling_blocked",
+	    Invalid::CastlingCheck => "castling_check",
+	    Invalid::Check => "check",
+	    Invalid::CheckClaim => "check_claim",
+	    Invalid::MateClaim => "mate_claim",
+	    Invalid::EnPassantClaim => "en_passant_claim"
+	}
+    }
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let msg = match self {
+	    Invalid::Piece => "piece to move is not on the starting square",
+	    Invalid::Turn => "piece to move belongs to the other player",
+	    Invalid::Occupied => "ending square is not empty",
+	    Invalid::Capture => "piece to take is not on the ending square",
+	    Invalid::Movement => "piece cannot move that way",
+	    Invalid::Promotion => "pawn promotion is missing or invalid",
+	    Invalid::CastlingRights => "king or rook has already moved",
+	    Invalid::CastlingBlocked => "pieces between king and rook",
+	    Invalid::CastlingCheck => "king would castle out of, through or into check",
+	    Invalid::Check => "move leaves king in check",
+	    Invalid::CheckClaim => "move is marked as check but does not give check",
+	    Invalid::MateClaim => "move is marked as checkmate but does not give checkmate",
+	    Invalid::EnPassantClaim => "move is marked as en passant but does not take en passant"
+	};
+	write!(f,"{}",msg)
+    }
+}
+
+/**
+ * Identifies why a string could not be parsed as a move.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** The move ends too early (e.g. "Nb1-") */
+    Truncated,
+    /** A square is malformed (e.g. "i9") */
+    Square,
+    /** The squares are not separated by "-" or "x" */
+    Separator,
+    /** A promotion is malformed (e.g. "=" without a piece) */
+    Promotion,
+    /** The annotations following the move are malformed (i.e. why) */
+    Annotation(annotation::Error)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let msg = match self {
+	    Error::Truncated => "move ends too early",
+	    Error::Square => "malformed square",
+	    Error::Separator => "expected \"-\" or \"x\" between squares",
+	    Error::Promotion => "malformed promotion",
+	    Error::Annotation(e) => return write!(f,"{}",e)
+	};
+	write!(f,"{}",msg)
+    }
 }
 
 /**
  * Parse a given string into a Move.  If the string is invalid, then
- * an error is returned.
+ * an error is returned identifying the problem.  This never panics,
+ * whatever the input.
  */
-pub fn from_str(s1:&str, p:Player) -> Result<Box<dyn Move>,()> {
+pub fn from_str(s1:&str, p:Player) -> Result<Box<dyn Move>,Error> {
+    // Check for castling (queen side first as longer)
+    if let Some(s2) = s1.strip_prefix("O-O-O").or(s1.strip_prefix("0-0-0")) {
+	return annotate(Box::new(Castle{player: p, kingside: false}),s2);
+    } else if let Some(s2) = s1.strip_prefix("O-O").or(s1.strip_prefix("0-0")) {
+	return annotate(Box::new(Castle{player: p, kingside: true}),s2);
+    }
+    let mut lexer = Lexer{text: s1, index: 0};
     // Parse piece (if exists)
-    let (piece,s2) = parse_piece(s1,p);
+    let piece = lexer.piece(p).unwrap_or(Piece{player: p, kind: Kind::Pawn});
     // Parse origin
-    let (from, s3) = parse_square(s2)?;
+    let from = lexer.square()?;
     // Check whether this is a take or not
-    let (kind, s4) = parse_kind(s3);    
+    let kind = lexer.separator()?;
     // Parse piece (if exists)
-    let (taken,s5) = parse_piece(s4,p.flip());
+    let taken = lexer.piece(p.flip()).unwrap_or(Piece{player: p.flip(), kind: Kind::Pawn});
     // Parse destiation
-    let (to,  _s6) = parse_square(s5)?;
+    let to = lexer.square()?;
+    // Parse promotion (if exists)
+    let promote = lexer.promotion(p)?;
     // Create appropriate move
     let m : Box<dyn Move> = if kind {
-	Box::new(SimpleTake{piece,from,to,taken})	
+	Box::new(SimpleTake{piece,from,to,taken,promote})	
     } else {
-	Box::new(SimpleMove{piece,from,to})
+	Box::new(SimpleMove{piece,from,to,promote})
     };
-    // Done
-    Ok(m)
+    // Parse annotations (if any)
+    annotate(m,lexer.rest())
 }
 
 /**
- * Parse a single character piece (e.g. "Q", "K", "B", etc).  If no
- * valid character piece exists, then assume its a pawn.
+ * Attach the annotations given by the remainder of a move string to
+ * a move.  If there are none, the move is returned as is.
  */
-fn parse_piece(mut s:&str, p:Player) -> (Piece,&str) {
-    let r : Piece;
-    // Parse piece (if exists)       
-    if piece::is_char(&s[0..1]) {
-	// Piece given
-	r = piece::from_str(&s[0..1],p).unwrap();
-	s = skip(s,1);
+fn annotate(m: Box<dyn Move>, s: &str) -> Result<Box<dyn Move>,Error> {
+    let annotation = annotation::from_str(s).map_err(Error::Annotation)?;
+    if annotation.is_empty() {
+	Ok(m)
     } else {
-	r = match p {
-	    Player::White => WHITE_PAWN,
-	    Player::Black => BLACK_PAWN
-	};	
+	Ok(Box::new(Annotated{muve: m, annotation}))
     }
-    // Convert to appropriate player
-    (r,s)
 }
 
 /**
- * Parse a square (e.g. "a5", "b3", etc).  This can produce an error
- * as a location can be incorrectly specified.
+ * Reads the components of a move from a string one at a time.  This
+ * only ever advances over ASCII characters, hence the remaining text
+ * always starts on a character boundary (whatever the input).
  */
-fn parse_square(s:&str) -> Result<(Square,&str),()> {
-    // Parse square
-    let sq = square::from_str(&s[0..2])?;
-    // Done
-    Ok((sq,skip(s,2)))
+struct Lexer<'a> {
+    /**
+     * Text of the move
+     */
+    text: &'a str,
+    /**
+     * Position of the next byte to read
+     */
+    index: usize
 }
 
-/**
- * Parse the kind of a move.
- */
-fn parse_kind(s:&str) -> (bool,&str) {
-    let b = &s[0..1] == "x";
-    //
-    (b,skip(s,1))
+impl<'a> Lexer<'a> {
+    /**
+     * Look at the next byte without reading it.
+     */
+    fn peek(&self) -> Option<u8> {
+	self.text.as_bytes().get(self.index).copied()
+    }
+
+    /**
+     * Read a single character piece (e.g. "Q", "K", "B", etc) for a
+     * given player, if one is next.
+     */
+    fn piece(&mut self, p:Player) -> Option<Piece> {
+	let kind = match self.peek()? {
+	    b'N' => Kind::Knight,
+	    b'B' => Kind::Bishop,
+	    b'R' => Kind::Rook,
+	    b'Q' => Kind::Queen,
+	    b'K' => Kind::King,
+	    _ => { return None; }
+	};
+	self.index += 1;
+	Some(Piece{player: p, kind})
+    }
+
+    /**
+     * Read a square (e.g. "a5", "b3", etc).
+     */
+    fn square(&mut self) -> Result<Square,Error> {
+	let bytes = self.text.as_bytes();
+	match (bytes.get(self.index),bytes.get(self.index+1)) {
+	    (Some(c @ b'a'..=b'h'),Some(r @ b'1'..=b'8')) => {
+		self.index += 2;
+		Ok(Square::new(c - b'a',r - b'1'))
+	    }
+	    (_,None) => Err(Error::Truncated),
+	    _ => Err(Error::Square)
+	}
+    }
+
+    /**
+     * Read the separator between two squares, which determines
+     * whether this is a take ("x") or not ("-").
+     */
+    fn separator(&mut self) -> Result<bool,Error> {
+	let take = match self.peek() {
+	    Some(b'-') => false,
+	    Some(b'x') => true,
+	    Some(_) => { return Err(Error::Separator); }
+	    None => { return Err(Error::Truncated); }
+	};
+	self.index += 1;
+	Ok(take)
+    }
+
+    /**
+     * Read the piece a pawn is promoted to (e.g. "=Q" or just "Q"),
+     * if one is given.
+     */
+    fn promotion(&mut self, p:Player) -> Result<Option<Piece>,Error> {
+	// Equals is optional, but must be followed by a piece
+	if self.peek() == Some(b'=') {
+	    self.index += 1;
+	    self.piece(p).map(Some).ok_or(Error::Promotion)
+	} else {
+	    Ok(self.piece(p))
+	}
+    }
+
+    /**
+     * Get whatever remains after the components read so far.
+     */
+    fn rest(&self) -> &'a str {
+	&self.text[self.index..]
+    }
 }
 
 /**
- * Method for moving through a given string slice
+ * Check whether a given promotion is valid for a piece moving to a
+ * given square.  Specifically, a pawn reaching the final row must be
+ * promoted to a knight, bishop, rook or queen of the same player.
+ * Otherwise, no promotion is permitted.
  */
-fn skip(s:&str,i:usize) -> &str {
-    &s[i..]
+fn is_valid_promotion(piece:Piece, to:Square, promote:Option<Piece>) -> bool {
+    let last = match piece.player { Player::White => 7, Player::Black => 0 };
+    //
+    if piece.kind == Kind::Pawn && to.row() == last {
+	match promote {
+	    Some(q) => {
+		q.player == piece.player
+		    && matches!(q.kind, Kind::Knight | Kind::Bishop | Kind::Rook | Kind::Queen)
+	    }
+	    None => false
+	}
+    } else {
+	promote.is_none()
+    }
 }
 
 // ================================================================
@@ -112,46 +355,108 @@ pub struct SimpleMove {
     /**
      * Ending position of piece
      */
-    to: Square
+    to: Square,
+    /**
+     * Piece which a pawn is promoted to (if applicable)
+     */
+    promote: Option<Piece>
 }
 
-/**
- * Logic for deciding whether or not a physical move can be applied.
- * If the move is invalid, then None is returned.
- */
-impl Move for SimpleMove {
-    fn apply(&self, mut board: Board) -> Option<Board> {
+impl SimpleMove {
+    /**
+     * Construct a move of a given piece between two squares, along
+     * with the piece a pawn is promoted to (if applicable).
+     */
+    pub fn new(piece: Piece, from: Square, to: Square, promote: Option<Piece>) -> SimpleMove {
+	SimpleMove{piece,from,to,promote}
+    }
+
+    /**
+     * Check whether this move is valid for a given board, producing
+     * the reason when it is not.  Note, this does not consider
+     * whether the move leaves the king in check.
+     */
+    fn is_valid(&self, board: &Board) -> Result<(),Invalid> {
 	// Read out piece at from position
         let p = board.get(self.from);
 	// Read out piece at to position (should be blank)
 	let t = board.get(self.to);
 	// Check piece matches what is expected
-	if p == self.piece && t == BLANK && p.can_move(board,self.from,self.to) {
-	    // Remove piece from board
-	    board = board.set(self.from,BLANK);
-	    // Put piece at new position
-	    board = board.set(self.to,p);
-	    // Done
-	    return Some(board);
+	if p != self.piece {
+	    Err(Invalid::Piece)
+	} else if p.player != board.turn() {
+	    Err(Invalid::Turn)
+	} else if t != BLANK {
+	    Err(Invalid::Occupied)
+	} else if !p.can_move(*board,self.from,self.to) {
+	    Err(Invalid::Movement)
+	} else if !is_valid_promotion(p,self.to,self.promote) {
+	    Err(Invalid::Promotion)
+	} else {
+	    Ok(())
+	}
+    }
+}
+
+/**
+ * Logic for deciding whether or not a physical move can be applied.
+ * If the move is invalid, then None is returned.
+ */
+impl Move for SimpleMove {
+    fn make(&self, board: &mut Board) -> Option<Undo> {
+	if self.is_valid(board).is_ok() {
+	    let mut undo = board.checkpoint();
+	    // Move piece to new position
+	    board.put(self.from,BLANK,&mut undo);
+	    board.put(self.to,self.promote.unwrap_or(self.piece),&mut undo);
+	    // Update game state
+	    board.advance(self.piece,self.from,self.to,false);
+	    // Check king not left in check
+	    if !board.in_check(board.turn().flip()) {
+		return Some(undo);
+	    }
+	    board.restore(&undo);
 	}
 	// Failure
 	None
     }
+
+    fn from(&self) -> Square {
+	self.from
+    }
+
+    fn to(&self) -> Square {
+	self.to
+    }
+
+    fn promotion(&self) -> Option<Piece> {
+	self.promote
+    }
+
+    fn reason(&self, board: &Board) -> Option<Invalid> {
+	match self.is_valid(board) {
+	    Err(e) => Some(e),
+	    Ok(_) if self.apply(*board).is_none() => Some(Invalid::Check),
+	    Ok(_) => None
+	}
+    }
 }
 
 /**
- * Generic debugging output.
+ * Moves are written in long algebraic notation, where pieces are
+ * always uppercase (as when parsed).
  */
 impl fmt::Display for SimpleMove {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 	match self.piece.kind {
 	    Kind::Pawn => {
-		write!(f,"{}-{}",self.from,self.to)
+		write!(f,"{}-{}",self.from,self.to)?;
 	    }
 	    _ => {
-		write!(f,"{}{}-{}",self.piece,self.from,self.to)
+		write!(f,"{}{}-{}",self.piece.kind.to_white_string(),self.from,self.to)?;
 	    }
-	}        
+	}
+	write_promotion(f,self.promote)
     }
 }
 
@@ -179,7 +484,65 @@ pub struct SimpleTake {
     /**
      * Piece being taken
      */
-    taken: Piece
+    taken: Piece,
+    /**
+     * Piece which a pawn is promoted to (if applicable)
+     */
+    promote: Option<Piece>
+}
+
+impl SimpleTake {
+    /**
+     * Construct a move of a given piece between two squares which
+     * takes a given piece, along with the piece a pawn is promoted to
+     * (if applicable).
+     */
+    pub fn new(piece: Piece, from: Square, to: Square, taken: Piece, promote: Option<Piece>) -> SimpleTake {
+	SimpleTake{piece,from,to,taken,promote}
+    }
+
+    /**
+     * Check whether this move is valid for a given board, producing
+     * the square of the piece being taken (success) or the reason it
+     * is not (failure).  The taken piece is normally on the ending
+     * position, except when a pawn is taken en passant.  Note, this
+     * does not consider whether the move leaves the king in check.
+     */
+    fn is_valid(&self, board: &Board) -> Result<Square,Invalid> {
+	// Read out piece at from position
+        let p = board.get(self.from);
+	// Read out piece at to position
+	let t = board.get(self.to);
+	// Check piece matches what is expected
+	if p != self.piece {
+	    return Err(Invalid::Piece);
+	} else if p.player != board.turn() {
+	    return Err(Invalid::Turn);
+	} else if !is_valid_promotion(p,self.to,self.promote) {
+	    return Err(Invalid::Promotion);
+	} else if self.taken.kind == Kind::Blank || self.taken.player == p.player {
+	    // Only the opponent's pieces can be taken
+	    return Err(Invalid::Capture);
+	}
+	if t == self.taken && p.can_move(*board,self.from,self.to) {
+	    return Ok(self.to);
+	}
+	// Check for pawn taken en passant
+	if p.kind == Kind::Pawn && board.en_passant() == Some(self.to)
+	    && p.can_attack(*board,self.from,self.to) {
+	    // Pawn being taken is behind the ending position
+	    let victim = Square::new(self.to.column(),self.from.row());
+	    if board.get(victim) == self.taken {
+		return Ok(victim);
+	    }
+	}
+	// Failure
+	if t != self.taken {
+	    Err(Invalid::Capture)
+	} else {
+	    Err(Invalid::Movement)
+	}
+    }
 }
 
 /**
@@ -187,43 +550,313 @@ pub struct SimpleTake {
  * If the move is invalid, then None is returned.
  */
 impl Move for SimpleTake {
-    fn apply(&self, mut board: Board) -> Option<Board> {
-	// Read out piece at from position
-        let p = board.get(self.from);
-	// Read out piece at to position (should be blank)
-	let t = board.get(self.to);
-	// Check piece matches what is expected
-	if p == self.piece && t == self.taken && p.can_move(board,self.from,self.to) {
-	    // Remove piece from board
-	    board = board.set(self.from,BLANK);
-	    // Put piece at new position
-	    board = board.set(self.to,p);
-	    // Done
-	    return Some(board);
+    fn make(&self, board: &mut Board) -> Option<Undo> {
+	if let Ok(victim) = self.is_valid(board) {
+	    let mut undo = board.checkpoint();
+	    // Remove piece being taken
+	    board.put(victim,BLANK,&mut undo);
+	    // Move piece to new position
+	    board.put(self.from,BLANK,&mut undo);
+	    board.put(self.to,self.promote.unwrap_or(self.piece),&mut undo);
+	    // Update game state
+	    board.advance(self.piece,self.from,self.to,true);
+	    // Check king not left in check
+	    if !board.in_check(board.turn().flip()) {
+		return Some(undo);
+	    }
+	    board.restore(&undo);
 	}
 	// Failure
 	None
     }
+
+    fn from(&self) -> Square {
+	self.from
+    }
+
+    fn to(&self) -> Square {
+	self.to
+    }
+
+    fn promotion(&self) -> Option<Piece> {
+	self.promote
+    }
+
+    fn reason(&self, board: &Board) -> Option<Invalid> {
+	match self.is_valid(board) {
+	    Err(e) => Some(e),
+	    Ok(_) if self.apply(*board).is_none() => Some(Invalid::Check),
+	    Ok(_) => None
+	}
+    }
 }
 
 /**
- * Generic debugging output.
+ * Takes are written in long algebraic notation, where pieces are
+ * always uppercase (as when parsed).
  */
 impl fmt::Display for SimpleTake {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 	match (self.piece.kind,self.taken.kind) {
 	    (Kind::Pawn,Kind::Pawn) => {
-		write!(f,"{}x{}",self.from,self.to)
+		write!(f,"{}x{}",self.from,self.to)?;
 	    }
 	    (Kind::Pawn,_) => {
-		write!(f,"{}x{}{}",self.from,self.taken,self.to)
+		write!(f,"{}x{}{}",self.from,self.taken.kind.to_white_string(),self.to)?;
 	    }
 	    (_,Kind::Pawn) => {
-		write!(f,"{}{}x{}",self.piece,self.from,self.to)
+		write!(f,"{}{}x{}",self.piece.kind.to_white_string(),self.from,self.to)?;
 	    }	    
 	    _ => {
-		write!(f,"{}{}x{}{}",self.piece,self.from,self.taken,self.to)
+		write!(f,"{}{}x{}{}",self.piece.kind.to_white_string(),self.from,
+		       self.taken.kind.to_white_string(),self.to)?;
+	    }
+	}
+	write_promotion(f,self.promote)
+    }
+}
+
+/**
+ * Write out the piece a pawn is promoted to (if applicable).
+ */
+fn write_promotion(f: &mut fmt::Formatter, promote: Option<Piece>) -> fmt::Result {
+    match promote {
+	Some(q) => write!(f,"={}",q.kind.to_white_string()),
+	None => Ok(())
+    }
+}
+
+// ================================================================
+// Castle
+// ================================================================
+
+/**
+ * Represents a castling move, either on the king side ("O-O") or the
+ * queen side ("O-O-O").  In both cases, the king moves two squares
+ * towards the rook, and the rook moves to the square the king passed
+ * over.
+ */
+pub struct Castle {
+    /**
+     * Player doing the castling
+     */
+    player: Player,
+    /**
+     * Whether castling on the king side (or queen side)
+     */
+    kingside: bool
+}
+
+impl Castle {
+    /**
+     * Construct a castling move for a given player on either the king
+     * side or the queen side.
+     */
+    pub fn new(player: Player, kingside: bool) -> Castle {
+	Castle{player,kingside}
+    }
+
+    /**
+     * Determine the starting and ending positions of the king, and
+     * then those of the rook.
+     */
+    fn squares(&self) -> (Square,Square,Square,Square) {
+	let row = match self.player { Player::White => 0, Player::Black => 7 };
+	if self.kingside {
+	    (Square::new(4,row),Square::new(6,row),Square::new(7,row),Square::new(5,row))
+	} else {
+	    (Square::new(4,row),Square::new(2,row),Square::new(0,row),Square::new(3,row))
+	}
+    }
+
+    /**
+     * Check whether this move is valid for a given board.  For this,
+     * the king and rook must not have moved before, all squares
+     * between them must be blank and the king cannot be in, pass
+     * through or end up in check.
+     */
+    fn is_valid(&self, board: &Board) -> Result<(),Invalid> {
+	let (king_from,_,rook_from,_) = self.squares();
+	let row = king_from.row();
+	let right = match (self.player,self.kingside) {
+	    (Player::White,true) => WHITE_KINGSIDE,
+	    (Player::White,false) => WHITE_QUEENSIDE,
+	    (Player::Black,true) => BLACK_KINGSIDE,
+	    (Player::Black,false) => BLACK_QUEENSIDE
+	};
+	let king = Piece{player: self.player, kind: Kind::King};
+	let rook = Piece{player: self.player, kind: Kind::Rook};
+	//
+	if board.turn() != self.player {
+	    return Err(Invalid::Turn);
+	} else if (board.castling() & right) == 0
+	    || board.get(king_from) != king || board.get(rook_from) != rook {
+		return Err(Invalid::CastlingRights);
+	    }
+	// Check squares between king and rook are blank
+	let (lo,hi) = if self.kingside { (5,6) } else { (1,3) };
+	for col in lo..=hi {
+	    if board.get(Square::new(col,row)) != BLANK {
+		return Err(Invalid::CastlingBlocked);
+	    }
+	}
+	// Check king never in check along the way
+	let (lo,hi) = if self.kingside { (4,6) } else { (2,4) };
+	for col in lo..=hi {
+	    if board.is_attacked(Square::new(col,row),self.player.flip()) {
+		return Err(Invalid::CastlingCheck);
 	    }
-	}        
+	}
+	// Success
+	Ok(())
+    }
+}
+
+/**
+ * Logic for deciding whether or not a castling move can be applied.
+ * If the move is invalid, then None is returned.
+ */
+impl Move for Castle {
+    fn make(&self, board: &mut Board) -> Option<Undo> {
+	if self.is_valid(board).is_ok() {
+	    let (king_from,king_to,rook_from,rook_to) = self.squares();
+	    let king = board.get(king_from);
+	    let rook = board.get(rook_from);
+	    let mut undo = board.checkpoint();
+	    // Move king and rook
+	    board.put(king_from,BLANK,&mut undo);
+	    board.put(rook_from,BLANK,&mut undo);
+	    board.put(king_to,king,&mut undo);
+	    board.put(rook_to,rook,&mut undo);
+	    // Update game state
+	    board.advance(king,king_from,king_to,false);
+	    // Check king not left in check
+	    if !board.in_check(board.turn().flip()) {
+		return Some(undo);
+	    }
+	    board.restore(&undo);
+	}
+	// Failure
+	None
+    }
+
+    fn from(&self) -> Square {
+	self.squares().0
+    }
+
+    fn to(&self) -> Square {
+	self.squares().1
+    }
+
+    fn reason(&self, board: &Board) -> Option<Invalid> {
+	match self.is_valid(board) {
+	    Err(e) => Some(e),
+	    Ok(_) if self.apply(*board).is_none() => Some(Invalid::Check),
+	    Ok(_) => None
+	}
+    }
+}
+
+/**
+ * Castling is written using the letter "O".
+ */
+impl fmt::Display for Castle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	if self.kingside {
+	    write!(f,"O-O")
+	} else {
+	    write!(f,"O-O-O")
+	}
+    }
+}
+
+// ================================================================
+// Annotated
+// ================================================================
+
+/**
+ * Represents a move written with annotations, such as "Qd1-h5+!?" or
+ * "e5xd6e.p.".  Claims made by the annotations are checked when the
+ * move is applied, so a move marked as check must give check, a move
+ * marked as checkmate must give checkmate, and a move marked as en
+ * passant must take en passant.  Glyphs are not checked.
+ */
+pub struct Annotated {
+    /**
+     * Move being annotated
+     */
+    muve: Box<dyn Move>,
+    /**
+     * Annotations written after the move
+     */
+    annotation: Annotation
+}
+
+impl Annotated {
+    /**
+     * Check whether the claims made by the annotations hold for the
+     * boards before and after the move.
+     */
+    fn is_valid(&self, before: &Board, after: &Board) -> Result<(),Invalid> {
+	let (from,to) = (self.muve.from(),self.muve.to());
+	let en_passant = before.get(from).kind == Kind::Pawn
+	    && from.column() != to.column() && before.get(to) == BLANK;
+	if self.annotation.en_passant && !en_passant {
+	    return Err(Invalid::EnPassantClaim);
+	}
+	match self.annotation.check {
+	    Some(Check::Check) if !after.in_check(after.turn()) => Err(Invalid::CheckClaim),
+	    Some(Check::Mate) if !movegen::is_checkmate(after) => Err(Invalid::MateClaim),
+	    _ => Ok(())
+	}
+    }
+}
+
+/**
+ * Logic for deciding whether or not an annotated move can be applied.
+ * This is the case when the underlying move can be applied, and the
+ * claims made by the annotations hold.
+ */
+impl Move for Annotated {
+    fn make(&self, board: &mut Board) -> Option<Undo> {
+	let before = *board;
+	let undo = self.muve.make(board)?;
+	if self.is_valid(&before,board).is_ok() {
+	    return Some(undo);
+	}
+	board.restore(&undo);
+	None
+    }
+
+    fn from(&self) -> Square {
+	self.muve.from()
+    }
+
+    fn to(&self) -> Square {
+	self.muve.to()
+    }
+
+    fn promotion(&self) -> Option<Piece> {
+	self.muve.promotion()
+    }
+
+    fn reason(&self, board: &Board) -> Option<Invalid> {
+	match self.muve.apply(*board) {
+	    None => self.muve.reason(board),
+	    Some(after) => self.is_valid(board,&after).err()
+	}
+    }
+
+    fn annotation(&self) -> Annotation {
+	self.annotation
+    }
+}
+
+/**
+ * Annotated moves are written as the move followed by its annotations.
+ */
+impl fmt::Display for Annotated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	write!(f,"{}{}",self.muve,self.annotation)
     }
 }
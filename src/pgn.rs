@@ -0,0 +1,323 @@
+use std::fmt;
+use super::board::*;
+use super::error::Located;
+use super::fen;
+use super::game::{Game, Outcome};
+use super::muve::Move;
+use super::san;
+
+/**
+ * A game read from a PGN database, along with its tags.
+ */
+pub struct Record {
+    /**
+     * Tags given before the game (e.g. "White"), in order
+     */
+    pub tags: Vec<(String,String)>,
+    /**
+     * Board the game starts from, which is given by the "FEN" tag (if
+     * any)
+     */
+    pub start: Board,
+    /**
+     * Moves of the main line, and the result
+     */
+    pub game: Game
+}
+
+impl Record {
+    /**
+     * Get the value of a given tag (if it is given).
+     */
+    pub fn tag(&self, name: &str) -> Option<&str> {
+	self.tags.iter().find(|(n,_)| n == name).map(|(_,v)| v.as_str())
+    }
+}
+
+/**
+ * Identifies what is wrong with a game in a PGN database.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /** A tag pair is malformed (e.g. its value is not quoted) */
+    Tag,
+    /** The position given by the "FEN" tag is invalid (i.e. why) */
+    Fen(fen::Error),
+    /** A comment or variation is not closed */
+    Unclosed,
+    /** A move cannot be played (i.e. its ply, text and why) */
+    Move(usize,String,san::Error)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    ErrorKind::Tag => write!(f,"malformed tag pair"),
+	    ErrorKind::Fen(e) => write!(f,"invalid FEN tag ({})",e),
+	    ErrorKind::Unclosed => write!(f,"comment or variation is not closed"),
+	    ErrorKind::Move(p,s,e) => write!(f,"invalid move \"{}\" at ply {} ({})",s,p,e)
+	}
+    }
+}
+
+/**
+ * Describes why a game in a PGN database could not be read, including
+ * where the problem was found.
+ */
+pub type Error = Located<ErrorKind>;
+
+/**
+ * A game being read, which becomes a record (or an error) once it
+ * ends.
+ */
+#[derive(Default)]
+struct Pending {
+    tags: Vec<(String,String)>,
+    start: Option<Board>,
+    board: Option<Board>,
+    moves: Vec<Box<dyn Move>>,
+    error: Option<Error>,
+    /** Whether anything has been read */
+    started: bool
+}
+
+impl Pending {
+    fn finish(self, result: Option<Outcome>) -> Result<Record,Error> {
+	match self.error {
+	    Some(e) => Err(e),
+	    None => Ok(Record{tags: self.tags, start: self.start.unwrap_or(INITIAL), game: Game::new(self.moves,result)})
+	}
+    }
+
+    fn fail(&mut self, line: usize, kind: ErrorKind) {
+	if self.error.is_none() {
+	    self.error = Some(Error{line, kind});
+	}
+    }
+}
+
+/**
+ * Read every game in a PGN database.  Only the main line of each game
+ * is read, so comments, variations and annotation glyphs are skipped.
+ * Moves are read as SAN and must be legal.
+ */
+pub fn read(text: &str) -> Vec<Result<Record,Error>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut records = Vec::new();
+    let mut game = Pending::default();
+    let (mut i, mut line) = (0,1);
+    while i < chars.len() {
+	let c = chars[i];
+	if c == '\n' {
+	    line += 1;
+	    i += 1;
+	} else if c.is_whitespace() {
+	    i += 1;
+	} else if c == '[' {
+	    // A tag after moves begins the next game
+	    if !game.moves.is_empty() || game.board.is_some() {
+		records.push(std::mem::take(&mut game).finish(None));
+	    }
+	    let end = chars[i..].iter().position(|&c| c == ']' || c == '\n').map(|n| i + n);
+	    let text: String = chars[i + 1..end.unwrap_or(chars.len())].iter().collect();
+	    game.started = true;
+	    match (end.map(|e| chars[e]),parse_tag(&text)) {
+		(Some(']'),Some((name,value))) => {
+		    if name == "FEN" {
+			match fen::from_str(&value) {
+			    Ok(b) => game.start = Some(b),
+			    Err(e) => game.fail(line,ErrorKind::Fen(e))
+			}
+		    }
+		    game.tags.push((name,value));
+		}
+		_ => game.fail(line,ErrorKind::Tag)
+	    }
+	    i = end.map(|e| e + 1).unwrap_or(chars.len());
+	} else if c == ';' || (c == '%' && (i == 0 || chars[i - 1] == '\n')) {
+	    // Rest of line comment or escape
+	    i += chars[i..].iter().position(|&c| c == '\n').unwrap_or(chars.len() - i);
+	} else if c == '{' || c == '(' {
+	    // Comment or (possibly nested) variation
+	    let start = line;
+	    match skip(&chars,i,&mut line) {
+		Some(end) => i = end,
+		None => {
+		    game.fail(start,ErrorKind::Unclosed);
+		    i = chars.len();
+		}
+	    }
+	} else {
+	    let end = chars[i..].iter().position(|&c| c.is_whitespace() || "{}()[];".contains(c))
+		.map(|n| i + n.max(1)).unwrap_or(chars.len());
+	    let token: String = chars[i..end].iter().collect();
+	    i = end;
+	    game.started = true;
+	    if let Some(result) = parse_result(&token) {
+		records.push(std::mem::take(&mut game).finish(Some(result)));
+		continue;
+	    }
+	    let token = strip_number(&token);
+	    if token.is_empty() || token.starts_with('$') || game.error.is_some() {
+		continue;
+	    }
+	    let board = *game.board.get_or_insert(game.start.unwrap_or(INITIAL));
+	    match san::from_str(&board,token) {
+		Ok(m) => {
+		    game.board = m.apply(board);
+		    game.moves.push(m);
+		}
+		Err(e) => {
+		    let ply = game.moves.len() + 1;
+		    game.fail(line,ErrorKind::Move(ply,token.to_string(),e));
+		}
+	    }
+	}
+    }
+    if game.started {
+	records.push(game.finish(None));
+    }
+    records
+}
+
+/**
+ * Parse the contents of a tag pair (e.g. `White "Carlsen"`) into its
+ * name and value.  Within the value, backslashes escape quotes and
+ * backslashes.
+ */
+fn parse_tag(s: &str) -> Option<(String,String)> {
+    let (name,rest) = s.trim().split_once(char::is_whitespace)?;
+    let rest = rest.trim().strip_prefix('"')?.strip_suffix('"')?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+	return None;
+    }
+    let mut value = String::new();
+    let mut escaped = false;
+    for c in rest.chars() {
+	if c == '\\' && !escaped {
+	    escaped = true;
+	} else {
+	    value.push(c);
+	    escaped = false;
+	}
+    }
+    Some((name.to_string(),value))
+}
+
+/**
+ * Skip a comment or variation starting at a given index, producing
+ * the index after it ends (or nothing if it does not end).
+ * Variations can contain comments and other variations.
+ */
+fn skip(chars: &[char], mut i: usize, line: &mut usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut comment = false;
+    while i < chars.len() {
+	match chars[i] {
+	    '\n' => *line += 1,
+	    '}' if comment => {
+		comment = false;
+		if depth == 0 { return Some(i + 1); }
+	    }
+	    _ if comment => {}
+	    '{' => comment = true,
+	    '(' => depth += 1,
+	    ')' => {
+		depth -= 1;
+		if depth == 0 { return Some(i + 1); }
+	    }
+	    _ => {}
+	}
+	i += 1;
+    }
+    None
+}
+
+/**
+ * Remove any move number (e.g. "12." or "12...") from the start of a
+ * token.
+ */
+fn strip_number(token: &str) -> &str {
+    let digits = token.bytes().take_while(|b| b.is_ascii_digit()).count();
+    let rest = &token[digits..];
+    if rest.starts_with('.') || (digits > 0 && rest.is_empty()) {
+	rest.trim_start_matches('.')
+    } else {
+	token
+    }
+}
+
+/**
+ * Parse the result which ends a game (e.g. "1-0").
+ */
+fn parse_result(token: &str) -> Option<Outcome> {
+    match token {
+	"1-0" => Some(Outcome::WhiteWins),
+	"0-1" => Some(Outcome::BlackWins),
+	"1/2-1/2" => Some(Outcome::Draw),
+	"*" => Some(Outcome::Unfinished),
+	_ => None
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+const DATABASE: &str = "[Event \"Casual\"]
+[White \"A. \\\"Player\\\"\"]
+[Black \"B\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Qh5 {threatening mate} Nc6 (2... Nf6?? 3. Qxe5+ (3. Qxf7#?) ) 3. Bc4
+Nf6?? $4 4. Qxf7# 1-0
+
+[Event \"Second\"]
+[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]
+
+1. O-O-O Kf7 ; rest of line
+2. Rd7+ *
+
+[Event \"Broken\"]
+
+1. e4 e5 2. Nf6 1/2-1/2
+1. d4 d5
+";
+
+#[test]
+fn test_01() {
+    let records = read(DATABASE);
+    assert_eq!(records.len(), 4);
+    let r = records[0].as_ref().unwrap();
+    assert_eq!(r.tag("White"), Some("A. \"Player\""));
+    assert_eq!(r.tag("Site"), None);
+    assert_eq!(r.game.len(), 7);
+    assert_eq!(r.game.result(), Some(Outcome::WhiteWins));
+    assert!(super::movegen::is_checkmate(&r.game.apply(r.start).unwrap()));
+}
+
+#[test]
+fn test_02() {
+    let records = read(DATABASE);
+    // Starting from a position
+    let r = records[1].as_ref().unwrap();
+    assert_eq!(fen::to_string(&r.start), "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+    assert_eq!(r.game.len(), 3);
+    assert_eq!(r.game.result(), Some(Outcome::Unfinished));
+    // Illegal moves
+    let e = records[2].as_ref().err().unwrap();
+    assert_eq!(e.line, 17);
+    assert_eq!(e.kind, ErrorKind::Move(3,"Nf6".to_string(),san::Error::Illegal));
+    // Games without tags or results
+    let r = records[3].as_ref().unwrap();
+    assert_eq!(r.game.len(), 2);
+    assert_eq!(r.game.result(), None);
+}
+
+#[test]
+fn test_03() {
+    let errors: Vec<ErrorKind> = ["[Event Casual]\n1. e4 *", "[FEN \"8/8 w\"]\n*", "1. e4 {never closed", "1. e4 (1. d4 *"]
+	.iter().map(|s| read(s)[0].as_ref().err().unwrap().kind.clone()).collect();
+    assert_eq!(errors, vec![ErrorKind::Tag, ErrorKind::Fen(fen::Error::Fields), ErrorKind::Unclosed, ErrorKind::Unclosed]);
+}
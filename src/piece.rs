@@ -11,14 +11,21 @@ use super::board::Board;
 /**
  * The kind of a piece (e.g. kind, queen, etc)
  */
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Kind {
+    /** No piece (i.e. an empty square) */
     Blank,
+    /** A pawn */
     Pawn,
+    /** A knight */
     Knight,
+    /** A bishop */
     Bishop,
+    /** A rook */
     Rook,
+    /** A queen */
     Queen,
+    /** A king */
     King,
 }
 
@@ -62,15 +69,34 @@ impl Kind {
             Kind::King => "k"
         }
     }
+
+    /**
+     * Get the standard point value of a piece kind (e.g. 3 for a
+     * knight).  The king has no value, since it is never taken.
+     */
+    pub fn value(self) -> u32 {
+	match self {
+	    Kind::Pawn => 1,
+	    Kind::Knight | Kind::Bishop => 3,
+	    Kind::Rook => 5,
+	    Kind::Queen => 9,
+	    Kind::Blank | Kind::King => 0
+	}
+    }
 }
 
 // ==========================================================================
 // Player
 // ==========================================================================
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+/**
+ * The players of the game.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Player {
+    /** The player moving first */
     White,
+    /** The player moving second */
     Black
 }
 
@@ -97,9 +123,11 @@ impl Player {
 /**
  * Define a given piece on the board
  */
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Piece {
+    /** Player who owns this piece */
     pub player: Player,
+    /** Kind of this piece */
     pub kind: Kind,
 }
 
@@ -125,6 +153,19 @@ impl Piece {
 	    Kind::King => can_king_move(board,from,to)		
 	}
     }
+
+    /**
+     * Check whether this piece attacks a given square on the board
+     * from a given position.  This is the same as for `can_move()`,
+     * except that pawns attack only diagonally (and regardless of
+     * what occupies the target square).
+     */
+    pub fn can_attack(&self, board: Board, from: Square, to: Square) -> bool {
+	match self.kind {
+	    Kind::Pawn => can_pawn_attack(self.player,from,to),
+	    _ => self.can_move(board,from,to)
+	}
+    }
     
     /** 
      * Flip to other player.  For example, if this is white then it
@@ -141,6 +182,7 @@ impl Piece {
  * error if invalid string).  In doing this, we must determine which
  * player the piece is for.
  */
+#[allow(clippy::result_unit_err)]
 pub fn from_str(s: &str, p:Player) -> Result<Piece, ()> {
     match s {
         "" => Ok(Piece{player: p,kind: Kind::Pawn}),
@@ -197,6 +239,17 @@ pub fn can_pawn_move(board: Board, player: Player, from: Square, to: Square) ->
     false
 }
 
+/**
+ * Determine whether a pawn for a given player attacks a given square.
+ * Pawns attack the squares diagonally in front of them.
+ */
+pub fn can_pawn_attack(player: Player, from: Square, to: Square) -> bool {
+    // Get direction of movement for given player
+    let dir : i8 = match player { Player::White => 1, Player::Black => -1 };
+    //
+    (from.row() as i8 + dir) == to.row() as i8 && abs_diff_column(from,to) == 1
+}
+
 /**
  * Determine whether a given knight move is valid (or not).
  */
@@ -348,56 +401,69 @@ impl fmt::Display for Piece {
 // Piece Constants
 // ======================================================
 
+/** An empty square */
 pub const BLANK: Piece = Piece {
     player: Player::White,
     kind: Kind::Blank,
 };
 
+/** White pawn */
 pub const WHITE_PAWN: Piece = Piece {
     player: Player::White,
     kind: Kind::Pawn,
 };
+/** White knight */
 pub const WHITE_KNIGHT: Piece = Piece {
     player: Player::White,
     kind: Kind::Knight,
 };
+/** White bishop */
 pub const WHITE_BISHOP: Piece = Piece {
     player: Player::White,
     kind: Kind::Bishop,
 };
+/** White rook */
 pub const WHITE_ROOK: Piece = Piece {
     player: Player::White,
     kind: Kind::Rook,
 };
+/** White queen */
 pub const WHITE_QUEEN: Piece = Piece {
     player: Player::White,
     kind: Kind::Queen,
 };
+/** White king */
 pub const WHITE_KING: Piece = Piece {
     player: Player::White,
     kind: Kind::King,
 };
 // Black's pieces
+/** Black pawn */
 pub const BLACK_PAWN: Piece = Piece {
     player: Player::Black,
     kind: Kind::Pawn,
 };
+/** Black knight */
 pub const BLACK_KNIGHT: Piece = Piece {
     player: Player::Black,
     kind: Kind::Knight,
 };
+/** Black bishop */
 pub const BLACK_BISHOP: Piece = Piece {
     player: Player::Black,
     kind: Kind::Bishop,
 };
+/** Black rook */
 pub const BLACK_ROOK: Piece = Piece {
     player: Player::Black,
     kind: Kind::Rook,
 };
+/** Black queen */
 pub const BLACK_QUEEN: Piece = Piece {
     player: Player::Black,
     kind: Kind::Queen,
 };
+/** Black king */
 pub const BLACK_KING: Piece = Piece {
     player: Player::Black,
     kind: Kind::King,
@@ -476,3 +542,14 @@ fn test_13() {
 fn test_14() {
     assert!(from_str("p",Player::White).is_err());
 }
+
+#[test]
+fn test_15() {
+    // Flipping a piece twice gives the same piece
+    let pieces = [BLANK, WHITE_PAWN, WHITE_KNIGHT, WHITE_BISHOP, WHITE_ROOK, WHITE_QUEEN, WHITE_KING,
+		  BLACK_PAWN, BLACK_KNIGHT, BLACK_BISHOP, BLACK_ROOK, BLACK_QUEEN, BLACK_KING];
+    for p in pieces {
+	assert_eq!(p.flip().flip(), p);
+	assert_eq!(p.flip().kind, p.kind);
+    }
+}
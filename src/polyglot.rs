@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::fmt;
+use super::board::Board;
+use super::game::{Game, Outcome};
+use super::movegen;
+use super::muve::Move;
+use super::piece::*;
+use super::square::Square;
+
+/**
+ * Number of bytes in each entry of a Polyglot book.
+ */
+const ENTRY: usize = 16;
+
+/**
+ * Identifies why a Polyglot book could not be read.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** The book is not a whole number of entries (i.e. its length in bytes) */
+    Length(usize),
+    /** The entries of the book are not sorted by key (i.e. the first out of order) */
+    Unsorted(usize)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Error::Length(n) => write!(f,"length {} is not a multiple of {}",n,ENTRY),
+	    Error::Unsorted(i) => write!(f,"entry {} is out of order",i)
+	}
+    }
+}
+
+/**
+ * A single entry of a Polyglot book, giving a move which can be played
+ * in a position and how often it should be chosen.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /**
+     * Zobrist hash of the position (see `Board::hash()`)
+     */
+    pub key: u64,
+    /**
+     * Encoded move (see `encode()`)
+     */
+    pub muve: u16,
+    /**
+     * How often the move should be chosen, relative to the other
+     * moves for the position
+     */
+    pub weight: u16,
+    /**
+     * Learning data, which is not used here
+     */
+    pub learn: u32
+}
+
+/**
+ * An opening book in the Polyglot format, where entries are sorted by
+ * the key of their position.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<Entry>
+}
+
+impl Book {
+    /**
+     * Read a book from the contents of a Polyglot `.bin` file, where
+     * each entry is 16 bytes in big-endian order.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Book,Error> {
+	if !bytes.len().is_multiple_of(ENTRY) {
+	    return Err(Error::Length(bytes.len()));
+	}
+	let mut entries: Vec<Entry> = Vec::with_capacity(bytes.len() / ENTRY);
+	for (i,e) in bytes.chunks_exact(ENTRY).enumerate() {
+	    let entry = Entry{
+		key: number(&e[0..8]),
+		muve: number(&e[8..10]) as u16,
+		weight: number(&e[10..12]) as u16,
+		learn: number(&e[12..16]) as u32
+	    };
+	    if entries.last().is_some_and(|l| l.key > entry.key) {
+		return Err(Error::Unsorted(i));
+	    }
+	    entries.push(entry);
+	}
+	Ok(Book{entries})
+    }
+
+    /**
+     * Write this book in the format of a Polyglot `.bin` file.
+     */
+    pub fn to_bytes(&self) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY);
+	for e in &self.entries {
+	    bytes.extend_from_slice(&e.key.to_be_bytes());
+	    bytes.extend_from_slice(&e.muve.to_be_bytes());
+	    bytes.extend_from_slice(&e.weight.to_be_bytes());
+	    bytes.extend_from_slice(&e.learn.to_be_bytes());
+	}
+	bytes
+    }
+
+    /**
+     * Get the entries of this book, sorted by key.
+     */
+    pub fn entries(&self) -> &[Entry] {
+	&self.entries
+    }
+
+    /**
+     * Get the number of entries in this book.
+     */
+    pub fn len(&self) -> usize {
+	self.entries.len()
+    }
+
+    /**
+     * Check whether this book has no entries.
+     */
+    pub fn is_empty(&self) -> bool {
+	self.entries.is_empty()
+    }
+
+    /**
+     * Find the book moves for a given board, along with their weights,
+     * in the order they appear in the book.  Entries whose moves are
+     * not legal on the board are ignored.
+     */
+    pub fn lookup(&self, board: &Board) -> Vec<(Box<dyn Move>,u16)> {
+	let key = board.hash();
+	let start = self.entries.partition_point(|e| e.key < key);
+	let mut legal = movegen::legal_moves(board);
+	let mut moves = Vec::new();
+	for e in self.entries[start..].iter().take_while(|e| e.key == key) {
+	    if let Some(i) = legal.iter().position(|m| encode(board,m.as_ref()) == e.muve) {
+		moves.push((legal.swap_remove(i),e.weight));
+	    }
+	}
+	moves
+    }
+
+    /**
+     * Get the weight of a given move on a given board (if it is in
+     * this book).
+     */
+    pub fn weight(&self, board: &Board, m: &dyn Move) -> Option<u16> {
+	let key = board.hash();
+	let code = encode(board,m);
+	let start = self.entries.partition_point(|e| e.key < key);
+	self.entries[start..].iter().take_while(|e| e.key == key).find(|e| e.muve == code).map(|e| e.weight)
+    }
+}
+
+/**
+ * Builds a Polyglot book from games, where each move played scores 2
+ * for a win, 1 for a draw (or unknown result) and 0 for a loss, from
+ * the point of view of the player making it.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    scores: HashMap<(u64,u16),u64>
+}
+
+impl Builder {
+    /**
+     * Construct a builder for an empty book.
+     */
+    pub fn new() -> Builder {
+	Builder::default()
+    }
+
+    /**
+     * Add the moves of a game played from a given starting board, up
+     * to a given number of plies.
+     */
+    pub fn add(&mut self, start: Board, game: &Game, plies: usize) {
+	for (_,m,board,_) in game.positions(start).take(plies) {
+	    let score = match (game.result(),board.turn()) {
+		(Some(Outcome::WhiteWins),Player::White) | (Some(Outcome::BlackWins),Player::Black) => 2,
+		(Some(Outcome::WhiteWins),Player::Black) | (Some(Outcome::BlackWins),Player::White) => 0,
+		_ => 1
+	    };
+	    *self.scores.entry((board.hash(),encode(&board,m))).or_insert(0) += score;
+	}
+    }
+
+    /**
+     * Produce the book, where moves which never scored are left out.
+     * Weights are scaled down (if necessary) so the largest fits.
+     */
+    pub fn finish(self) -> Book {
+	let max = self.scores.values().copied().max().unwrap_or(0);
+	let scale = max.div_ceil(u16::MAX as u64).max(1);
+	let mut entries: Vec<Entry> = self.scores.into_iter().filter(|(_,s)| *s > 0).map(|((key,muve),s)| {
+	    Entry{key, muve, weight: (s / scale).max(1) as u16, learn: 0}
+	}).collect();
+	// Within a position, the most popular moves come first
+	entries.sort_by_key(|e| (e.key,u16::MAX - e.weight,e.muve));
+	Book{entries}
+    }
+}
+
+/**
+ * Read a big-endian number from some bytes.
+ */
+fn number(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0,|n,&b| n << 8 | b as u64)
+}
+
+/**
+ * Encode a move on a given board in the Polyglot format.  The bits
+ * give the destination column (0-2) and row (3-5), the starting column
+ * (6-8) and row (9-11), and any promotion (12-14, where 1 is a knight
+ * up to 4 for a queen).  Castling is encoded as the king moving onto
+ * its rook (e.g. "e1h1").
+ */
+pub fn encode(board: &Board, m: &dyn Move) -> u16 {
+    let (from,mut to) = (m.from(),m.to());
+    if board.get(from).kind == Kind::King && from.column().abs_diff(to.column()) == 2 {
+	to = Square::new(if to.column() > from.column() { 7 } else { 0 },to.row());
+    }
+    let promotion = match m.promotion().map(|p| p.kind) {
+	Some(Kind::Knight) => 1,
+	Some(Kind::Bishop) => 2,
+	Some(Kind::Rook) => 3,
+	Some(Kind::Queen) => 4,
+	_ => 0
+    };
+    (to.column() as u16) | (to.row() as u16) << 3 | (from.column() as u16) << 6
+	| (from.row() as u16) << 9 | promotion << 12
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use super::board::INITIAL;
+#[cfg(test)]
+use super::fen;
+#[cfg(test)]
+use super::san;
+
+#[cfg(test)]
+const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[cfg(test)]
+fn code(fen: &str, san: &str) -> u16 {
+    let board = fen::from_str(fen).unwrap();
+    encode(&board,san::from_str(&board,san).unwrap().as_ref())
+}
+
+#[test]
+fn test_01() {
+    assert_eq!(INITIAL.hash(), 0x463b96181691fc9c);
+    assert_eq!(code(START,"e4"), 0x031c);
+    assert_eq!(code(START,"Nf3"), 0x0195);
+    assert_eq!(code("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1","O-O"), 0x0107);
+    assert_eq!(code("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1","O-O-O"), 0x0f38);
+    assert_eq!(code("8/P6k/8/8/8/8/8/K7 w - - 0 1","a8=N"), 0x1c38);
+}
+
+#[test]
+fn test_02() {
+    let game = Game::from_str("e2-e4 e7-e5\nNg1-f3 Nb8-c6").unwrap();
+    let other = Game::from_str("e2-e4 c7-c5").unwrap();
+    let mut builder = Builder::new();
+    builder.add(INITIAL,&game,3);
+    builder.add(INITIAL,&game,10);
+    builder.add(INITIAL,&other,10);
+    let book = builder.finish();
+    assert_eq!(book.len(), 5);
+    let moves = book.lookup(&INITIAL);
+    assert_eq!(moves.len(), 1);
+    assert_eq!(moves[0].1, 3);
+    let after = moves[0].0.apply(INITIAL).unwrap();
+    let replies: Vec<(String,u16)> = book.lookup(&after).iter().map(|(m,w)| (san::to_string(&after,m.as_ref()),*w)).collect();
+    assert_eq!(replies, vec![("e5".to_string(),2), ("c5".to_string(),1)]);
+    // Round trip
+    let bytes = book.to_bytes();
+    assert_eq!(bytes.len(), 80);
+    let i = book.entries().iter().position(|e| e.key == INITIAL.hash()).unwrap() * ENTRY;
+    assert_eq!(&bytes[i..i + 16], &[0x46,0x3b,0x96,0x18,0x16,0x91,0xfc,0x9c,0x03,0x1c,0,3,0,0,0,0]);
+    assert_eq!(Book::from_bytes(&bytes), Ok(book));
+}
+
+#[test]
+fn test_03() {
+    assert_eq!(Book::from_bytes(&[0;17]), Err(Error::Length(17)));
+    let mut bytes = vec![0;32];
+    bytes[0] = 1;
+    assert_eq!(Book::from_bytes(&bytes), Err(Error::Unsorted(1)));
+    // Results decide the weights
+    let mut builder = Builder::new();
+    let lost = Game::new(vec![san::from_str(&INITIAL,"e4").unwrap()],Some(Outcome::BlackWins));
+    builder.add(INITIAL,&lost,1);
+    assert!(builder.finish().is_empty());
+    let won = Game::new(vec![san::from_str(&INITIAL,"e4").unwrap()],Some(Outcome::WhiteWins));
+    let mut builder = Builder::new();
+    builder.add(INITIAL,&won,1);
+    assert_eq!(builder.finish().weight(&INITIAL,san::from_str(&INITIAL,"e4").unwrap().as_ref()), Some(2));
+}
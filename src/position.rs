@@ -0,0 +1,255 @@
+use super::board::*;
+use super::muve::Move;
+
+/**
+ * Represents a position which is updated in place as moves are made
+ * and unmade.  This avoids the cost of copying the board on every
+ * move (as done by `Move::apply()`), which is important when
+ * exploring many moves (e.g. during search).  Every move made is
+ * recorded on an undo stack, so that it can be subsequently unmade.
+ */
+pub struct Position {
+    /**
+     * Current state of the board
+     */
+    board: Board,
+    /**
+     * Stack of records for undoing the moves made so far
+     */
+    history: Vec<Undo>
+}
+
+impl Position {
+    /**
+     * Construct a position starting from a given board.
+     */
+    pub fn new(board: Board) -> Position {
+	Position{board, history: Vec::new()}
+    }
+
+    /**
+     * Get the current state of the board.
+     */
+    pub fn board(&self) -> &Board {
+	&self.board
+    }
+
+    /**
+     * Get the number of moves made (and not yet unmade) from the
+     * starting board.
+     */
+    pub fn len(&self) -> usize {
+	self.history.len()
+    }
+
+    /**
+     * Check whether any moves have been made (and not yet unmade)
+     * from the starting board.
+     */
+    pub fn is_empty(&self) -> bool {
+	self.history.is_empty()
+    }
+
+    /**
+     * Make a given move on this position.  If the move is invalid,
+     * then false is returned and the position is unchanged.
+     */
+    pub fn make_move(&mut self, m: &dyn Move) -> bool {
+	match m.make(&mut self.board) {
+	    Some(undo) => {
+		self.history.push(undo);
+		true
+	    }
+	    None => false
+	}
+    }
+
+    /**
+     * Unmake the last move made on this position, restoring the board
+     * exactly as it was beforehand.  If no moves have been made, then
+     * false is returned.
+     */
+    pub fn unmake_move(&mut self) -> bool {
+	match self.history.pop() {
+	    Some(undo) => {
+		self.board.restore(&undo);
+		true
+	    }
+	    None => false
+	}
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::movegen;
+#[cfg(test)]
+use super::muve;
+#[cfg(test)]
+use super::piece::*;
+#[cfg(test)]
+use super::square::Square;
+#[cfg(test)]
+use super::testing::{Random, random_boards};
+#[cfg(test)]
+use super::zobrist;
+
+#[test]
+fn test_01() {
+    let mut pos = Position::new(INITIAL);
+    let m = muve::from_str("e2-e4",Player::White).unwrap();
+    assert!(pos.make_move(m.as_ref()));
+    assert_eq!(*pos.board(), m.apply(INITIAL).unwrap());
+    assert!(pos.unmake_move());
+    assert_eq!(*pos.board(), INITIAL);
+}
+
+#[test]
+fn test_02() {
+    let mut pos = Position::new(INITIAL);
+    let m = muve::from_str("e2-e5",Player::White).unwrap();
+    assert!(!pos.make_move(m.as_ref()));
+    assert_eq!(*pos.board(), INITIAL);
+    assert!(pos.is_empty());
+}
+
+#[test]
+fn test_03() {
+    let mut pos = Position::new(INITIAL);
+    assert!(!pos.unmake_move());
+}
+
+#[test]
+fn test_04() {
+    // En passant and promotion
+    check_make_unmake(&["e2-e4","d7-d5","e4-e5","f7-f5","e5xf6","Ng8-h6",
+			"f6xg7","d5-d4","g7xRh8=Q"]);
+}
+
+#[test]
+fn test_05() {
+    // Castling on both sides
+    check_make_unmake(&["e2-e4","e7-e5","Ng1-f3","Nb8-c6","Bf1-c4","d7-d6",
+			"O-O","Bc8-g4","d2-d3","Qd8-d7","Nb1-c3","O-O-O"]);
+}
+
+#[test]
+fn test_06() {
+    for seed in 1..20 {
+	check_random_game(seed,80);
+    }
+}
+
+#[test]
+fn test_07() {
+    // Making then unmaking any legal move restores the position
+    for board in random_boards(7,10,80) {
+	let mut pos = Position::new(board);
+	for m in movegen::legal_moves(&board) {
+	    assert!(pos.make_move(m.as_ref()));
+	    assert_eq!(Some(*pos.board()), m.apply(board));
+	    assert!(pos.unmake_move());
+	    assert_eq!(*pos.board(), board);
+	}
+    }
+}
+
+/**
+ * Play a given sequence of moves on a position, checking each against
+ * the copy-based application of moves, before unmaking them all again.
+ */
+#[cfg(test)]
+fn check_make_unmake(moves: &[&str]) {
+    let mut pos = Position::new(INITIAL);
+    let mut boards = vec![INITIAL];
+    let mut player = Player::White;
+    // Make all the moves
+    for s in moves {
+	let m = muve::from_str(s,player).unwrap();
+	let expected = m.apply(*pos.board()).unwrap();
+	assert!(pos.make_move(m.as_ref()));
+	assert_eq!(*pos.board(),expected);
+	boards.push(expected);
+	player = player.flip();
+    }
+    // Unmake all the moves
+    while let Some(b) = boards.pop() {
+	assert_eq!(*pos.board(),b);
+	pos.unmake_move();
+    }
+    assert!(pos.is_empty());
+}
+
+/**
+ * Play a random game, where moves are occasionally unmade along the
+ * way, checking the position always matches the board obtained from
+ * the copy-based application of moves.
+ */
+#[cfg(test)]
+fn check_random_game(seed: u64, length: usize) {
+    let mut rng = Random(seed);
+    let mut pos = Position::new(INITIAL);
+    let mut boards = vec![INITIAL];
+    //
+    for _i in 0..length {
+	if !pos.is_empty() && rng.next(4) == 0 {
+	    // Unmake a move
+	    boards.pop();
+	    assert!(pos.unmake_move());
+	} else {
+	    let moves = candidate_moves(pos.board());
+	    if moves.is_empty() { break; }
+	    let m = &moves[rng.next(moves.len())];
+	    let expected = m.apply(*pos.board()).unwrap();
+	    assert!(pos.make_move(m.as_ref()));
+	    boards.push(expected);
+	}
+	assert_eq!(pos.board(),boards.last().unwrap());
+	assert_eq!(pos.board().hash(),zobrist::hash(pos.board()));
+    }
+    // Unmake everything
+    while pos.unmake_move() {
+	boards.pop();
+	assert_eq!(pos.board(),boards.last().unwrap());
+    }
+    assert_eq!(*pos.board(),INITIAL);
+}
+
+/**
+ * Determine all moves which can be applied to a given board.  This is
+ * done by brute force, attempting every possible move between any two
+ * squares.
+ */
+#[cfg(test)]
+fn candidate_moves(board: &Board) -> Vec<Box<dyn Move>> {
+    let player = board.turn();
+    let mut strs = vec!["O-O".to_string(),"O-O-O".to_string()];
+    for i in 0..64 {
+	let from = Square::new(i % 8, i / 8);
+	let p = board.get(from);
+	if p == BLANK || p.player != player { continue; }
+	for j in 0..64 {
+	    let to = Square::new(j % 8, j / 8);
+	    let t = board.get(to);
+	    let piece = if p.kind == Kind::Pawn { "" } else { p.kind.to_white_string() };
+	    let taken = if t.kind == Kind::Pawn { "" } else { t.kind.to_white_string() };
+	    let m = if t == BLANK {
+		format!("{}{}-{}",piece,from,to)
+	    } else {
+		format!("{}{}x{}{}",piece,from,taken,to)
+	    };
+	    strs.push(format!("{}=Q",m));
+	    strs.push(format!("{}=N",m));
+	    // Pawns can also take en passant
+	    if p.kind == Kind::Pawn { strs.push(format!("{}x{}",from,to)); }
+	    strs.push(m);
+	}
+    }
+    strs.iter()
+	.map(|s| muve::from_str(s,player).unwrap())
+	.filter(|m| m.apply(*board).is_some())
+	.collect()
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+use std::str::FromStr;
+use super::board::Board;
+use super::epd;
+use super::error::Located;
+use super::fen;
+use super::material::Material;
+use super::movegen;
+use super::muve::Move;
+use super::piece::Player;
+use super::san;
+
+/**
+ * What a puzzle's solution should achieve, which is checked against
+ * the position it ends in.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /** The solution ends in checkmate */
+    Mate,
+    /** The solver ends with more material (relative to the opponent) than they started with */
+    Material,
+    /** The solver promotes a pawn */
+    Promotion,
+    /** The solution ends in stalemate */
+    Stalemate
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let s = match self {
+	    Theme::Mate => "mate",
+	    Theme::Material => "material",
+	    Theme::Promotion => "promotion",
+	    Theme::Stalemate => "stalemate"
+	};
+	write!(f,"{}",s)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Theme,()> {
+	match s {
+	    "mate" => Ok(Theme::Mate),
+	    "material" => Ok(Theme::Material),
+	    "promotion" => Ok(Theme::Promotion),
+	    "stalemate" => Ok(Theme::Stalemate),
+	    _ => Err(())
+	}
+    }
+}
+
+/**
+ * A puzzle, which gives a position, the moves solving it (alternating
+ * between the solver and their opponent, starting with the player to
+ * move) and what the solution achieves.
+ */
+pub struct Puzzle {
+    /**
+     * Position to be solved
+     */
+    pub board: Board,
+    /**
+     * Moves solving the puzzle
+     */
+    pub solution: Vec<Box<dyn Move>>,
+    /**
+     * What the solution achieves (which may be nothing)
+     */
+    pub themes: Vec<Theme>
+}
+
+/**
+ * Identifies what is wrong with a puzzle.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /** The record does not have the fields "FEN; moves; themes" */
+    Fields,
+    /** The position is not valid FEN (i.e. why) */
+    Fen(fen::Error),
+    /** The record is not valid EPD (i.e. why) */
+    Epd(epd::ErrorKind),
+    /** The solution has no moves */
+    Empty,
+    /** A move of the solution cannot be played (i.e. its ply, text and why) */
+    Move(usize,String,san::Error),
+    /** A theme is not recognised */
+    Theme(String),
+    /** The solution does not achieve a theme */
+    Unmet(Theme)
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    ErrorKind::Fields => write!(f,"expected \"FEN; moves; themes\""),
+	    ErrorKind::Fen(e) => write!(f,"invalid FEN ({})",e),
+	    ErrorKind::Epd(e) => write!(f,"invalid EPD ({})",e),
+	    ErrorKind::Empty => write!(f,"no solution given"),
+	    ErrorKind::Move(p,s,e) => write!(f,"invalid move \"{}\" at ply {} ({})",s,p,e),
+	    ErrorKind::Theme(s) => write!(f,"unknown theme \"{}\"",s),
+	    ErrorKind::Unmet(t) => write!(f,"solution does not achieve {}",t)
+	}
+    }
+}
+
+/**
+ * Describes why a puzzle in a collection is invalid, including where
+ * it was found.
+ */
+pub type Error = Located<ErrorKind>;
+
+impl Puzzle {
+    /**
+     * Construct a puzzle from a position and a solution in SAN,
+     * checking every move of the solution can be played (as for a
+     * game) and that it achieves the given themes.
+     */
+    pub fn new(board: Board, solution: &[&str], themes: Vec<Theme>) -> Result<Puzzle,ErrorKind> {
+	if solution.is_empty() {
+	    return Err(ErrorKind::Empty);
+	}
+	let mut b = board;
+	let mut moves = Vec::new();
+	for (i,s) in solution.iter().enumerate() {
+	    let m = san::from_str(&b,s).map_err(|e| ErrorKind::Move(i + 1,s.to_string(),e))?;
+	    b = m.apply(b).unwrap();
+	    moves.push(m);
+	}
+	let puzzle = Puzzle{board, solution: moves, themes};
+	if let Some(&t) = puzzle.themes.iter().find(|&&t| !puzzle.achieves(t)) {
+	    return Err(ErrorKind::Unmet(t));
+	}
+	Ok(puzzle)
+    }
+
+    /**
+     * Get the position reached by the solution.
+     */
+    pub fn end(&self) -> Board {
+	self.solution.iter().fold(self.board,|b,m| m.apply(b).unwrap())
+    }
+
+    /**
+     * Check whether the solution achieves a given theme.
+     */
+    pub fn achieves(&self, theme: Theme) -> bool {
+	let end = self.end();
+	match theme {
+	    Theme::Mate => movegen::is_checkmate(&end),
+	    Theme::Stalemate => movegen::is_stalemate(&end),
+	    Theme::Promotion => self.solution.iter().step_by(2).any(|m| m.promotion().is_some()),
+	    Theme::Material => {
+		let sign = if self.board.turn() == Player::White { 1 } else { -1 };
+		sign * Material::of(&end).balance() > sign * Material::of(&self.board).balance()
+	    }
+	}
+    }
+}
+
+/**
+ * Read a puzzle written as a position in FEN, its solution in SAN and
+ * (optionally) its themes, separated by semi-colons, such as:
+ *
+ * 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra8#; mate
+ *
+ * Themes are separated by spaces or commas.  Puzzles can also be
+ * written in EPD, where the solution is given by "pv" (or otherwise
+ * the first move of "bm") and the themes by "themes", such as:
+ *
+ * 6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; themes mate;
+ */
+pub fn from_str(s: &str) -> Result<Puzzle,ErrorKind> {
+    // EPD has an opcode after the position, rather than a clock or ";"
+    let head: Vec<&str> = s.split(';').next().unwrap_or("").split_ascii_whitespace().collect();
+    if head.get(4).is_some_and(|t| t.starts_with(|c: char| c.is_ascii_alphabetic())) {
+	return from_epd(s);
+    }
+    let fields: Vec<&str> = s.split(';').map(str::trim).collect();
+    if fields.len() < 2 || fields.len() > 3 {
+	return Err(ErrorKind::Fields);
+    }
+    let board = fen::from_str(fields[0]).map_err(ErrorKind::Fen)?;
+    let solution: Vec<&str> = fields[1].split_ascii_whitespace().collect();
+    let themes = fields.get(2).copied().unwrap_or("").split([' ',',']).filter(|t| !t.is_empty())
+	.map(|t| Theme::from_str(t).map_err(|_| ErrorKind::Theme(t.to_string())))
+	.collect::<Result<Vec<_>,_>>()?;
+    Puzzle::new(board,&solution,themes)
+}
+
+/**
+ * Read a puzzle written in EPD.
+ */
+fn from_epd(s: &str) -> Result<Puzzle,ErrorKind> {
+    let e = epd::from_str(s).map_err(ErrorKind::Epd)?;
+    let solution = match (e.get("pv"),e.get("bm")) {
+	(Some(pv),_) => pv.iter().map(|m| m.as_str()).collect(),
+	(None,Some(bm)) => bm.iter().take(1).map(|m| m.as_str()).collect(),
+	(None,None) => Vec::new()
+    };
+    let themes = e.get("themes").unwrap_or(&[]).iter()
+	.map(|t| Theme::from_str(t).map_err(|_| ErrorKind::Theme(t.to_string())))
+	.collect::<Result<Vec<_>,_>>()?;
+    Puzzle::new(e.board,&solution,themes)
+}
+
+/**
+ * Read every puzzle in a collection, where each puzzle is on its own
+ * line.  Blank lines and lines starting with "#" are skipped.
+ */
+pub fn read(text: &str) -> Vec<Result<Puzzle,Error>> {
+    text.lines().enumerate()
+	.filter(|(_,l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+	.map(|(i,l)| from_str(l).map_err(|kind| Error{line: i + 1, kind}))
+	.collect()
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+fn check(s: &str) -> Result<(),ErrorKind> {
+    from_str(s).map(|_| ())
+}
+
+#[test]
+fn test_01() {
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra8#; mate"), Ok(()));
+    assert_eq!(check("7k/8/8/8/8/8/R7/1R4K1 w - -; Ra7 Kg8 Rb8#; mate"), Ok(()));
+    // Winning material, and promotion
+    assert_eq!(check("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1; Rxd5; material"), Ok(()));
+    assert_eq!(check("8/4P3/8/8/8/8/k7/4K3 w - - 0 1; e8=Q; promotion, material"), Ok(()));
+    assert_eq!(check("k7/7Q/1K6/8/8/8/8/8 w - - 0 1; Qc7; stalemate"), Ok(()));
+    // Black to move, and no themes
+    assert_eq!(check("4k3/8/8/3Q4/8/8/8/3rK3 b - - 0 1; Rxd5"), Ok(()));
+    assert_eq!(check("4k3/8/8/3Q4/8/8/8/3rK3 b - - 0 1; Rxd5; material"), Ok(()));
+}
+
+#[test]
+fn test_02() {
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"), Err(ErrorKind::Fields));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8 w - - 0 1; Ra8#"), Err(ErrorKind::Fen(fen::Error::Placement)));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1;  ; mate"), Err(ErrorKind::Empty));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra8# Kh8"),
+	       Err(ErrorKind::Move(2,"Kh8".to_string(),san::Error::Illegal)));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra7; mate"), Err(ErrorKind::Unmet(Theme::Mate)));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra8#; smothered"), Err(ErrorKind::Theme("smothered".to_string())));
+    assert_eq!(check("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1; Rd2; material"), Err(ErrorKind::Unmet(Theme::Material)));
+}
+
+#[test]
+fn test_03() {
+    let text = "# Mates\n6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra8#; mate\n\n6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1; Ra7; mate\n";
+    let puzzles = read(text);
+    assert_eq!(puzzles.len(), 2);
+    assert!(puzzles[0].is_ok());
+    let err = puzzles[1].as_ref().err().unwrap();
+    assert_eq!(err.line, 4);
+    assert_eq!(err.to_string(), "line 4: solution does not achieve mate");
+}
+
+#[test]
+fn test_04() {
+    // Puzzles in EPD
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; themes mate;"), Ok(()));
+    assert_eq!(check("7k/8/8/8/8/8/R7/1R4K1 w - - pv Ra7 Kg8 Rb8#; bm Ra7; themes mate;"), Ok(()));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra7; themes mate;"), Err(ErrorKind::Unmet(Theme::Mate)));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - id \"no solution\";"), Err(ErrorKind::Empty));
+    assert_eq!(check("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra9;"),
+	       Err(ErrorKind::Epd(epd::ErrorKind::Move("bm".to_string(),"Ra9".to_string(),san::Error::Syntax))));
+}
@@ -0,0 +1,291 @@
+/**
+ * A colour with an alpha (i.e. opacity) component, where each
+ * component is between 0 and 255.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Colour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8
+}
+
+impl Colour {
+    /**
+     * Construct a fully opaque colour.
+     */
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Colour {
+	Colour{r,g,b,a:255}
+    }
+
+    /**
+     * Construct a partially transparent colour.
+     */
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Colour {
+	Colour{r,g,b,a}
+    }
+}
+
+/**
+ * A polygon given as a sequence of points, where the last point is
+ * implicitly joined to the first.
+ */
+pub type Polygon = Vec<(f64,f64)>;
+
+/**
+ * Number of samples taken vertically within each pixel when
+ * determining how much of it is covered by a shape.  Horizontal
+ * coverage is computed exactly.
+ */
+const SAMPLES: usize = 4;
+
+/**
+ * An image made up of RGB pixels, onto which polygons can be drawn
+ * with anti-aliasing.
+ */
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f64;3]>
+}
+
+impl Image {
+    /**
+     * Construct an image of a given size filled with a given colour.
+     */
+    pub fn new(width: usize, height: usize, background: Colour) -> Image {
+	let c = [background.r as f64, background.g as f64, background.b as f64];
+	Image{width, height, pixels: vec![c; width * height]}
+    }
+
+    /**
+     * Fill a set of polygons (whose points are in pixels) with a given
+     * colour.  The polygons are filled together using the non-zero
+     * winding rule, so overlapping areas are not painted twice.
+     */
+    pub fn fill(&mut self, polygons: &[Polygon], colour: Colour) {
+	// Determine bounding box
+	let mut min_y = f64::MAX;
+	let mut max_y = f64::MIN;
+	for p in polygons {
+	    for &(_,y) in p {
+		min_y = min_y.min(y);
+		max_y = max_y.max(y);
+	    }
+	}
+	if min_y > max_y { return; }
+	let top = min_y.floor().max(0.0) as usize;
+	let bottom = (max_y.ceil().max(0.0) as usize).min(self.height);
+	let mut coverage = vec![0.0; self.width];
+	for py in top..bottom {
+	    coverage.iter_mut().for_each(|c| *c = 0.0);
+	    for j in 0..SAMPLES {
+		let y = py as f64 + (j as f64 + 0.5) / SAMPLES as f64;
+		self.cover_scanline(polygons, y, &mut coverage);
+	    }
+	    self.blend_row(py, &coverage, colour);
+	}
+    }
+
+    /**
+     * Accumulate the coverage of a single horizontal line through a
+     * set of polygons.
+     */
+    fn cover_scanline(&self, polygons: &[Polygon], y: f64, coverage: &mut [f64]) {
+	// Find crossings along with their winding direction
+	let mut crossings : Vec<(f64,i32)> = Vec::new();
+	for p in polygons {
+	    for i in 0..p.len() {
+		let (x0,y0) = p[i];
+		let (x1,y1) = p[(i + 1) % p.len()];
+		if (y0 <= y && y < y1) || (y1 <= y && y < y0) {
+		    let x = x0 + (y - y0) * (x1 - x0) / (y1 - y0);
+		    crossings.push((x, if y1 > y0 { 1 } else { -1 }));
+		}
+	    }
+	}
+	crossings.sort_by(|a,b| a.0.partial_cmp(&b.0).unwrap());
+	// Fill spans where winding is non-zero
+	let mut winding = 0;
+	for i in 0..crossings.len() {
+	    let before = winding;
+	    winding += crossings[i].1;
+	    if before == 0 && winding != 0 {
+		// Find where span ends
+		let mut w = winding;
+		let mut k = i + 1;
+		while k < crossings.len() {
+		    w += crossings[k].1;
+		    if w == 0 { break; }
+		    k += 1;
+		}
+		if k < crossings.len() {
+		    self.cover_span(crossings[i].0, crossings[k].0, coverage);
+		}
+	    }
+	}
+    }
+
+    /**
+     * Accumulate the coverage of a horizontal span between two points.
+     */
+    fn cover_span(&self, x0: f64, x1: f64, coverage: &mut [f64]) {
+	let x0 = x0.max(0.0);
+	let x1 = x1.min(self.width as f64);
+	if x0 >= x1 { return; }
+	let weight = 1.0 / SAMPLES as f64;
+	let first = x0.floor() as usize;
+	let last = (x1.ceil() as usize).min(self.width);
+	for (px,c) in coverage.iter_mut().enumerate().take(last).skip(first) {
+	    let left = (px as f64).max(x0);
+	    let right = ((px + 1) as f64).min(x1);
+	    if right > left {
+		*c += (right - left) * weight;
+	    }
+	}
+    }
+
+    /**
+     * Blend a given colour into a row of the image according to the
+     * coverage of each pixel.
+     */
+    fn blend_row(&mut self, py: usize, coverage: &[f64], colour: Colour) {
+	let c = [colour.r as f64, colour.g as f64, colour.b as f64];
+	let opacity = colour.a as f64 / 255.0;
+	for (px,&cover) in coverage.iter().enumerate() {
+	    let alpha = cover.min(1.0) * opacity;
+	    if alpha > 0.0 {
+		let pixel = &mut self.pixels[py * self.width + px];
+		for i in 0..3 {
+		    pixel[i] = pixel[i] * (1.0 - alpha) + c[i] * alpha;
+		}
+	    }
+	}
+    }
+
+    /**
+     * Encode this image in the PNG format.  For simplicity, the image
+     * data is stored without compression.
+     */
+    pub fn to_png(&self) -> Vec<u8> {
+	// Raw image data, where each row begins with a filter type
+	let mut raw = Vec::with_capacity(self.height * (1 + self.width * 3));
+	for y in 0..self.height {
+	    raw.push(0);
+	    for x in 0..self.width {
+		for v in &self.pixels[y * self.width + x] {
+		    raw.push(v.round().clamp(0.0,255.0) as u8);
+		}
+	    }
+	}
+	let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+	// Header (8 bit RGB)
+	let mut header = Vec::new();
+	header.extend_from_slice(&(self.width as u32).to_be_bytes());
+	header.extend_from_slice(&(self.height as u32).to_be_bytes());
+	header.extend_from_slice(&[8, 2, 0, 0, 0]);
+	write_chunk(&mut png, b"IHDR", &header);
+	write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+	write_chunk(&mut png, b"IEND", &[]);
+	png
+    }
+}
+
+/**
+ * Write a PNG chunk with a given type and data.
+ */
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8;4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/**
+ * Wrap some data in the zlib format using only uncompressed (i.e.
+ * stored) deflate blocks.
+ */
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut chunks = data.chunks(0xffff).peekable();
+    if chunks.peek().is_none() {
+	// Empty data still requires a final block
+	out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+	let last = chunks.peek().is_none();
+	let len = chunk.len() as u16;
+	out.push(if last { 1 } else { 0 });
+	out.extend_from_slice(&len.to_le_bytes());
+	out.extend_from_slice(&(!len).to_le_bytes());
+	out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/**
+ * Compute the CRC-32 checksum of some data, as used by PNG.
+ */
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &b in data {
+	crc ^= b as u32;
+	for _ in 0..8 {
+	    crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+	}
+    }
+    !crc
+}
+
+/**
+ * Compute the Adler-32 checksum of some data, as used by zlib.
+ */
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for &d in data {
+	a = (a + d as u32) % 65521;
+	b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[test]
+fn test_01() {
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+}
+
+#[test]
+fn test_02() {
+    // Square covering left half of image
+    let mut img = Image::new(4, 2, Colour::rgb(255,255,255));
+    img.fill(&[vec![(0.0,0.0),(2.0,0.0),(2.0,2.0),(0.0,2.0)]], Colour::rgb(0,0,0));
+    assert_eq!(img.pixels[0], [0.0,0.0,0.0]);
+    assert_eq!(img.pixels[5], [0.0,0.0,0.0]);
+    assert_eq!(img.pixels[2], [255.0,255.0,255.0]);
+}
+
+#[test]
+fn test_03() {
+    // Partial coverage gives intermediate colour
+    let mut img = Image::new(2, 1, Colour::rgb(0,0,0));
+    img.fill(&[vec![(0.0,0.0),(1.5,0.0),(1.5,1.0),(0.0,1.0)]], Colour::rgb(200,200,200));
+    assert_eq!(img.pixels[0], [200.0,200.0,200.0]);
+    assert_eq!(img.pixels[1], [100.0,100.0,100.0]);
+}
+
+#[test]
+fn test_04() {
+    let png = Image::new(3, 2, Colour::rgb(1,2,3)).to_png();
+    assert_eq!(&png[0..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(&png[png.len()-8..png.len()-4], b"IEND");
+}
@@ -0,0 +1,301 @@
+use std::fmt::Write;
+use super::board::Board;
+use super::piece::*;
+use super::square::Square;
+
+/**
+ * Escape sequence for starting highlighted (i.e. reverse video) text.
+ */
+const HIGHLIGHT: &str = "\x1b[7m";
+
+/**
+ * Escape sequence for returning to normal text.
+ */
+const NORMAL: &str = "\x1b[0m";
+
+/**
+ * Escape sequence for the background of a light square.
+ */
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+
+/**
+ * Escape sequence for the background of a dark square.
+ */
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+
+/**
+ * Escape sequence for the background of a highlighted square.
+ */
+const HIGHLIGHT_SQUARE: &str = "\x1b[48;5;143m";
+
+/**
+ * Escape sequence for the colour of white's pieces.
+ */
+const WHITE_PIECE: &str = "\x1b[97m";
+
+/**
+ * Escape sequence for the colour of black's pieces.
+ */
+const BLACK_PIECE: &str = "\x1b[30m";
+
+/**
+ * Something which can render a board as text, such that certain
+ * squares can be highlighted (e.g. to show the last move made).
+ */
+pub trait Renderer {
+    /**
+     * Render a given board, highlighting a given set of squares.
+     */
+    fn render(&self, board: &Board, highlight: &[Square]) -> String;
+}
+
+/**
+ * Construct a renderer for a given style name (i.e. "ascii",
+ * "unicode" or "colour"), optionally flipped so that the board is
+ * seen from black's perspective.  If the style is unknown, then
+ * nothing is returned.
+ */
+pub fn from_style(style: &str, flipped: bool) -> Option<Box<dyn Renderer>> {
+    match style {
+	"ascii" => Some(Box::new(Ascii{flipped})),
+	"unicode" => Some(Box::new(Unicode{flipped})),
+	"colour" | "color" => Some(Box::new(Colour{flipped})),
+	_ => None
+    }
+}
+
+/**
+ * Determine the order in which rows and columns should be drawn, from
+ * top to bottom and left to right respectively.
+ */
+fn order(flipped: bool) -> (Vec<u8>,Vec<u8>) {
+    if flipped {
+	((0..8).collect(),(0..8).rev().collect())
+    } else {
+	((0..8).rev().collect(),(0..8).collect())
+    }
+}
+
+/**
+ * Write out the column letters in a given order.
+ */
+fn columns(cols: &[u8], sep: &str) -> String {
+    let letters : Vec<String> = cols.iter()
+	.map(|c| ((b'a' + c) as char).to_string())
+	.collect();
+    letters.join(sep)
+}
+
+/**
+ * Determine the Unicode glyph for a given piece.  Blank squares are
+ * shown with a middle dot.
+ */
+pub fn glyph(p: Piece) -> char {
+    match (p.player,p.kind) {
+	(_,Kind::Blank) => '·',
+	(Player::White,Kind::Pawn) => '♙',
+	(Player::White,Kind::Knight) => '♘',
+	(Player::White,Kind::Bishop) => '♗',
+	(Player::White,Kind::Rook) => '♖',
+	(Player::White,Kind::Queen) => '♕',
+	(Player::White,Kind::King) => '♔',
+	(Player::Black,Kind::Pawn) => '♟',
+	(Player::Black,Kind::Knight) => '♞',
+	(Player::Black,Kind::Bishop) => '♝',
+	(Player::Black,Kind::Rook) => '♜',
+	(Player::Black,Kind::Queen) => '♛',
+	(Player::Black,Kind::King) => '♚'
+    }
+}
+
+// ======================================================
+// ASCII
+// ======================================================
+
+/**
+ * Renders a board in the same format as its `Display`
+ * implementation (e.g. "|r|n|b|" with "_" for blanks).  Highlighted
+ * squares are shown in reverse video.
+ */
+pub struct Ascii {
+    /**
+     * Whether to show the board from black's perspective
+     */
+    pub flipped: bool
+}
+
+impl Renderer for Ascii {
+    fn render(&self, board: &Board, highlight: &[Square]) -> String {
+	let (rows,cols) = order(self.flipped);
+	let mut out = String::new();
+	for &row in &rows {
+	    let _ = write!(out,"{}",row+1);
+	    for &col in &cols {
+		let s = Square::new(col,row);
+		if highlight.contains(&s) {
+		    let _ = write!(out,"|{}{}{}",HIGHLIGHT,board.get(s),NORMAL);
+		} else {
+		    let _ = write!(out,"|{}",board.get(s));
+		}
+	    }
+	    out.push_str("|\n");
+	}
+	out.push_str("-|");
+	out.push_str(&columns(&cols," "));
+	out
+    }
+}
+
+// ======================================================
+// Unicode
+// ======================================================
+
+/**
+ * Renders a board using Unicode chess glyphs (e.g. "♔" and "♚").
+ * Highlighted squares are shown in reverse video.
+ */
+pub struct Unicode {
+    /**
+     * Whether to show the board from black's perspective
+     */
+    pub flipped: bool
+}
+
+impl Renderer for Unicode {
+    fn render(&self, board: &Board, highlight: &[Square]) -> String {
+	let (rows,cols) = order(self.flipped);
+	let mut out = String::new();
+	for &row in &rows {
+	    let _ = write!(out,"{}",row+1);
+	    for &col in &cols {
+		let s = Square::new(col,row);
+		if highlight.contains(&s) {
+		    let _ = write!(out," {}{}{}",HIGHLIGHT,glyph(board.get(s)),NORMAL);
+		} else {
+		    let _ = write!(out," {}",glyph(board.get(s)));
+		}
+	    }
+	    out.push('\n');
+	}
+	out.push_str("  ");
+	out.push_str(&columns(&cols," "));
+	out
+    }
+}
+
+// ======================================================
+// Colour
+// ======================================================
+
+/**
+ * Renders a board using ANSI colours for the light and dark squares,
+ * with pieces shown using Unicode chess glyphs.  Highlighted squares
+ * are shown in a different colour.
+ */
+pub struct Colour {
+    /**
+     * Whether to show the board from black's perspective
+     */
+    pub flipped: bool
+}
+
+impl Renderer for Colour {
+    fn render(&self, board: &Board, highlight: &[Square]) -> String {
+	let (rows,cols) = order(self.flipped);
+	let mut out = String::new();
+	for &row in &rows {
+	    let _ = write!(out,"{} ",row+1);
+	    for &col in &cols {
+		let s = Square::new(col,row);
+		let p = board.get(s);
+		let background = if highlight.contains(&s) {
+		    HIGHLIGHT_SQUARE
+		} else if (row + col) % 2 == 0 {
+		    DARK_SQUARE
+		} else {
+		    LIGHT_SQUARE
+		};
+		let foreground = match p.player {
+		    Player::White => WHITE_PIECE,
+		    Player::Black => BLACK_PIECE
+		};
+		// Use solid glyphs, with colour distinguishing players
+		let g = if p == BLANK { ' ' } else { glyph(Piece{player: Player::Black, kind: p.kind}) };
+		let _ = write!(out,"{}{} {} ",background,foreground,g);
+	    }
+	    let _ = writeln!(out,"{}",NORMAL);
+	}
+	out.push_str("   ");
+	out.push_str(&columns(&cols,"  "));
+	out
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::board::INITIAL;
+
+#[test]
+fn test_01() {
+    // ASCII matches Display
+    let r = Ascii{flipped: false};
+    assert_eq!(r.render(&INITIAL,&[]),INITIAL.to_string());
+}
+
+#[test]
+fn test_02() {
+    let r = Ascii{flipped: true};
+    assert_eq!(r.render(&INITIAL,&[]),
+	       "1|R|N|B|K|Q|B|N|R|\n\
+		2|P|P|P|P|P|P|P|P|\n\
+		3|_|_|_|_|_|_|_|_|\n\
+		4|_|_|_|_|_|_|_|_|\n\
+		5|_|_|_|_|_|_|_|_|\n\
+		6|_|_|_|_|_|_|_|_|\n\
+		7|p|p|p|p|p|p|p|p|\n\
+		8|r|n|b|k|q|b|n|r|\n\
+		-|h g f e d c b a");
+}
+
+#[test]
+fn test_03() {
+    let r = Unicode{flipped: false};
+    assert_eq!(r.render(&INITIAL,&[]),
+	       "8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜\n\
+		7 ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟\n\
+		6 · · · · · · · ·\n\
+		5 · · · · · · · ·\n\
+		4 · · · · · · · ·\n\
+		3 · · · · · · · ·\n\
+		2 ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙\n\
+		1 ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖\n  \
+		a b c d e f g h");
+}
+
+#[test]
+fn test_04() {
+    // Highlighted squares
+    let r = Ascii{flipped: false};
+    let text = r.render(&INITIAL,&[Square::new(4,1)]);
+    assert!(text.contains("2|P|P|P|P|\x1b[7mP\x1b[0m|P|P|P|"));
+}
+
+#[test]
+fn test_05() {
+    // Square a1 is dark, and b1 is light
+    let r = Colour{flipped: false};
+    let text = r.render(&INITIAL,&[]);
+    let line = text.lines().nth(7).unwrap();
+    assert!(line.starts_with(&format!("1 {}{} ♜ {}{} ♞ ",DARK_SQUARE,WHITE_PIECE,LIGHT_SQUARE,WHITE_PIECE)));
+}
+
+#[test]
+fn test_06() {
+    assert!(from_style("ascii",false).is_some());
+    assert!(from_style("unicode",true).is_some());
+    assert!(from_style("colour",false).is_some());
+    assert!(from_style("fancy",false).is_none());
+}
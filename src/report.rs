@@ -0,0 +1,225 @@
+use std::fmt::Write;
+use super::board::Board;
+use super::eco;
+use super::eco::Opening;
+use super::fen;
+use super::game::{Game, Outcome, ParseError};
+
+/**
+ * Describes why a game is invalid.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    /**
+     * Ply of the first move which could not be applied (starting from
+     * 1), or nothing if the game could not be parsed
+     */
+    pub ply: Option<usize>,
+    /**
+     * Text of the first move which could not be applied (if any)
+     */
+    pub text: Option<String>,
+    /**
+     * Short identifier for the reason (e.g. "movement")
+     */
+    pub code: &'static str,
+    /**
+     * Explanation of the reason
+     */
+    pub reason: String
+}
+
+/**
+ * The result of checking whether a game is valid, which can be
+ * written as JSON for consumption by other programs.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /**
+     * Number of moves which were successfully applied
+     */
+    pub plies: usize,
+    /**
+     * Why the game is invalid (if it is)
+     */
+    pub failure: Option<Failure>,
+    /**
+     * Last board reached (if the game could be parsed)
+     */
+    pub board: Option<Board>,
+    /**
+     * Outcome of the game (if it is valid)
+     */
+    pub outcome: Option<Outcome>,
+    /**
+     * Opening reached by the game (if any), considering only the
+     * moves before the first invalid move
+     */
+    pub opening: Option<Opening>
+}
+
+impl Report {
+    /**
+     * Check a given game played from a given starting board.
+     */
+    pub fn new(game: &Game, start: Board) -> Report {
+	let mut board = start;
+	let mut plies = 0;
+	for (ply,_,_,after) in game.positions(start) {
+	    board = after;
+	    plies = ply;
+	}
+	let failure = game.get(plies).map(|m| {
+	    let invalid = m.reason(&board);
+	    Failure {
+		ply: Some(plies + 1),
+		text: Some(m.to_string()),
+		code: invalid.map(|i| i.code()).unwrap_or("unknown"),
+		reason: invalid.map(|i| i.to_string()).unwrap_or_default()
+	    }
+	});
+	let outcome = if failure.is_none() { Some(Outcome::of(&board)) } else { None };
+	let opening = eco::classify(game,start);
+	Report{plies, failure, board: Some(board), outcome, opening}
+    }
+
+    /**
+     * Construct the report for a game which could not be parsed.
+     */
+    pub fn unparsed(error: &ParseError) -> Report {
+	let failure = Failure {
+	    ply: None,
+	    text: None,
+	    code: "parse",
+	    reason: error.to_string()
+	};
+	Report{plies: 0, failure: Some(failure), board: None, outcome: None, opening: None}
+    }
+
+    /**
+     * Check whether the game was valid.
+     */
+    pub fn is_valid(&self) -> bool {
+	self.failure.is_none()
+    }
+
+    /**
+     * Write this report as a JSON object on a single line, optionally
+     * including the name of the file the game was read from.
+     */
+    pub fn to_json(&self, file: Option<&str>) -> String {
+	let mut out = String::from("{");
+	if let Some(f) = file {
+	    let _ = write!(out,"\"file\":{},",quote(f));
+	}
+	let _ = write!(out,"\"valid\":{},\"plies\":{},\"failure\":",self.is_valid(),self.plies);
+	match &self.failure {
+	    Some(f) => {
+		let ply = f.ply.map(|p| p.to_string()).unwrap_or("null".to_string());
+		let text = f.text.as_deref().map(quote).unwrap_or("null".to_string());
+		let _ = write!(out,"{{\"ply\":{},\"move\":{},\"code\":{},\"reason\":{}}}",
+			       ply,text,quote(f.code),quote(&f.reason));
+	    }
+	    None => out.push_str("null")
+	}
+	out.push_str(",\"fen\":");
+	match &self.board {
+	    Some(b) => out.push_str(&quote(&fen::to_string(b))),
+	    None => out.push_str("null")
+	}
+	out.push_str(",\"outcome\":");
+	match self.outcome {
+	    Some(o) => out.push_str(&quote(&o.to_string())),
+	    None => out.push_str("null")
+	}
+	out.push_str(",\"opening\":");
+	match self.opening {
+	    Some(o) => { let _ = write!(out,"{{\"eco\":{},\"name\":{}}}",quote(o.code),quote(o.name)); }
+	    None => out.push_str("null")
+	}
+	out.push('}');
+	out
+    }
+}
+
+/**
+ * Write a string as a JSON string literal, escaping characters as
+ * necessary.
+ */
+pub fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+	match c {
+	    '"' => out.push_str("\\\""),
+	    '\\' => out.push_str("\\\\"),
+	    '\n' => out.push_str("\\n"),
+	    '\r' => out.push_str("\\r"),
+	    '\t' => out.push_str("\\t"),
+	    c if (c as u32) < 0x20 => { let _ = write!(out,"\\u{:04x}",c as u32); }
+	    c => out.push(c)
+	}
+    }
+    out.push('"');
+    out
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use super::board::INITIAL;
+
+#[test]
+fn test_01() {
+    let g = Game::from_str("e2-e4 e7-e5").unwrap();
+    let r = Report::new(&g,INITIAL);
+    assert!(r.is_valid());
+    assert_eq!(r.outcome, Some(Outcome::Unfinished));
+    assert_eq!(r.to_json(Some("a.txt")),
+	       "{\"file\":\"a.txt\",\"valid\":true,\"plies\":2,\"failure\":null,\
+		\"fen\":\"rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2\",\"outcome\":\"*\",\
+		\"opening\":{\"eco\":\"C20\",\"name\":\"King's Pawn Game\"}}");
+}
+
+#[test]
+fn test_02() {
+    let g = Game::from_str("e2-e4 e7-e5\ne4-e6").unwrap();
+    let r = Report::new(&g,INITIAL);
+    assert!(!r.is_valid());
+    assert_eq!(r.plies, 2);
+    assert_eq!(r.outcome, None);
+    assert!(r.to_json(None).contains("\"failure\":{\"ply\":3,\"move\":\"e4-e6\",\"code\":\"movement\",\
+				       \"reason\":\"piece cannot move that way\"}"));
+}
+
+#[test]
+fn test_03() {
+    // Fool's mate
+    let g = Game::from_str("f2-f3 e7-e5\ng2-g4 Qd8-h4").unwrap();
+    let r = Report::new(&g,INITIAL);
+    assert_eq!(r.outcome, Some(Outcome::BlackWins));
+    assert!(r.to_json(None).ends_with("\"outcome\":\"0-1\",\"opening\":{\"eco\":\"A00\",\"name\":\"Barnes Opening\"}}"));
+}
+
+#[test]
+fn test_04() {
+    // Moving into check
+    let g = Game::from_str("e2-e4 f7-f5\nQd1-h5 Ke8-f7\nNg1-f3 g7-g6").unwrap();
+    let r = Report::new(&g,INITIAL);
+    let f = r.failure.unwrap();
+    assert_eq!(f.ply, Some(4));
+    assert_eq!(f.code, "check");
+}
+
+#[test]
+fn test_05() {
+    let e = Game::from_str("e2-e4 e7-e5\ne2-e9").err().unwrap();
+    let r = Report::unparsed(&e);
+    assert_eq!(r.to_json(None),
+	       "{\"valid\":false,\"plies\":0,\"failure\":{\"ply\":null,\"move\":null,\"code\":\"parse\",\
+		\"reason\":\"line 2: invalid move \\\"e2-e9\\\" (malformed square)\"},\"fen\":null,\"outcome\":null,\"opening\":null}");
+    assert_eq!(quote("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+}
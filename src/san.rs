@@ -0,0 +1,322 @@
+use std::fmt;
+use super::annotation;
+use super::board::*;
+use super::movegen;
+use super::muve::Move;
+use super::piece::*;
+use super::square::Square;
+
+/**
+ * Write a given move in Standard Algebraic Notation (SAN) for the
+ * board it is played on (e.g. "Nf3", "exd5", "Rae1", "e8=Q+" or
+ * "O-O").  Unlike the long algebraic notation used for games, SAN
+ * omits the starting square unless it is needed to distinguish
+ * between pieces, and so depends upon the board.  The move is
+ * assumed to be legal on the given board.
+ */
+pub fn to_string(board: &Board, m: &dyn Move) -> String {
+    let (from,to) = (m.from(),m.to());
+    let piece = board.get(from);
+    let mut out = String::new();
+    if piece.kind == Kind::King && (from.column() as i8 - to.column() as i8).abs() == 2 {
+	// Castling
+	out.push_str(if to.column() > from.column() { "O-O" } else { "O-O-O" });
+    } else {
+	// Pawns capture diagonally, which includes en passant
+	let capture = board.get(to) != BLANK
+	    || (piece.kind == Kind::Pawn && from.column() != to.column());
+	if piece.kind == Kind::Pawn {
+	    if capture {
+		out.push((b'a' + from.column()) as char);
+	    }
+	} else {
+	    out.push_str(piece.kind.to_white_string());
+	    out.push_str(&disambiguate(board,m));
+	}
+	if capture {
+	    out.push('x');
+	}
+	out.push_str(&to.to_string());
+	if let Some(q) = m.promotion() {
+	    out.push('=');
+	    out.push_str(q.kind.to_white_string());
+	}
+    }
+    // Check and checkmate
+    if let Some(after) = m.apply(*board) {
+	if movegen::is_checkmate(&after) {
+	    out.push('#');
+	} else if after.in_check(after.turn()) {
+	    out.push('+');
+	}
+    }
+    out
+}
+
+/**
+ * Determine what (if anything) must be written to distinguish the
+ * starting square of a given move from other pieces of the same kind
+ * which could also move to the same square.  The column is preferred
+ * over the row, with both being used only when necessary.
+ */
+fn disambiguate(board: &Board, m: &dyn Move) -> String {
+    let (from,to) = (m.from(),m.to());
+    let piece = board.get(from);
+    let others : Vec<_> = movegen::legal_moves(board).into_iter()
+	.filter(|o| o.to() == to && o.from() != from && board.get(o.from()) == piece)
+	.map(|o| o.from())
+	.collect();
+    let col = (b'a' + from.column()) as char;
+    let row = (b'1' + from.row()) as char;
+    if others.is_empty() {
+	String::new()
+    } else if others.iter().all(|o| o.column() != from.column()) {
+	col.to_string()
+    } else if others.iter().all(|o| o.row() != from.row()) {
+	row.to_string()
+    } else {
+	format!("{}{}",col,row)
+    }
+}
+
+/**
+ * Identifies why a string could not be read as a move in SAN.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** The string is not written in SAN (e.g. "Nz3") */
+    Syntax,
+    /** No legal move matches (e.g. "Ne4" when no knight can get there) */
+    Illegal,
+    /** More than one legal move matches (e.g. "Rd1" when both rooks can get there) */
+    Ambiguous,
+    /** The annotations following the move are malformed (i.e. why) */
+    Annotation(annotation::Error)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	let msg = match self {
+	    Error::Syntax => "not a move in SAN",
+	    Error::Illegal => "no legal move matches",
+	    Error::Ambiguous => "more than one legal move matches",
+	    Error::Annotation(e) => return write!(f,"{}",e)
+	};
+	write!(f,"{}",msg)
+    }
+}
+
+/**
+ * Read a move written in Standard Algebraic Notation (SAN) for the
+ * board it is played on (e.g. "Nf3", "exd5", "Rae1", "e8=Q" or
+ * "O-O"), producing the legal move it identifies.  Any check, mate or
+ * glyph written after the move (e.g. "+" or "!?") is accepted but not
+ * checked.  Captures must be marked with "x" exactly when a piece is
+ * taken.
+ */
+pub fn from_str(board: &Board, s: &str) -> Result<Box<dyn Move>,Error> {
+    // Separate the move from any annotations
+    let end = s.find(['+','#','!','?']).unwrap_or(s.len());
+    annotation::from_str(&s[end..]).map_err(Error::Annotation)?;
+    let s = &s[..end];
+    // Determine what the move must look like
+    let pattern = match s {
+	"O-O" | "0-0" => Pattern::Castle(true),
+	"O-O-O" | "0-0-0" => Pattern::Castle(false),
+	_ => parse_pattern(s.as_bytes()).ok_or(Error::Syntax)?
+    };
+    let mut matches = movegen::legal_moves(board).into_iter().filter(|m| pattern.matches(board,m.as_ref()));
+    match (matches.next(),matches.next()) {
+	(None,_) => Err(Error::Illegal),
+	(Some(m),None) => Ok(m),
+	(Some(_),Some(_)) => Err(Error::Ambiguous)
+    }
+}
+
+/**
+ * Describes the moves which a string in SAN could identify, before
+ * considering which are legal on the board.
+ */
+enum Pattern {
+    /** Castling on the king side (true) or queen side (false) */
+    Castle(bool),
+    /** Moving a piece (with the column and row of its starting square, if given) */
+    Piece{kind: Kind, col: Option<u8>, row: Option<u8>, capture: bool, to: Square, promote: Option<Kind>}
+}
+
+impl Pattern {
+    /**
+     * Check whether a given legal move on a given board fits this
+     * pattern.
+     */
+    fn matches(&self, board: &Board, m: &dyn Move) -> bool {
+	let (from,to) = (m.from(),m.to());
+	let piece = board.get(from);
+	let castle = piece.kind == Kind::King && (from.column() as i8 - to.column() as i8).abs() == 2;
+	match *self {
+	    Pattern::Castle(kingside) => castle && (to.column() > from.column()) == kingside,
+	    Pattern::Piece{kind,col,row,capture,to: t,promote} => {
+		// Pawns capture diagonally, which includes en passant
+		let taken = board.get(to) != BLANK
+		    || (piece.kind == Kind::Pawn && from.column() != to.column());
+		!castle && piece.kind == kind && to == t && taken == capture
+		    && col.is_none_or(|c| c == from.column())
+		    && row.is_none_or(|r| r == from.row())
+		    && m.promotion().map(|q| q.kind) == promote
+	    }
+	}
+    }
+}
+
+/**
+ * Parse a move in SAN other than castling, without annotations (e.g.
+ * "Nbxd2" or "exd8=Q").  This reads from the end, since the
+ * destination and promotion are always written last.
+ */
+fn parse_pattern(s: &[u8]) -> Option<Pattern> {
+    // Piece (if not a pawn)
+    let (kind,s) = match s.first()? {
+	b'N' => (Kind::Knight,&s[1..]),
+	b'B' => (Kind::Bishop,&s[1..]),
+	b'R' => (Kind::Rook,&s[1..]),
+	b'Q' => (Kind::Queen,&s[1..]),
+	b'K' => (Kind::King,&s[1..]),
+	_ => (Kind::Pawn,s)
+    };
+    // Promotion (if any)
+    let (promote,s) = match s {
+	[rest @ .., b'=', p] => (Some(promotion(*p)?),rest),
+	_ => (None,s)
+    };
+    // Destination
+    let (to,s) = match s {
+	[rest @ .., c, r] => (square(*c,*r)?,rest),
+	_ => { return None; }
+    };
+    // Capture (if any)
+    let (capture,s) = match s {
+	[rest @ .., b'x'] => (true,rest),
+	_ => (false,s)
+    };
+    // Starting column and/or row (if given)
+    let (col,row) = match s {
+	[] => (None,None),
+	[c @ b'a'..=b'h'] => (Some(c - b'a'),None),
+	[r @ b'1'..=b'8'] => (None,Some(r - b'1')),
+	[c,r] => {
+	    let sq = square(*c,*r)?;
+	    (Some(sq.column()),Some(sq.row()))
+	}
+	_ => { return None; }
+    };
+    // Pawns are given by their column, and only when capturing
+    if kind == Kind::Pawn && (row.is_some() || col.is_some() != capture) {
+	return None;
+    }
+    if kind != Kind::Pawn && promote.is_some() {
+	return None;
+    }
+    Some(Pattern::Piece{kind,col,row,capture,to,promote})
+}
+
+/**
+ * Read the square given by a column and row (e.g. "e4").
+ */
+fn square(col: u8, row: u8) -> Option<Square> {
+    match (col,row) {
+	(c @ b'a'..=b'h',r @ b'1'..=b'8') => Some(Square::new(c - b'a',r - b'1')),
+	_ => None
+    }
+}
+
+/**
+ * Read the kind of piece a pawn is promoted to.
+ */
+fn promotion(c: u8) -> Option<Kind> {
+    match c {
+	b'N' => Some(Kind::Knight),
+	b'B' => Some(Kind::Bishop),
+	b'R' => Some(Kind::Rook),
+	b'Q' => Some(Kind::Queen),
+	_ => None
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::fen;
+#[cfg(test)]
+use super::muve;
+#[cfg(test)]
+use super::testing::random_boards;
+
+#[cfg(test)]
+fn check(fen: &str, long: &str, san: &str) {
+    let b = fen::from_str(fen).unwrap();
+    let m = muve::from_str(long,b.turn()).unwrap();
+    assert_eq!(to_string(&b,m.as_ref()), san);
+}
+
+#[test]
+fn test_01() {
+    check(fen::STARTING,"e2-e4","e4");
+    check(fen::STARTING,"Ng1-f3","Nf3");
+}
+
+#[test]
+fn test_02() {
+    // Captures, including en passant
+    check("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2","e4xd5","exd5");
+    check("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3","e5xf6","exf6");
+    check("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1","Qd1xd5","Qxd5");
+}
+
+#[test]
+fn test_03() {
+    // Disambiguation by column, row and both
+    check("4k3/8/8/8/8/8/4K3/R6R w - - 0 1","Ra1-d1","Rad1");
+    check("4k3/8/R7/8/8/8/8/R3K3 w - - 0 1","Ra1-a3","R1a3");
+    check("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1","Qh4-e1","Qh4e1");
+}
+
+#[test]
+fn test_04() {
+    // Castling, promotion, check and checkmate
+    check("5k2/8/8/8/8/8/8/4K2R w K - 0 1","O-O","O-O+");
+    check("r3k3/8/8/8/8/8/8/4K3 b q - 0 1","O-O-O","O-O-O");
+    check("8/P6k/8/8/8/8/8/4K3 w - - 0 1","a7-a8=Q","a8=Q");
+    check("k7/8/8/8/8/8/8/4K2R w - - 0 1","Rh1-h8","Rh8+");
+    check("k7/8/1K6/8/8/8/8/7R w - - 0 1","Rh1-h8","Rh8#");
+}
+
+#[test]
+fn test_05() {
+    let b = fen::from_str("4k3/8/8/8/8/8/4K3/R6R w - - 0 1").unwrap();
+    assert_eq!(from_str(&b,"Rad1").unwrap().from().to_string(), "a1");
+    assert_eq!(from_str(&b,"Rh1d1+!?").unwrap().from().to_string(), "h1");
+    assert_eq!(from_str(&b,"Rd1").err(), Some(Error::Ambiguous));
+    assert_eq!(from_str(&b,"Rxd1").err(), Some(Error::Illegal));
+    assert_eq!(from_str(&b,"Nd1").err(), Some(Error::Illegal));
+    assert_eq!(from_str(&b,"O-O").err(), Some(Error::Illegal));
+    for s in ["","R","Rz1","Ra1d1d1","e8=K","Rd1=Q","♜d1"] {
+	assert_eq!(from_str(&b,s).err(), Some(Error::Syntax), "{}", s);
+    }
+    assert_eq!(from_str(&b,"Rd1+x").err(), Some(Error::Annotation(annotation::Error::Text)));
+    assert_eq!(from_str(&b,"Rad1!+").err(), Some(Error::Annotation(annotation::Error::Check)));
+}
+
+#[test]
+fn test_06() {
+    // Every legal move on boards reached in random games round-trips
+    // through SAN
+    for board in random_boards(5,6,80) {
+	for m in movegen::legal_moves(&board) {
+	    let s = to_string(&board,m.as_ref());
+	    let n = from_str(&board,&s).unwrap();
+	    assert_eq!((n.from(),n.to(),n.promotion()), (m.from(),m.to(),m.promotion()), "{}", s);
+	}
+    }
+}
@@ -0,0 +1,675 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use super::board::*;
+use super::eval::{self, Weights};
+use super::movegen;
+use super::muve::Move;
+use super::piece::*;
+use super::square::Square;
+
+/**
+ * Score given to checkmate, from which the number of plies needed to
+ * deliver it are subtracted (so that faster mates score higher).
+ */
+pub const MATE: i32 = 30000;
+
+/**
+ * Maximum number of plies searched from the root, including
+ * quiescence search.
+ */
+pub const MAX_PLY: usize = 128;
+
+/**
+ * Bound which no score can reach.
+ */
+const INFINITY: i32 = MATE + 1;
+
+/**
+ * Default number of entries in the transposition table.
+ */
+const TABLE_SIZE: usize = 1 << 16;
+
+/**
+ * The score of a position from the perspective of the player to move,
+ * in centipawns (i.e. hundredths of a pawn).
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Score(pub i32);
+
+impl Score {
+    /**
+     * Determine the number of moves (not plies) until checkmate, if
+     * this score indicates a forced mate.  This is positive when the
+     * player to move delivers mate, and negative when they are
+     * mated.
+     */
+    pub fn mate(self) -> Option<i32> {
+	let plies = MATE - self.0.abs();
+	if plies > MAX_PLY as i32 {
+	    None
+	} else if self.0 > 0 {
+	    Some((plies + 1) / 2)
+	} else {
+	    Some(-(plies + 1) / 2)
+	}
+    }
+
+    /**
+     * Construct the score of a forced mate in a given number of moves
+     * (not plies), which is the reverse of `mate()`.  This is positive
+     * when the player to move delivers mate, and zero or negative when
+     * they are mated.
+     */
+    pub fn from_mate(moves: i32) -> Score {
+	if moves > 0 {
+	    Score(MATE - (2 * moves - 1))
+	} else {
+	    Score(-(MATE + 2 * moves))
+	}
+    }
+}
+
+/**
+ * Scores are written in pawns (e.g. "+0.35"), or as the number of
+ * moves to mate (e.g. "mate 3" or "mate -2").
+ */
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self.mate() {
+	    Some(n) => write!(f,"mate {}",n),
+	    None => {
+		let sign = if self.0 < 0 { "-" } else { "+" };
+		write!(f,"{}{}.{:02}",sign,self.0.abs() / 100,self.0.abs() % 100)
+	    }
+	}
+    }
+}
+
+/**
+ * Limits on how long a search runs.  The search stops when any limit
+ * is reached, or when it reaches MAX_PLY if none are given.
+ */
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /**
+     * Maximum depth (in plies) to search
+     */
+    pub depth: Option<usize>,
+    /**
+     * Maximum time to search for
+     */
+    pub time: Option<Duration>,
+    /**
+     * Maximum number of positions to search
+     */
+    pub nodes: Option<u64>
+}
+
+/**
+ * The result of searching a position to a given depth.
+ */
+pub struct Analysis {
+    /**
+     * Depth (in plies) which was searched
+     */
+    pub depth: usize,
+    /**
+     * Score of the position for the player to move
+     */
+    pub score: Score,
+    /**
+     * Number of positions searched so far
+     */
+    pub nodes: u64,
+    /**
+     * Time spent searching so far
+     */
+    pub time: Duration,
+    /**
+     * Principal variation (i.e. the best line found for both
+     * players), which is empty if there are no legal moves
+     */
+    pub pv: Vec<Box<dyn Move>>
+}
+
+/**
+ * Identifies a move by its squares and promotion, which is enough to
+ * find it amongst the legal moves of a board.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Key {
+    from: Square,
+    to: Square,
+    promotion: Option<Kind>
+}
+
+impl Key {
+    fn of(m: &dyn Move) -> Key {
+	Key{from: m.from(), to: m.to(), promotion: m.promotion().map(|p| p.kind)}
+    }
+}
+
+/**
+ * Indicates how a score stored in the transposition table relates to
+ * the true score of the position.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Bound {
+    /** The score is exact */
+    Exact,
+    /** The true score is at least this (i.e. the search failed high) */
+    Lower,
+    /** The true score is at most this (i.e. the search failed low) */
+    Upper
+}
+
+/**
+ * An entry in the transposition table.
+ */
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    hash: u64,
+    depth: usize,
+    score: i32,
+    bound: Bound,
+    best: Option<Key>
+}
+
+/**
+ * An iterative deepening alpha-beta search, with quiescence search
+ * at the leaves.  Moves are ordered by the best move found previously
+ * (from the transposition table), then captures (most valuable victim
+ * first), then killer moves.  The transposition table is kept between
+ * searches, so it should be cleared when starting a new game.
+ */
+pub struct Search {
+    /**
+     * Weights used to evaluate positions
+     */
+    pub weights: Weights,
+    /**
+     * Transposition table, indexed by the low bits of the hash
+     */
+    table: Vec<Option<Entry>>,
+    /**
+     * Quiet moves which caused a cutoff at each ply
+     */
+    killers: Vec<[Option<Key>;2]>,
+    /**
+     * Hashes of the positions leading to the current one, for
+     * detecting repetitions
+     */
+    path: Vec<u64>,
+    /**
+     * Limits for the current search
+     */
+    limits: Limits,
+    /**
+     * When the current search started
+     */
+    start: Instant,
+    /**
+     * Number of positions searched so far
+     */
+    nodes: u64,
+    /**
+     * Whether a limit was reached during the current search
+     */
+    stopped: bool,
+    /**
+     * Set (e.g. by another thread) to stop searching
+     */
+    stop: Arc<AtomicBool>,
+    /**
+     * Moves to which the root is restricted (or empty for every move)
+     */
+    root: Vec<Key>
+}
+
+impl Default for Search {
+    fn default() -> Self {
+	Search::new(TABLE_SIZE)
+    }
+}
+
+impl Search {
+    /**
+     * Construct a search with a transposition table of (at least) a
+     * given number of entries.
+     */
+    pub fn new(entries: usize) -> Search {
+	Search {
+	    weights: Weights::default(),
+	    table: vec![None; entries.max(1).next_power_of_two()],
+	    killers: vec![[None;2]; MAX_PLY + 1],
+	    path: Vec::new(),
+	    limits: Limits::default(),
+	    start: Instant::now(),
+	    nodes: 0,
+	    stopped: false,
+	    stop: Arc::new(AtomicBool::new(false)),
+	    root: Vec::new()
+	}
+    }
+
+    /**
+     * Get a flag which stops any search as soon as it is set (e.g. from
+     * another thread), as though a limit was reached.  Searches keep
+     * stopping immediately until the flag is cleared.
+     */
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+	self.stop.clone()
+    }
+
+    /**
+     * Restrict later searches to consider only some moves from the
+     * board searched, or every move when none are given.  Moves which
+     * are not legal on the board searched are ignored (as is the
+     * restriction, if none are legal).
+     */
+    pub fn restrict(&mut self, moves: &[Box<dyn Move>]) {
+	self.root = moves.iter().map(|m| Key::of(m.as_ref())).collect();
+    }
+
+    /**
+     * Forget everything learned in previous searches.
+     */
+    pub fn clear(&mut self) {
+	self.table.iter_mut().for_each(|e| *e = None);
+    }
+
+    /**
+     * Search a given board within some limits, deepening one ply at a
+     * time.  The result of each completed depth is passed to a given
+     * function as it becomes available, and the result of the deepest
+     * is returned.
+     */
+    pub fn run(&mut self, board: &Board, limits: Limits, mut report: impl FnMut(&Analysis)) -> Analysis {
+	self.limits = limits;
+	self.start = Instant::now();
+	self.nodes = 0;
+	self.stopped = false;
+	self.killers.iter_mut().for_each(|k| *k = [None;2]);
+	let mut b = *board;
+	let mut result = Analysis{depth: 0, score: Score(0), nodes: 0, time: Duration::ZERO, pv: Vec::new()};
+	if movegen::legal_moves(board).is_empty() {
+	    result.score = Score(if board.in_check(board.turn()) { -MATE } else { 0 });
+	    return result;
+	}
+	let max = limits.depth.unwrap_or(MAX_PLY).clamp(1,MAX_PLY);
+	for depth in 1..=max {
+	    let mut pv = Vec::new();
+	    let score = self.alpha_beta(&mut b,depth,0,-INFINITY,INFINITY,&mut pv);
+	    // An unfinished search is only used if there is nothing else
+	    if self.stopped && (depth > 1 || pv.is_empty()) {
+		break;
+	    }
+	    result = Analysis{depth, score: Score(score), nodes: self.nodes, time: self.start.elapsed(), pv: resolve(board,&pv)};
+	    report(&result);
+	    // A mate found within the depth searched cannot be improved on
+	    if self.stopped || (MATE - score.abs()) as usize <= depth {
+		break;
+	    }
+	}
+	result
+    }
+
+    /**
+     * Check whether any limit (other than depth) has been reached, or
+     * the search has been stopped.  The clock and the stop flag are
+     * checked only occasionally, since they are slow.
+     */
+    fn check_limits(&mut self) {
+	let nodes = self.limits.nodes.is_some_and(|n| self.nodes >= n);
+	let time = self.nodes.is_multiple_of(1024)
+	    && (self.stop.load(Ordering::Relaxed) || self.limits.time.is_some_and(|t| self.start.elapsed() >= t));
+	self.stopped |= nodes || time;
+    }
+
+    /**
+     * Search a board to a given depth, producing its score for the
+     * player to move (within the window alpha to beta) along with the
+     * best line found.
+     */
+    fn alpha_beta(&mut self, board: &mut Board, depth: usize, ply: usize, mut alpha: i32, beta: i32,
+		  pv: &mut Vec<Key>) -> i32 {
+	pv.clear();
+	self.nodes += 1;
+	self.check_limits();
+	if self.stopped {
+	    return 0;
+	}
+	let hash = board.hash();
+	if ply > 0 && (board.halfmove() >= 100 || self.path.contains(&hash)) {
+	    return 0;
+	}
+	if depth == 0 || ply >= MAX_PLY {
+	    return self.quiesce(board,ply,alpha,beta);
+	}
+	// Consult the transposition table
+	let mut best_key = None;
+	if let Some(e) = self.probe(hash) {
+	    best_key = e.best;
+	    let score = from_table(e.score,ply);
+	    if ply > 0 && e.depth >= depth {
+		match e.bound {
+		    Bound::Exact => return score,
+		    Bound::Lower if score >= beta => return score,
+		    Bound::Upper if score <= alpha => return score,
+		    _ => {}
+		}
+	    }
+	}
+	let mut moves = movegen::legal_moves(board);
+	if moves.is_empty() {
+	    return if board.in_check(board.turn()) { -(MATE - ply as i32) } else { 0 };
+	}
+	if ply == 0 && moves.iter().any(|m| self.root.contains(&Key::of(m.as_ref()))) {
+	    moves.retain(|m| self.root.contains(&Key::of(m.as_ref())));
+	}
+	self.order(board,&mut moves,best_key,ply);
+	let original = alpha;
+	let mut best = -INFINITY;
+	let mut child = Vec::new();
+	self.path.push(hash);
+	for m in &moves {
+	    let k = Key::of(m.as_ref());
+	    let quiet = is_quiet(board,m.as_ref());
+	    let undo = m.make(board).unwrap();
+	    let score = -self.alpha_beta(board,depth - 1,ply + 1,-beta,-alpha,&mut child);
+	    board.restore(&undo);
+	    if self.stopped {
+		break;
+	    }
+	    if score > best {
+		best = score;
+		best_key = Some(k);
+		if score > alpha {
+		    alpha = score;
+		    pv.clear();
+		    pv.push(k);
+		    pv.extend_from_slice(&child);
+		}
+	    }
+	    if alpha >= beta {
+		if quiet && self.killers[ply][0] != Some(k) {
+		    self.killers[ply] = [Some(k),self.killers[ply][0]];
+		}
+		break;
+	    }
+	}
+	self.path.pop();
+	// A restricted root may not have its true score
+	if !self.stopped && (ply > 0 || self.root.is_empty()) {
+	    let bound = if best <= original { Bound::Upper } else if best >= beta { Bound::Lower } else { Bound::Exact };
+	    self.store(Entry{hash, depth, score: to_table(best,ply), bound, best: best_key});
+	}
+	best
+    }
+
+    /**
+     * Search only captures and promotions (or every move when in
+     * check) until the position is quiet, so that positions are not
+     * evaluated in the middle of an exchange.
+     */
+    fn quiesce(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+	self.nodes += 1;
+	self.check_limits();
+	if self.stopped {
+	    return 0;
+	}
+	let check = board.in_check(board.turn());
+	let mut moves = movegen::legal_moves(board);
+	if moves.is_empty() {
+	    return if check { -(MATE - ply as i32) } else { 0 };
+	}
+	let mut best = -INFINITY;
+	if !check {
+	    // The player to move can choose not to capture
+	    best = eval::evaluate(board,&self.weights);
+	    if best >= beta || ply >= MAX_PLY {
+		return best;
+	    }
+	    alpha = alpha.max(best);
+	    moves.retain(|m| !is_quiet(board,m.as_ref()));
+	}
+	self.order(board,&mut moves,None,ply);
+	for m in &moves {
+	    let undo = m.make(board).unwrap();
+	    let score = -self.quiesce(board,ply + 1,-beta,-alpha);
+	    board.restore(&undo);
+	    if self.stopped {
+		break;
+	    }
+	    if score > best {
+		best = score;
+		alpha = alpha.max(score);
+	    }
+	    if alpha >= beta {
+		break;
+	    }
+	}
+	best
+    }
+
+    /**
+     * Order moves so that those most likely to be best come first:
+     * the best move found previously, then captures and promotions
+     * (most valuable victim first, then least valuable attacker),
+     * then killer moves, then everything else.
+     */
+    fn order(&self, board: &Board, moves: &mut [Box<dyn Move>], best: Option<Key>, ply: usize) {
+	let killers = self.killers[ply.min(MAX_PLY)];
+	moves.sort_by_cached_key(|m| {
+	    let k = Key::of(m.as_ref());
+	    let rank = if Some(k) == best {
+		1_000_000
+	    } else if !is_quiet(board,m.as_ref()) {
+		let victim = board.get(k.to).kind.value() as i32;
+		let promotion = k.promotion.map(|p| p.value() as i32).unwrap_or(0);
+		100_000 + (100 * (victim + promotion)) - board.get(k.from).kind.value() as i32
+	    } else if killers.contains(&Some(k)) {
+		50_000
+	    } else {
+		0
+	    };
+	    -rank
+	});
+    }
+
+    /**
+     * Look up the entry (if any) for a given position in the
+     * transposition table.
+     */
+    fn probe(&self, hash: u64) -> Option<Entry> {
+	let i = (hash as usize) & (self.table.len() - 1);
+	self.table[i].filter(|e| e.hash == hash)
+    }
+
+    /**
+     * Store an entry in the transposition table, unless this would
+     * replace a deeper search of the same position.
+     */
+    fn store(&mut self, entry: Entry) {
+	let i = (entry.hash as usize) & (self.table.len() - 1);
+	match self.table[i] {
+	    Some(e) if e.hash == entry.hash && e.depth > entry.depth => {}
+	    _ => self.table[i] = Some(entry)
+	}
+    }
+}
+
+/**
+ * Search a given board within some limits, using a fresh search.
+ */
+pub fn search(board: &Board, limits: Limits) -> Analysis {
+    Search::default().run(board,limits,|_| {})
+}
+
+/**
+ * Check whether a move is quiet (i.e. neither a capture nor a
+ * promotion).
+ */
+fn is_quiet(board: &Board, m: &dyn Move) -> bool {
+    let (from,to) = (m.from(),m.to());
+    let pawn = board.get(from).kind == Kind::Pawn;
+    board.get(to) == BLANK && !(pawn && from.column() != to.column()) && m.promotion().is_none()
+}
+
+/**
+ * Adjust a mate score so that it is relative to the current position
+ * (rather than the root) before storing it in the transposition table.
+ */
+fn to_table(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+	score + ply as i32
+    } else if score < -(MATE - MAX_PLY as i32) {
+	score - ply as i32
+    } else {
+	score
+    }
+}
+
+/**
+ * Reverse the adjustment made by `to_table()`.
+ */
+fn from_table(score: i32, ply: usize) -> i32 {
+    if score > MATE - MAX_PLY as i32 {
+	score - ply as i32
+    } else if score < -(MATE - MAX_PLY as i32) {
+	score + ply as i32
+    } else {
+	score
+    }
+}
+
+/**
+ * Convert a line of moves into the legal moves they identify when
+ * played from a given board.
+ */
+fn resolve(board: &Board, line: &[Key]) -> Vec<Box<dyn Move>> {
+    let mut b = *board;
+    let mut moves = Vec::new();
+    for k in line {
+	let m = match movegen::legal_moves(&b).into_iter().find(|m| Key::of(m.as_ref()) == *k) {
+	    Some(m) => m,
+	    None => break
+	};
+	b = m.apply(b).unwrap();
+	moves.push(m);
+    }
+    moves
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use super::fen;
+#[cfg(test)]
+use super::san;
+
+/**
+ * Search a given board (in FEN) to a given depth, producing the score
+ * and the principal variation in SAN.
+ */
+#[cfg(test)]
+fn check(fen: &str, depth: usize) -> (Score,Vec<String>) {
+    let board = fen::from_str(fen).unwrap();
+    let a = search(&board,Limits{depth: Some(depth), ..Limits::default()});
+    let mut b = board;
+    let mut line = Vec::new();
+    for m in &a.pv {
+	line.push(san::to_string(&b,m.as_ref()));
+	b = m.apply(b).unwrap();
+    }
+    (a.score,line)
+}
+
+#[test]
+fn test_01() {
+    // Mate in one
+    let (score,pv) = check("k7/8/1K6/8/8/8/8/7R w - - 0 1",3);
+    assert_eq!(pv, vec!["Rh8#"]);
+    assert_eq!(score.mate(), Some(1));
+    assert_eq!(score.to_string(), "mate 1");
+}
+
+#[test]
+fn test_02() {
+    // Mate in two (rook ladder)
+    let (score,pv) = check("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1",4);
+    assert_eq!(score.mate(), Some(2));
+    assert_eq!(pv.len(), 3);
+    assert!(pv[2].ends_with('#'));
+}
+
+#[test]
+fn test_03() {
+    // Take the undefended queen
+    let (score,pv) = check("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1",2);
+    assert_eq!(pv[0], "Rxd5");
+    assert!(score.0 >= 400);
+}
+
+#[test]
+fn test_04() {
+    // Quiescence search sees the pawn is defended
+    let (_,pv) = check("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1",1);
+    assert_ne!(pv[0], "Qxd5");
+}
+
+#[test]
+fn test_05() {
+    // No legal moves
+    let (score,pv) = check("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",3);
+    assert!(pv.is_empty());
+    assert_eq!(score, Score(0));
+    let (score,pv) = check("k6R/8/1K6/8/8/8/8/8 b - - 0 1",3);
+    assert!(pv.is_empty());
+    assert_eq!(score.mate(), Some(0));
+}
+
+#[test]
+fn test_06() {
+    // Limits are respected, and each depth is reported
+    let mut depths = Vec::new();
+    let a = Search::default().run(&INITIAL,Limits{depth: Some(3), ..Limits::default()},|a| depths.push(a.depth));
+    assert_eq!(depths, vec![1,2,3]);
+    assert_eq!(a.depth, 3);
+    assert!(!a.pv.is_empty());
+    let a = search(&INITIAL,Limits{nodes: Some(500), ..Limits::default()});
+    assert!(a.nodes <= 500 && !a.pv.is_empty());
+    let a = search(&INITIAL,Limits{time: Some(Duration::from_millis(50)), ..Limits::default()});
+    assert!(!a.pv.is_empty());
+}
+
+#[test]
+fn test_07() {
+    assert_eq!(Score(35).to_string(), "+0.35");
+    assert_eq!(Score(-120).to_string(), "-1.20");
+    assert_eq!(Score(-(MATE - 4)).to_string(), "mate -2");
+    assert_eq!(Score(MATE - 5).mate(), Some(3));
+    for n in -3..=3 {
+	assert_eq!(Score::from_mate(n).mate(), Some(n));
+    }
+    assert_eq!(Score::from_mate(0), Score(-MATE));
+}
+
+#[test]
+fn test_08() {
+    // Only the moves given are considered at the root
+    let board = fen::from_str("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1").unwrap();
+    let limits = Limits{depth: Some(3), ..Limits::default()};
+    let mut s = Search::default();
+    s.restrict(&[san::from_str(&board,"Qh1+").unwrap(),san::from_str(&board,"Qb2+").unwrap()]);
+    let a = s.run(&board,limits,|_| {});
+    assert!(a.score.mate().is_none_or(|n| n > 1));
+    assert!(["Qh1+","Qb2+"].contains(&san::to_string(&board,a.pv[0].as_ref()).as_str()));
+    // Without any restriction, mate is found again
+    s.restrict(&[]);
+    assert_eq!(s.run(&board,limits,|_| {}).score.mate(), Some(1));
+}
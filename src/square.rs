@@ -4,7 +4,7 @@ use std::str;
 /**
  * Represents a give position on the board, such as "a1" or "h5", etc.
  */
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Square {
     col: u8, // where 0 <= col <= 7    
     row: u8  // where 0 <= row <= 7
@@ -46,34 +46,50 @@ impl Square {
     }    
 }
 /**
- * Convert a string into a square.
+ * Identifies why a string could not be parsed as a square.
  */
-pub fn from_str(s: &str) -> Result<Square, ()> {
-    if s.len() < 2 {
-	Err(())
-    } else {
-	// Parse column
-	let c = parse_col(&s[0..1]).ok_or(())?;
-	// Parse row
-	let r = parse_row(&s[1..2]).ok_or(())?;
-	// Done
-	Ok(Square { row: r, col: c })
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** The string is not exactly two characters long */
+    Length,
+    /** The column is not between "a" and "h" */
+    Column,
+    /** The row is not between "1" and "8" */
+    Row
+}
+
+/**
+ * Convert a string into a square, which must consist of exactly a
+ * column and a row (e.g. "e4").
+ */
+pub fn from_str(s: &str) -> Result<Square, Error> {
+    let mut chars = s.chars();
+    match (chars.next(),chars.next(),chars.next()) {
+	(Some(c),Some(r),None) => {
+	    // Parse column
+	    let col = parse_col(c).ok_or(Error::Column)?;
+	    // Parse row
+	    let row = parse_row(r).ok_or(Error::Row)?;
+	    // Done
+	    Ok(Square { row, col })
+	}
+	_ => Err(Error::Length)
     }
 }
 
 /**
  * Convert column identifier into integer
  */
-fn parse_col(s: &str) -> Option<u8> {
-    match s {
-        "a" => Some(0),
-        "b" => Some(1),
-        "c" => Some(2),
-        "d" => Some(3),
-        "e" => Some(4),
-        "f" => Some(5),
-        "g" => Some(6),
-        "h" => Some(7),
+fn parse_col(c: char) -> Option<u8> {
+    match c {
+        'a' => Some(0),
+        'b' => Some(1),
+        'c' => Some(2),
+        'd' => Some(3),
+        'e' => Some(4),
+        'f' => Some(5),
+        'g' => Some(6),
+        'h' => Some(7),
         _ => None,
     }
 }
@@ -81,17 +97,17 @@ fn parse_col(s: &str) -> Option<u8> {
 /**
  * Convert row into integer
  */
-fn parse_row(s: &str) -> Option<u8> {
+fn parse_row(c: char) -> Option<u8> {
     // This could be simpler!
-    match s {
-        "1" => Some(0),
-        "2" => Some(1),
-        "3" => Some(2),
-        "4" => Some(3),
-        "5" => Some(4),
-        "6" => Some(5),
-        "7" => Some(6),
-        "8" => Some(7),
+    match c {
+        '1' => Some(0),
+        '2' => Some(1),
+        '3' => Some(2),
+        '4' => Some(3),
+        '5' => Some(4),
+        '6' => Some(5),
+        '7' => Some(6),
+        '8' => Some(7),
         _ => None,
     }
 }
@@ -181,3 +197,23 @@ fn test_11() {
 fn test_12() {
     assert!(from_str("i1").is_err());
 }
+
+#[test]
+fn test_13() {
+    assert_eq!(from_str("e"), Err(Error::Length));
+    assert_eq!(from_str("e44"), Err(Error::Length));
+    assert_eq!(from_str("é4"), Err(Error::Column));
+    assert_eq!(from_str("eé"), Err(Error::Row));
+    assert_eq!(from_str("é"), Err(Error::Length));
+}
+
+#[test]
+fn test_14() {
+    // Every square round-trips through its textual form
+    for c in 0..8 {
+	for r in 0..8 {
+	    let s = Square::new(c,r);
+	    assert_eq!(from_str(&s.to_string()), Ok(s));
+	}
+    }
+}
@@ -0,0 +1,1138 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::str;
+use super::board::Board;
+use super::movegen;
+use super::movegen::{DIAGONALS, DIRECTIONS, KNIGHT, PROMOTIONS, STRAIGHTS};
+use super::muve::Move;
+use super::piece::*;
+use super::zobrist;
+
+/**
+ * Largest number of pieces (including kings) which tables can be
+ * generated for.
+ */
+pub const MAX_PIECES: usize = 4;
+
+/**
+ * Marks the start of a table written to disk.
+ */
+const MAGIC: &[u8] = b"CCTB\x01";
+
+/**
+ * Stored value of a draw (or, during generation, of a position not yet
+ * resolved).
+ */
+const DRAW: u8 = 0;
+
+/**
+ * Stored values of losses start here, where a win is stored as its
+ * number of plies.
+ */
+const LOSS: u8 = 128;
+
+/**
+ * Stored value of a position which cannot arise (e.g. the player not
+ * to move is in check).
+ */
+const ILLEGAL: u8 = 255;
+
+/**
+ * Marks a position whose value has been passed on to the positions
+ * before it during generation.
+ */
+const DONE: u8 = 255;
+
+/**
+ * Marks a square of a piece which has been taken.
+ */
+const NONE: u8 = 64;
+
+/**
+ * Identifies why a material signature or a table could not be read.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** The material signature is malformed (e.g. "KQ") */
+    Material(String),
+    /** The material has too many pieces for a table (i.e. how many) */
+    Pieces(usize),
+    /** The table is not in the expected format */
+    Format,
+    /** The table has the wrong number of positions (i.e. its length in bytes) */
+    Length(usize)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Error::Material(s) => write!(f,"invalid material \"{}\"",s),
+	    Error::Pieces(n) => write!(f,"{} pieces is more than {}",n,MAX_PIECES),
+	    Error::Format => write!(f,"not a tablebase file"),
+	    Error::Length(n) => write!(f,"wrong number of positions ({} bytes)",n)
+	}
+    }
+}
+
+// ======================================================
+// Value
+// ======================================================
+
+/**
+ * The exact result of a position with perfect play, from the point of
+ * view of the player to move.  Wins and losses give the number of plies
+ * until checkmate (i.e. the distance to mate).  The fifty move rule is
+ * not considered.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    /** The player to move mates in this many plies */
+    Win(usize),
+    /** Neither player can force mate */
+    Draw,
+    /** The player to move is mated in this many plies */
+    Loss(usize)
+}
+
+impl Value {
+    /**
+     * Get the value of a move for the player making it, given the
+     * value of the position it reaches.
+     */
+    pub fn parent(self) -> Value {
+	match self {
+	    Value::Win(n) => Value::Loss(n + 1),
+	    Value::Draw => Value::Draw,
+	    Value::Loss(n) => Value::Win(n + 1)
+	}
+    }
+
+    /**
+     * Check whether two values have the same result, regardless of how
+     * long mate takes.
+     */
+    pub fn same_result(self, other: Value) -> bool {
+	std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    fn rank(self) -> i32 {
+	match self {
+	    Value::Win(n) => 1000 - n as i32,
+	    Value::Draw => 0,
+	    Value::Loss(n) => n as i32 - 1000
+	}
+    }
+
+    fn from_byte(b: u8) -> Option<Value> {
+	match b {
+	    DRAW => Some(Value::Draw),
+	    ILLEGAL => None,
+	    LOSS.. => Some(Value::Loss((b - LOSS) as usize)),
+	    _ => Some(Value::Win(b as usize))
+	}
+    }
+
+    fn to_byte(self) -> u8 {
+	match self {
+	    Value::Win(n) => n as u8,
+	    Value::Draw => DRAW,
+	    Value::Loss(n) => LOSS + n as u8
+	}
+    }
+}
+
+/**
+ * Values are ordered from the point of view of the player to move, so
+ * a faster win is greater and a faster loss is less.
+ */
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+	self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+	Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Value::Win(n) => write!(f,"win, mate in {}",n.div_ceil(2)),
+	    Value::Draw => write!(f,"draw"),
+	    Value::Loss(0) => write!(f,"loss, checkmated"),
+	    Value::Loss(n) => write!(f,"loss, mated in {}",n / 2)
+	}
+    }
+}
+
+// ======================================================
+// Signature
+// ======================================================
+
+/**
+ * The material signature of an endgame, which gives the pieces of each
+ * player (including their kings) such as "KRK" where white has a king
+ * and rook against black's lone king.  Pieces are kept in order of
+ * value, most valuable first.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Signature {
+    white: Vec<Kind>,
+    black: Vec<Kind>
+}
+
+impl Signature {
+    /**
+     * Construct the material from each player's pieces.
+     */
+    pub fn new(mut white: Vec<Kind>, mut black: Vec<Kind>) -> Signature {
+	white.sort_by_key(|k| std::cmp::Reverse(*k as u8));
+	black.sort_by_key(|k| std::cmp::Reverse(*k as u8));
+	Signature{white, black}
+    }
+
+    /**
+     * Determine the material on a given board.
+     */
+    pub fn of(board: &Board) -> Signature {
+	let pieces: Vec<Piece> = board.pieces().map(|(_,p)| p).collect();
+	let kinds = |player| pieces.iter().filter(|p| p.player == player).map(|p| p.kind).collect();
+	Signature::new(kinds(Player::White),kinds(Player::Black))
+    }
+
+    /**
+     * Get the number of pieces (including kings).
+     */
+    pub fn len(&self) -> usize {
+	self.white.len() + self.black.len()
+    }
+
+    /**
+     * Check whether there are no pieces (which is never the case for
+     * valid material).
+     */
+    pub fn is_empty(&self) -> bool {
+	self.len() == 0
+    }
+
+    /**
+     * Swap the pieces of each player.
+     */
+    pub fn mirror(&self) -> Signature {
+	Signature{white: self.black.clone(), black: self.white.clone()}
+    }
+
+    /**
+     * Check whether white has the stronger pieces, which is how tables
+     * are stored.  Positions where black is stronger are looked up by
+     * swapping the players.
+     */
+    pub fn is_canonical(&self) -> bool {
+	let rank = |ks: &[Kind]| (ks.len(),ks.iter().map(|k| *k as u8).collect::<Vec<u8>>());
+	rank(&self.white) >= rank(&self.black)
+    }
+
+    /**
+     * Check whether neither player could ever checkmate, which is when
+     * there is nothing besides the kings but a single bishop or
+     * knight.  No table is needed, since every position is drawn.
+     */
+    pub fn is_dead(&self) -> bool {
+	match self.len() {
+	    2 => true,
+	    3 => self.white.iter().chain(&self.black).any(|&k| k == Kind::Bishop || k == Kind::Knight),
+	    _ => false
+	}
+    }
+
+    /**
+     * Get every piece in the order used to index tables, which is
+     * white's pieces (from its king) followed by black's.
+     */
+    fn pieces(&self) -> Vec<Piece> {
+	let white = self.white.iter().map(|&kind| Piece{player: Player::White, kind});
+	white.chain(self.black.iter().map(|&kind| Piece{player: Player::Black, kind})).collect()
+    }
+
+    /**
+     * Get the material reached by taking a piece or promoting a pawn,
+     * where the table needed is always smaller or has fewer pawns.
+     */
+    fn successors(&self) -> Vec<Signature> {
+	let mut r = Vec::new();
+	for (mine,theirs,white) in [(&self.white,&self.black,true),(&self.black,&self.white,false)] {
+	    let make = |pieces: Vec<Kind>| if white {
+		Signature::new(pieces,theirs.clone())
+	    } else {
+		Signature::new(theirs.clone(),pieces)
+	    };
+	    for (i,&k) in mine.iter().enumerate().skip(1) {
+		let mut pieces = mine.clone();
+		pieces.remove(i);
+		r.push(make(pieces.clone()));
+		if k == Kind::Pawn {
+		    for p in PROMOTIONS {
+			pieces.push(p);
+			r.push(make(pieces.clone()));
+			pieces.pop();
+		    }
+		}
+	    }
+	}
+	r
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	for k in self.white.iter().chain(&self.black) {
+	    write!(f,"{}",k.to_white_string())?;
+	}
+	Ok(())
+    }
+}
+
+/**
+ * Signatures are written as white's pieces followed by black's, each
+ * starting with its king (e.g. "KBNK" or "KQKR").
+ */
+impl str::FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Signature,Error> {
+	let invalid = || Error::Material(s.to_string());
+	let mut kinds = Vec::new();
+	for c in s.chars() {
+	    let k = match c.to_ascii_uppercase() {
+		'K' => Kind::King,
+		'Q' => Kind::Queen,
+		'R' => Kind::Rook,
+		'B' => Kind::Bishop,
+		'N' => Kind::Knight,
+		'P' => Kind::Pawn,
+		_ => return Err(invalid())
+	    };
+	    kinds.push(k);
+	}
+	let second = kinds.iter().skip(1).position(|&k| k == Kind::King).map(|i| i + 1);
+	match second {
+	    Some(i) if kinds[0] == Kind::King && !kinds[i + 1..].contains(&Kind::King) => {
+		let black = kinds.split_off(i);
+		let m = Signature::new(kinds,black);
+		if m.len() > MAX_PIECES {
+		    return Err(Error::Pieces(m.len()));
+		}
+		Ok(m)
+	    }
+	    _ => Err(invalid())
+	}
+    }
+}
+
+// ======================================================
+// Table
+// ======================================================
+
+/**
+ * The values of every position for some material (with white being
+ * the stronger side).  Each piece can be on any square, so a position
+ * is indexed by the player to move and then the square of each piece.
+ * Castling and en passant are not included.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    signature: Signature,
+    pieces: Vec<Piece>,
+    values: Vec<u8>
+}
+
+impl Table {
+    /**
+     * Get the material signature this table is for.
+     */
+    pub fn signature(&self) -> &Signature {
+	&self.signature
+    }
+
+    /**
+     * Count the positions which are wins, draws and losses for the
+     * player to move (ignoring those which cannot arise).
+     */
+    pub fn counts(&self) -> (usize,usize,usize) {
+	let mut counts = (0,0,0);
+	for v in self.values.iter().filter_map(|&b| Value::from_byte(b)) {
+	    match v {
+		Value::Win(_) => counts.0 += 1,
+		Value::Draw => counts.1 += 1,
+		Value::Loss(_) => counts.2 += 1
+	    }
+	}
+	counts
+    }
+
+    /**
+     * Get the longest distance to mate in this table (in plies).
+     */
+    pub fn longest(&self) -> usize {
+	self.values.iter().filter_map(|&b| match Value::from_byte(b) {
+	    Some(Value::Win(n)) | Some(Value::Loss(n)) => Some(n),
+	    _ => None
+	}).max().unwrap_or(0)
+    }
+
+    /**
+     * Write this table in the format used on disk, which is a header
+     * giving the material followed by one byte per position.
+     */
+    pub fn to_bytes(&self) -> Vec<u8> {
+	let name = self.signature.to_string();
+	let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + name.len() + self.values.len());
+	bytes.extend_from_slice(MAGIC);
+	bytes.push(name.len() as u8);
+	bytes.extend_from_slice(name.as_bytes());
+	bytes.extend_from_slice(&self.values);
+	bytes
+    }
+
+    /**
+     * Read a table written by `to_bytes()`.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Table,Error> {
+	let rest = bytes.strip_prefix(MAGIC).ok_or(Error::Format)?;
+	let (&n,rest) = rest.split_first().ok_or(Error::Format)?;
+	if rest.len() < n as usize {
+	    return Err(Error::Format);
+	}
+	let (name,values) = rest.split_at(n as usize);
+	let signature: Signature = str::from_utf8(name).map_err(|_| Error::Format)?.parse()?;
+	if !signature.is_canonical() {
+	    return Err(Error::Format);
+	}
+	if values.len() != size(signature.len()) {
+	    return Err(Error::Length(values.len()));
+	}
+	Ok(Table{pieces: signature.pieces(), signature, values: values.to_vec()})
+    }
+
+    fn get(&self, index: usize) -> Option<Value> {
+	Value::from_byte(self.values[index])
+    }
+}
+
+/**
+ * Get the number of positions in a table with a given number of
+ * pieces.
+ */
+fn size(pieces: usize) -> usize {
+    2 << (6 * pieces)
+}
+
+/**
+ * Determine the index of a position in a table.
+ */
+fn index(squares: &[u8], turn: Player) -> usize {
+    let mut i = 0;
+    for &s in squares.iter().rev() {
+	i = (i << 6) | s as usize;
+    }
+    (i << 1) | (turn == Player::Black) as usize
+}
+
+/**
+ * Determine the player to move and the squares of each piece from the
+ * index of a position in a table.
+ */
+fn position(index: usize, n: usize) -> (Player,[u8;MAX_PIECES]) {
+    let mut squares = [NONE;MAX_PIECES];
+    for (k,s) in squares.iter_mut().enumerate().take(n) {
+	*s = ((index >> (1 + 6 * k)) & 63) as u8;
+    }
+    let turn = if index & 1 == 0 { Player::White } else { Player::Black };
+    (turn,squares)
+}
+
+// ======================================================
+// Tablebase
+// ======================================================
+
+/**
+ * A collection of tables which positions can be looked up in.  Tables
+ * are generated by retrograde analysis, working backwards from every
+ * checkmate.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Tablebase {
+    tables: HashMap<Signature,Table>
+}
+
+impl Tablebase {
+    /**
+     * Construct a tablebase with no tables.
+     */
+    pub fn new() -> Tablebase {
+	Tablebase::default()
+    }
+
+    /**
+     * Add a table (e.g. one read from disk).
+     */
+    pub fn insert(&mut self, table: Table) {
+	self.tables.insert(table.signature.clone(),table);
+    }
+
+    /**
+     * Get the table for some material (if it is present).  Material
+     * where black is stronger shares the table where white is.
+     */
+    pub fn get(&self, material: &Signature) -> Option<&Table> {
+	if material.is_canonical() {
+	    self.tables.get(material)
+	} else {
+	    self.tables.get(&material.mirror())
+	}
+    }
+
+    /**
+     * Generate the table for some material, along with those it
+     * depends on (i.e. after taking a piece or promoting a pawn) which
+     * are not already present.  This produces the material of every
+     * table generated, in the order they were generated.
+     */
+    pub fn generate(&mut self, material: &Signature) -> Result<Vec<Signature>,Error> {
+	if material.len() > MAX_PIECES {
+	    return Err(Error::Pieces(material.len()));
+	}
+	let material = if material.is_canonical() { material.clone() } else { material.mirror() };
+	let mut done = Vec::new();
+	if material.is_dead() || self.tables.contains_key(&material) {
+	    return Ok(done);
+	}
+	for m in material.successors() {
+	    done.extend(self.generate(&m)?);
+	}
+	let table = Generator::new(self,&material).run();
+	self.insert(table);
+	done.push(material);
+	Ok(done)
+    }
+
+    /**
+     * Look up the value of a given board, which is nothing if there is
+     * no table for its material or castling is still possible.  When
+     * en passant is possible, the value is found from those of its
+     * moves.
+     */
+    pub fn probe(&self, board: &Board) -> Option<Value> {
+	if board.castling() != 0 {
+	    return None;
+	}
+	if board.en_passant().is_some_and(|s| zobrist::can_take_en_passant(board,s)) {
+	    let moves = self.moves(board)?;
+	    return Some(match moves.into_iter().map(|(_,v)| v).max() {
+		Some(v) => v,
+		None if board.in_check(board.turn()) => Value::Loss(0),
+		None => Value::Draw
+	    });
+	}
+	let pieces: Vec<(Piece,u8)> = board.pieces().map(|(s,p)| (p,s.to_offset() as u8)).collect();
+	self.lookup(&pieces,board.turn())
+    }
+
+    /**
+     * Look up the value of every legal move on a given board for the
+     * player making it, best first.  This is nothing if any position
+     * reached cannot be looked up.
+     */
+    pub fn moves(&self, board: &Board) -> Option<Vec<(Box<dyn Move>,Value)>> {
+	let mut moves = Vec::new();
+	for m in movegen::legal_moves(board) {
+	    let v = self.probe(&m.apply(*board).unwrap())?.parent();
+	    moves.push((m,v));
+	}
+	moves.sort_by_key(|m| std::cmp::Reverse(m.1));
+	Some(moves)
+    }
+
+    /**
+     * Look up the value of a position given by its pieces (with their
+     * squares) and the player to move.
+     */
+    fn lookup(&self, pieces: &[(Piece,u8)], turn: Player) -> Option<Value> {
+	if pieces.len() > MAX_PIECES {
+	    return None;
+	}
+	let kinds = |player| pieces.iter().filter(|(p,_)| p.player == player).map(|(p,_)| p.kind).collect();
+	let material = Signature::new(kinds(Player::White),kinds(Player::Black));
+	if material.is_dead() {
+	    return Some(Value::Draw);
+	}
+	let mirror = !material.is_canonical();
+	let table = self.get(&material)?;
+	// Swap the players if the table is for the other side
+	let (turn,pieces): (Player,Vec<(Piece,u8)>) = if mirror {
+	    (turn.flip(),pieces.iter().map(|(p,s)| (p.flip(),s ^ 56)).collect())
+	} else {
+	    (turn,pieces.to_vec())
+	};
+	let mut used = [false;MAX_PIECES];
+	let mut squares = [NONE;MAX_PIECES];
+	for (k,piece) in table.pieces.iter().enumerate() {
+	    let i = (0..pieces.len()).find(|&i| !used[i] && pieces[i].0 == *piece)?;
+	    used[i] = true;
+	    squares[k] = pieces[i].1;
+	}
+	table.get(index(&squares[..table.pieces.len()],turn))
+    }
+}
+
+// ======================================================
+// Generation
+// ======================================================
+
+/**
+ * Summarises the moves from a position during generation, where moves
+ * which take or promote lead to other tables (which are already
+ * known).
+ */
+struct Summary {
+    /** Number of legal moves staying within the table */
+    inside: u8,
+    /** Whether there are any legal moves */
+    any: bool,
+    /** Fastest win by leaving the table (if any) */
+    win: Option<usize>,
+    /** Whether leaving the table can draw */
+    draw: bool,
+    /** Slowest loss by leaving the table */
+    loss: usize
+}
+
+/**
+ * Where a move which takes a piece and/or promotes a pawn leads, which
+ * is worked out once for each such kind of move.
+ */
+struct Exit<'a> {
+    /** Piece taken (if any) */
+    taken: Option<usize>,
+    /** Pawn promoted and what to (if any) */
+    promotion: Option<(usize,Kind)>,
+    /** Table reached, or nothing if every position reached is drawn */
+    table: Option<&'a Table>,
+    /** Whether the players are swapped in the table reached */
+    mirror: bool,
+    /** Which piece of this table is each piece of the table reached */
+    slots: Vec<usize>
+}
+
+/**
+ * Builds a table by retrograde analysis.  Positions are resolved in
+ * order of their distance to mate, using buckets of positions for each
+ * number of plies.  When a position is lost, every position before it
+ * wins; when every move from a position reaches a win for the
+ * opponent, it is lost.  Positions never resolved are drawn.  En
+ * passant is not considered within a table.
+ */
+struct Generator<'a> {
+    base: &'a Tablebase,
+    signature: Signature,
+    pieces: Vec<Piece>,
+    values: Vec<u8>,
+    /** Number of moves from each position not yet known to lose */
+    remaining: Vec<u8>,
+    buckets: Vec<Vec<u32>>,
+    exits: Vec<Exit<'a>>
+}
+
+impl<'a> Generator<'a> {
+    fn new(base: &'a Tablebase, material: &Signature) -> Generator<'a> {
+	let n = size(material.len());
+	let mut g = Generator{base, signature: material.clone(), pieces: material.pieces(), values: vec![DRAW;n],
+			      remaining: vec![0;n], buckets: Vec::new(), exits: Vec::new()};
+	let pieces = &g.pieces;
+	let mut taken: Vec<Option<usize>> = vec![None];
+	taken.extend((0..pieces.len()).filter(|&k| pieces[k].kind != Kind::King).map(Some));
+	let mut promotions: Vec<Option<(usize,Kind)>> = vec![None];
+	for k in (0..pieces.len()).filter(|&k| pieces[k].kind == Kind::Pawn) {
+	    promotions.extend(PROMOTIONS.iter().map(|&q| Some((k,q))));
+	}
+	for &t in &taken {
+	    for &p in &promotions {
+		// A pawn can only promote while taking the other player's piece
+		let possible = match (t,p) {
+		    (None,None) => false,
+		    (Some(t),Some((k,_))) => pieces[t].player != pieces[k].player,
+		    _ => true
+		};
+		if possible {
+		    g.exits.push(g.exit(t,p));
+		}
+	    }
+	}
+	g
+    }
+
+    fn exit(&self, taken: Option<usize>, promotion: Option<(usize,Kind)>) -> Exit<'a> {
+	let pieces: Vec<(usize,Piece)> = self.pieces.iter().enumerate().filter(|(j,_)| Some(*j) != taken).map(|(j,&p)| {
+	    match promotion {
+		Some((k,kind)) if k == j => (j,Piece{player: p.player, kind}),
+		_ => (j,p)
+	    }
+	}).collect();
+	let kinds = |player| pieces.iter().filter(|(_,p)| p.player == player).map(|(_,p)| p.kind).collect();
+	let material = Signature::new(kinds(Player::White),kinds(Player::Black));
+	let mirror = !material.is_canonical();
+	let mut exit = Exit{taken, promotion, table: None, mirror, slots: Vec::new()};
+	if !material.is_dead() {
+	    let table = self.base.get(&material).expect("missing table");
+	    let mut used = vec![false;pieces.len()];
+	    for piece in &table.pieces {
+		let i = (0..pieces.len()).find(|&i| {
+		    !used[i] && if mirror { pieces[i].1.flip() == *piece } else { pieces[i].1 == *piece }
+		}).unwrap();
+		used[i] = true;
+		exit.slots.push(pieces[i].0);
+	    }
+	    exit.table = Some(table);
+	}
+	exit
+    }
+
+    fn run(mut self) -> Table {
+	let n = self.pieces.len();
+	// Find the illegal positions first, since every other position
+	// relies on them to find the positions before it
+	for i in 0..self.values.len() {
+	    let (turn,squares) = position(i,n);
+	    if !self.is_legal(&squares[..n],turn) {
+		self.values[i] = ILLEGAL;
+	    }
+	}
+	for i in 0..self.values.len() {
+	    if self.values[i] == ILLEGAL {
+		continue;
+	    }
+	    let s = self.summarise(i);
+	    self.remaining[i] = s.inside;
+	    if !s.any {
+		let (turn,squares) = position(i,n);
+		if self.in_check(&squares[..n],turn) {
+		    self.push(0,i);
+		}
+	    } else if let Some(w) = s.win {
+		self.push(w,i);
+	    } else if s.inside == 0 && !s.draw {
+		self.push(s.loss,i);
+	    }
+	}
+	let mut ply = 0;
+	while ply < self.buckets.len() {
+	    let bucket = std::mem::take(&mut self.buckets[ply]);
+	    let value = if ply % 2 == 1 { Value::Win(ply) } else { Value::Loss(ply) };
+	    for i in bucket {
+		self.resolve(i as usize,ply,value);
+	    }
+	    ply += 1;
+	}
+	Table{signature: self.signature, pieces: self.pieces, values: self.values}
+    }
+
+    fn push(&mut self, ply: usize, index: usize) {
+	assert!(ply < (ILLEGAL - LOSS) as usize, "distance to mate too long");
+	if self.buckets.len() <= ply {
+	    self.buckets.resize(ply + 1,Vec::new());
+	}
+	self.buckets[ply].push(index as u32);
+    }
+
+    /**
+     * Settle the value of a position (unless it is already settled)
+     * and pass it on to the positions before it.
+     */
+    fn resolve(&mut self, i: usize, ply: usize, value: Value) {
+	match self.values[i] {
+	    DRAW => self.values[i] = value.to_byte(),
+	    v if v != value.to_byte() || self.remaining[i] == DONE => return,
+	    _ => {}
+	}
+	self.remaining[i] = DONE;
+	for p in self.predecessors(i) {
+	    if self.values[p] != DRAW {
+		continue;
+	    }
+	    if let Value::Loss(_) = value {
+		self.values[p] = Value::Win(ply + 1).to_byte();
+		self.push(ply + 1,p);
+	    } else {
+		self.remaining[p] -= 1;
+		if self.remaining[p] == 0 {
+		    let s = self.summarise(p);
+		    if s.win.is_none() && !s.draw {
+			let loss = s.loss.max(ply + 1);
+			self.values[p] = Value::Loss(loss).to_byte();
+			self.push(loss,p);
+		    }
+		}
+	    }
+	}
+    }
+
+    fn is_legal(&self, squares: &[u8], turn: Player) -> bool {
+	for (k,&s) in squares.iter().enumerate() {
+	    if squares[..k].contains(&s) {
+		return false;
+	    }
+	    if self.pieces[k].kind == Kind::Pawn && !(8..56).contains(&s) {
+		return false;
+	    }
+	}
+	!self.in_check(squares,turn.flip())
+    }
+
+    fn in_check(&self, squares: &[u8], player: Player) -> bool {
+	attacked(&self.pieces,squares,squares[self.king(player)],player.flip())
+    }
+
+    /**
+     * Get which piece is the king of a given player.
+     */
+    fn king(&self, player: Player) -> usize {
+	if player == Player::White { 0 } else { self.signature.white.len() }
+    }
+
+    /**
+     * Examine every legal move from a position, looking up those which
+     * leave the table.
+     */
+    fn summarise(&self, i: usize) -> Summary {
+	let n = self.pieces.len();
+	let (turn,squares) = position(i,n);
+	let squares = &squares[..n];
+	let mut s = Summary{inside: 0, any: false, win: None, draw: false, loss: 0};
+	let king = self.king(turn);
+	moves(&self.pieces,squares,turn,|k,to,taken,promotion| {
+	    let mut after = [NONE;MAX_PIECES];
+	    after[..n].copy_from_slice(squares);
+	    after[k] = to;
+	    if let Some(t) = taken {
+		after[t] = NONE;
+	    }
+	    let after = &after[..n];
+	    if attacked(&self.pieces,after,after[king],turn.flip()) {
+		return;
+	    }
+	    s.any = true;
+	    if taken.is_none() && promotion.is_none() {
+		s.inside += 1;
+		return;
+	    }
+	    let exit = self.exits.iter().find(|e| e.taken == taken && e.promotion == promotion.map(|p| (k,p))).unwrap();
+	    let value = match exit.table {
+		None => Value::Draw,
+		Some(table) => {
+		    let mut reached = [NONE;MAX_PIECES];
+		    for (r,&j) in reached.iter_mut().zip(&exit.slots) {
+			*r = if exit.mirror { after[j] ^ 56 } else { after[j] };
+		    }
+		    let next = if exit.mirror { turn } else { turn.flip() };
+		    table.get(index(&reached[..exit.slots.len()],next)).expect("illegal position")
+		}
+	    };
+	    match value.parent() {
+		Value::Win(n) => s.win = Some(s.win.map_or(n,|w| w.min(n))),
+		Value::Draw => s.draw = true,
+		Value::Loss(n) => s.loss = s.loss.max(n)
+	    }
+	});
+	s
+    }
+
+    /**
+     * Find the positions (within the table) from which a move reaches a
+     * given position, which are those where the other player moved one
+     * of their pieces without taking or promoting.
+     */
+    fn predecessors(&self, i: usize) -> Vec<usize> {
+	let n = self.pieces.len();
+	let (turn,squares) = position(i,n);
+	let squares = &squares[..n];
+	let mover = turn.flip();
+	let mut r = Vec::new();
+	for k in 0..n {
+	    let piece = self.pieces[k];
+	    if piece.player != mover {
+		continue;
+	    }
+	    let mut from = Vec::new();
+	    let s = squares[k];
+	    if piece.kind == Kind::Pawn {
+		let back: i8 = if mover == Player::White { -1 } else { 1 };
+		if let Some(f) = step(s,0,back).filter(|&f| !squares.contains(&f)) {
+		    from.push(f);
+		    let start = if mover == Player::White { 3 } else { 4 };
+		    if s / 8 == start {
+			from.extend(step(f,0,back).filter(|f| !squares.contains(f)));
+		    }
+		}
+	    } else {
+		let (dirs,slides) = directions(piece.kind);
+		for &(dc,dr) in dirs {
+		    let mut t = s;
+		    while let Some(f) = step(t,dc,dr).filter(|f| !squares.contains(f)) {
+			from.push(f);
+			if !slides { break; }
+			t = f;
+		    }
+		}
+	    }
+	    for f in from {
+		let mut before = [NONE;MAX_PIECES];
+		before[..n].copy_from_slice(squares);
+		before[k] = f;
+		let p = index(&before[..n],mover);
+		if self.values[p] != ILLEGAL {
+		    r.push(p);
+		}
+	    }
+	}
+	r
+    }
+}
+
+/**
+ * Get the square reached from a given square by moving some columns and
+ * rows (if it is on the board).
+ */
+fn step(s: u8, dc: i8, dr: i8) -> Option<u8> {
+    let (col,row) = ((s % 8) as i8 + dc,(s / 8) as i8 + dr);
+    if (0..8).contains(&col) && (0..8).contains(&row) {
+	Some((row * 8 + col) as u8)
+    } else {
+	None
+    }
+}
+
+/**
+ * Get the directions a (non-pawn) piece moves in, and whether it can
+ * slide along them.
+ */
+fn directions(kind: Kind) -> (&'static [(i8,i8)],bool) {
+    match kind {
+	Kind::Knight => (&KNIGHT,false),
+	Kind::Bishop => (&DIAGONALS,true),
+	Kind::Rook => (&STRAIGHTS,true),
+	Kind::Queen => (&DIRECTIONS,true),
+	_ => (&DIRECTIONS,false)
+    }
+}
+
+/**
+ * Find the piece (if any) on a given square.
+ */
+fn occupant(squares: &[u8], s: u8) -> Option<usize> {
+    squares.iter().position(|&t| t == s)
+}
+
+/**
+ * Check whether a given square is attacked by any piece of a given
+ * player.
+ */
+fn attacked(pieces: &[Piece], squares: &[u8], target: u8, by: Player) -> bool {
+    for (k,&s) in squares.iter().enumerate() {
+	let p = pieces[k];
+	if s == NONE || p.player != by {
+	    continue;
+	}
+	let (dc,dr) = ((target % 8) as i8 - (s % 8) as i8,(target / 8) as i8 - (s / 8) as i8);
+	let attacks = match p.kind {
+	    Kind::Pawn => dc.abs() == 1 && dr == if by == Player::White { 1 } else { -1 },
+	    Kind::Knight => dc.abs() * dr.abs() == 2,
+	    Kind::King => dc.abs().max(dr.abs()) == 1,
+	    kind => {
+		let straight = (dc == 0) != (dr == 0);
+		let diagonal = dc != 0 && dc.abs() == dr.abs();
+		let aligned = match kind {
+		    Kind::Rook => straight,
+		    Kind::Bishop => diagonal,
+		    _ => straight || diagonal
+		};
+		aligned && {
+		    // Check nothing is in the way
+		    let (sc,sr) = (dc.signum(),dr.signum());
+		    let mut t = step(s,sc,sr).unwrap();
+		    while t != target && occupant(squares,t).is_none() {
+			t = step(t,sc,sr).unwrap();
+		    }
+		    t == target
+		}
+	    }
+	};
+	if attacks {
+	    return true;
+	}
+    }
+    false
+}
+
+/**
+ * Generate the moves of a given player which obey how pieces move,
+ * but may leave their king in check.  Each is given to a function as
+ * the piece moved, its destination, the piece taken (if any) and the
+ * promotion (if any).
+ */
+fn moves(pieces: &[Piece], squares: &[u8], turn: Player, mut f: impl FnMut(usize,u8,Option<usize>,Option<Kind>)) {
+    for (k,&s) in squares.iter().enumerate() {
+	let p = pieces[k];
+	if s == NONE || p.player != turn {
+	    continue;
+	}
+	if p.kind == Kind::Pawn {
+	    let (dir,start,last) = if turn == Player::White { (1,1,7) } else { (-1,6,0) };
+	    let mut add = |to: u8, taken: Option<usize>| {
+		if to / 8 == last {
+		    for q in PROMOTIONS {
+			f(k,to,taken,Some(q));
+		    }
+		} else {
+		    f(k,to,taken,None);
+		}
+	    };
+	    if let Some(t) = step(s,0,dir).filter(|&t| occupant(squares,t).is_none()) {
+		add(t,None);
+		if s / 8 == start {
+		    if let Some(t) = step(t,0,dir).filter(|&t| occupant(squares,t).is_none()) {
+			add(t,None);
+		    }
+		}
+	    }
+	    for dc in [-1,1] {
+		if let Some(t) = step(s,dc,dir) {
+		    if let Some(o) = occupant(squares,t).filter(|&o| pieces[o].player != turn) {
+			add(t,Some(o));
+		    }
+		}
+	    }
+	    continue;
+	}
+	let (dirs,slides) = directions(p.kind);
+	for &(dc,dr) in dirs {
+	    let mut t = s;
+	    while let Some(to) = step(t,dc,dr) {
+		match occupant(squares,to) {
+		    None => f(k,to,None,None),
+		    Some(o) => {
+			if pieces[o].player != turn {
+			    f(k,to,Some(o),None);
+			}
+			break;
+		    }
+		}
+		if !slides { break; }
+		t = to;
+	    }
+	}
+    }
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use super::fen;
+#[cfg(test)]
+use super::san;
+
+/**
+ * Tables for king and pawn against king, which are shared between
+ * tests since generating them takes a while.
+ */
+#[cfg(test)]
+fn tablebase() -> &'static Tablebase {
+    static TABLES: std::sync::OnceLock<Tablebase> = std::sync::OnceLock::new();
+    TABLES.get_or_init(|| {
+	let mut tb = Tablebase::new();
+	let made = tb.generate(&Signature::from_str("KKP").unwrap()).unwrap();
+	let names: Vec<String> = made.iter().map(|m| m.to_string()).collect();
+	assert_eq!(names, vec!["KQK","KRK","KPK"]);
+	tb
+    })
+}
+
+#[cfg(test)]
+fn probe(tb: &Tablebase, fen: &str) -> Option<Value> {
+    tb.probe(&fen::from_str(fen).unwrap())
+}
+
+#[test]
+fn test_01() {
+    assert_eq!(Signature::from_str("KQK").unwrap().to_string(), "KQK");
+    assert_eq!(Signature::from_str("KpKr").unwrap().to_string(), "KPKR");
+    assert_eq!(Signature::from_str("KNBK").unwrap().to_string(), "KBNK");
+    assert!(!Signature::from_str("KKR").unwrap().is_canonical());
+    assert!(Signature::from_str("KRKN").unwrap().is_canonical());
+    assert_eq!(Signature::from_str("KQ"), Err(Error::Material("KQ".to_string())));
+    assert_eq!(Signature::from_str("QKK"), Err(Error::Material("QKK".to_string())));
+    assert_eq!(Signature::from_str("KX"), Err(Error::Material("KX".to_string())));
+    assert_eq!(Signature::from_str("KQRKR"), Err(Error::Pieces(5)));
+    assert!(Signature::from_str("KKN").unwrap().is_dead() && !Signature::from_str("KNKN").unwrap().is_dead());
+    assert!(Value::Win(1) > Value::Win(3) && Value::Win(99) > Value::Draw && Value::Draw > Value::Loss(40));
+    assert!(Value::Loss(40) > Value::Loss(0));
+    assert_eq!(Value::Win(19).to_string(), "win, mate in 10");
+    assert_eq!(Value::Loss(18).to_string(), "loss, mated in 9");
+}
+
+#[test]
+fn test_02() {
+    let tb = tablebase();
+    // The longest mates are known to be 10 and 16 moves, after black
+    // has moved
+    assert_eq!(tb.get(&Signature::from_str("KQK").unwrap()).unwrap().longest(), 20);
+    assert_eq!(tb.get(&Signature::from_str("KKR").unwrap()).unwrap().longest(), 32);
+    assert_eq!(probe(tb,"k7/8/1K6/8/8/8/8/6Q1 w - - 0 1"), Some(Value::Win(1)));
+    assert_eq!(probe(tb,"k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"), Some(Value::Loss(0)));
+    // Stalemate, and black taking the queen
+    assert_eq!(probe(tb,"k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(Value::Draw));
+    assert_eq!(probe(tb,"8/8/8/8/8/8/1Q6/k5K1 b - - 0 1"), Some(Value::Draw));
+    // Black having the queen uses the same table
+    assert_eq!(probe(tb,"K7/8/1k6/8/8/8/8/6q1 b - - 0 1"), Some(Value::Win(1)));
+    // No table for the material, or castling is possible
+    assert_eq!(probe(tb,"k7/8/1K6/8/8/8/8/6BB w - - 0 1"), None);
+    assert_eq!(probe(tb,"k7/8/1K6/8/8/8/8/6B1 w - - 0 1"), Some(Value::Draw));
+    assert_eq!(probe(tb,"4k3/8/8/8/8/8/8/4K2R w K - 0 1"), None);
+}
+
+#[test]
+fn test_03() {
+    let tb = tablebase();
+    // Opposition decides king and pawn endings
+    assert!(matches!(probe(tb,"8/4k3/8/4K3/4P3/8/8/8 b - - 0 1"), Some(Value::Loss(_))));
+    assert_eq!(probe(tb,"8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), Some(Value::Draw));
+    assert_eq!(probe(tb,"8/8/8/4p3/4k3/8/4K3/8 b - - 0 1"), Some(Value::Draw));
+    assert_eq!(probe(tb,"8/8/8/4p3/4k3/8/4K3/8 w - - 0 1"), probe(tb,"8/4k3/8/4K3/4P3/8/8/8 b - - 0 1"));
+    // With the king on the sixth rank, the side to move does not matter
+    assert_eq!(probe(tb,"4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Value::Win(21)));
+    assert_eq!(probe(tb,"4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Value::Loss(24)));
+    // The longest mate is known to be 28 moves
+    assert_eq!(tb.get(&Signature::from_str("KPK").unwrap()).unwrap().longest(), 56);
+    let b = fen::from_str("8/8/8/8/8/2k5/4P3/4K3 w - - 0 1").unwrap();
+    let moves = tb.moves(&b).unwrap();
+    assert!(matches!(moves[0].1, Value::Win(_)));
+    assert_eq!(tb.probe(&b), Some(moves[0].1));
+    let (e4,v) = moves.iter().find(|(m,_)| san::to_string(&b,m.as_ref()) == "e4").unwrap();
+    assert_eq!(tb.probe(&e4.apply(b).unwrap()).map(Value::parent), Some(*v));
+    // Generating again makes nothing new, and tables round trip
+    assert_eq!(tb.clone().generate(&Signature::from_str("KPK").unwrap()), Ok(vec![]));
+    let kpk = tb.get(&Signature::from_str("KPK").unwrap()).unwrap();
+    let bytes = kpk.to_bytes();
+    assert_eq!(Table::from_bytes(&bytes).as_ref(), Ok(kpk));
+    assert_eq!(Table::from_bytes(&bytes[..100]), Err(Error::Length(100 - 9)));
+    assert_eq!(Table::from_bytes(b"CCTB"), Err(Error::Format));
+}
+
+
@@ -0,0 +1,42 @@
+use super::board::*;
+use super::movegen;
+
+/**
+ * A simple (xorshift) pseudo-random number generator for use in
+ * testing, so that tests using random games are repeatable.
+ */
+pub struct Random(pub u64);
+
+impl Random {
+    /**
+     * Generate the next random number between 0 and n (exclusive).
+     */
+    pub fn next(&mut self, n: usize) -> usize {
+	self.0 ^= self.0 << 13;
+	self.0 ^= self.0 >> 7;
+	self.0 ^= self.0 << 17;
+	(self.0 % n as u64) as usize
+    }
+}
+
+/**
+ * Play a number of random legal games (of up to a given number of
+ * plies each) from the initial board, returning every board reached
+ * along the way (including the initial board).
+ */
+pub fn random_boards(seed: u64, games: usize, plies: usize) -> Vec<Board> {
+    let mut rng = Random(seed);
+    let mut boards = vec![INITIAL];
+    for _ in 0..games {
+	let mut board = INITIAL;
+	for _ in 0..plies {
+	    let moves = movegen::legal_moves(&board);
+	    if moves.is_empty() {
+		break;
+	    }
+	    board = moves[rng.next(moves.len())].apply(board).unwrap();
+	    boards.push(board);
+	}
+    }
+    boards
+}
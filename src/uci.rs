@@ -0,0 +1,784 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use super::board::{Board, INITIAL};
+use super::fen;
+use super::game::Game;
+use super::movegen;
+use super::muve::Move;
+use super::piece::Player;
+use super::search::{Analysis, Limits, Score, Search};
+
+/**
+ * Name the engine gives itself.
+ */
+const NAME: &str = "ChessCheck";
+
+/**
+ * Time held back (in milliseconds) when playing on a clock, to allow
+ * for communication with the GUI.
+ */
+const OVERHEAD: u64 = 50;
+
+/**
+ * Identifies why a move in long algebraic notation (as used by UCI)
+ * could not be read.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /** The move is not of the form "e2e4" or "e7e8q" */
+    Syntax,
+    /** The move is well formed, but not legal on the board */
+    Illegal
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    Error::Syntax => write!(f,"malformed move"),
+	    Error::Illegal => write!(f,"illegal move")
+	}
+    }
+}
+
+/**
+ * Write a move in the long algebraic notation used by UCI, which gives
+ * the starting and ending squares followed by any promotion (e.g.
+ * "e2e4" or "e7e8q").  Castling is written as the king's move (e.g.
+ * "e1g1").
+ */
+pub fn to_string(m: &dyn Move) -> String {
+    let promotion = m.promotion().map(|p| p.kind.to_black_string()).unwrap_or("");
+    format!("{}{}{}",m.from(),m.to(),promotion)
+}
+
+/**
+ * Read a move in the long algebraic notation used by UCI, which must be
+ * legal on a given board.
+ */
+pub fn from_str(board: &Board, s: &str) -> Result<Box<dyn Move>,Error> {
+    let b = s.as_bytes();
+    let square = |i: usize| (b'a'..=b'h').contains(&b[i]) && (b'1'..=b'8').contains(&b[i + 1]);
+    let promotion = match b.len() {
+	4 => true,
+	5 => b"nbrq".contains(&b[4]),
+	_ => false
+    };
+    if !promotion || !square(0) || !square(2) {
+	return Err(Error::Syntax);
+    }
+    movegen::legal_moves(board).into_iter().find(|m| to_string(m.as_ref()) == s).ok_or(Error::Illegal)
+}
+
+// ======================================================
+// Server
+// ======================================================
+
+/**
+ * A search running in the background, which produces the search
+ * again when it finishes.
+ */
+struct Running {
+    handle: JoinHandle<Search>,
+    /** Whether the search only finishes when stopped */
+    infinite: bool
+}
+
+/**
+ * An engine which speaks the Universal Chess Interface, reading
+ * commands from a GUI and writing its replies.  Searches run in the
+ * background, so commands (e.g. "stop") are still read while
+ * searching.
+ */
+struct Server<W> {
+    output: Arc<Mutex<W>>,
+    board: Board,
+    /** The search, unless it is running */
+    search: Option<Search>,
+    running: Option<Running>,
+    stop: Arc<AtomicBool>
+}
+
+/**
+ * Act as a UCI engine, reading commands from a given input and writing
+ * replies to a given output until told to quit (or the input ends).
+ * Supported commands are "uci", "isready", "ucinewgame", "position",
+ * "go", "stop" and "quit".  Other commands are answered with an "info
+ * string" explaining they are not understood.  Every "go" ends with a
+ * "bestmove", even when its arguments cannot be read.  When the input
+ * ends, any search still running finishes first (unless it is
+ * infinite).
+ */
+pub fn serve<R: BufRead, W: Write + Send + 'static>(input: R, output: W, search: Search) -> io::Result<()> {
+    let stop = search.stop_flag();
+    let mut server = Server{output: Arc::new(Mutex::new(output)), board: INITIAL, search: Some(search), running: None, stop};
+    for line in input.lines() {
+	let line = line?;
+	let words: Vec<&str> = line.split_whitespace().collect();
+	match words.as_slice() {
+	    [] => {}
+	    ["uci"] => {
+		server.send(&format!("id name {}",NAME))?;
+		server.send("id author David J. Pearce")?;
+		server.send("uciok")?;
+	    }
+	    ["isready"] => server.send("readyok")?,
+	    ["ucinewgame"] => {
+		server.wait(true);
+		server.search.as_mut().unwrap().clear();
+		server.board = INITIAL;
+	    }
+	    ["position",args @ ..] => {
+		server.wait(true);
+		match position(args) {
+		    Ok(b) => server.board = b,
+		    Err(e) => server.send(&format!("info string {}",e))?
+		}
+	    }
+	    ["go",args @ ..] => {
+		server.wait(true);
+		match limits(args,&server.board) {
+		    Ok(go) => server.go(go),
+		    Err(e) => {
+			// A move must still be given, so make a quick one
+			server.send(&format!("info string {}",e))?;
+			server.go(Go{limits: Limits{depth: Some(1), ..Limits::default()}, infinite: false, moves: Vec::new()});
+		    }
+		}
+	    }
+	    ["stop"] => server.wait(true),
+	    ["quit"] => {
+		server.wait(true);
+		return Ok(());
+	    }
+	    // Options are not supported, but should not be complained about
+	    ["setoption",..] | ["debug",..] | ["register",..] | ["ponderhit"] => {}
+	    [cmd,..] => server.send(&format!("info string unknown command {}",cmd))?
+	}
+    }
+    server.wait(false);
+    Ok(())
+}
+
+impl<W: Write + Send + 'static> Server<W> {
+    fn send(&self, line: &str) -> io::Result<()> {
+	send(&self.output,line)
+    }
+
+    /**
+     * Wait for the running search (if any) to finish, either stopping it
+     * now or letting it finish by itself.  A search which only finishes
+     * when stopped is always stopped.
+     */
+    fn wait(&mut self, stop: bool) {
+	if let Some(r) = self.running.take() {
+	    if stop || r.infinite {
+		self.stop.store(true,Ordering::Relaxed);
+	    }
+	    self.search = Some(r.handle.join().expect("search failed"));
+	    self.stop.store(false,Ordering::Relaxed);
+	}
+    }
+
+    /**
+     * Start searching the current board in the background, reporting
+     * each depth completed and then the best move.
+     */
+    fn go(&mut self, go: Go) {
+	let Go{limits,infinite,moves} = go;
+	let mut search = self.search.take().unwrap();
+	search.restrict(&moves);
+	let (board,output,stop) = (self.board,self.output.clone(),self.stop.clone());
+	let handle = thread::spawn(move || {
+	    let result = search.run(&board,limits,|a| {
+		let _ = send(&output,&info(a));
+	    });
+	    // An infinite search reports its move only once stopped
+	    while infinite && !stop.load(Ordering::Relaxed) {
+		thread::sleep(Duration::from_millis(5));
+	    }
+	    // A search stopped straight away may not have found a move
+	    let best = result.pv.first().map(|m| to_string(m.as_ref()))
+		.or_else(|| movegen::legal_moves(&board).first().map(|m| to_string(m.as_ref())))
+		.unwrap_or_else(|| "0000".to_string());
+	    let _ = send(&output,&format!("bestmove {}",best));
+	    search
+	});
+	self.running = Some(Running{handle, infinite});
+    }
+}
+
+/**
+ * Write a line to the GUI, which is flushed straight away.
+ */
+fn send<W: Write>(output: &Mutex<W>, line: &str) -> io::Result<()> {
+    let mut out = output.lock().unwrap();
+    writeln!(out,"{}",line)?;
+    out.flush()
+}
+
+/**
+ * Describe the result of searching to some depth as an "info" line.
+ */
+fn info(a: &Analysis) -> String {
+    let score = match a.score.mate() {
+	Some(n) => format!("mate {}",n),
+	None => format!("cp {}",a.score.0)
+    };
+    let ms = a.time.as_millis();
+    let nps = a.nodes as u128 * 1000 / ms.max(1);
+    let mut line = format!("info depth {} score {} nodes {} nps {} time {} pv",a.depth,score,a.nodes,nps,ms);
+    for m in &a.pv {
+	line.push(' ');
+	line.push_str(&to_string(m.as_ref()));
+    }
+    line
+}
+
+/**
+ * Read the arguments of a "position" command, which gives either
+ * "startpos" or "fen" followed by a FEN string, and then (optionally)
+ * "moves" followed by moves to play.
+ */
+fn position(args: &[&str]) -> Result<Board,String> {
+    let moves = args.iter().position(|&w| w == "moves").unwrap_or(args.len());
+    let mut board = match &args[..moves] {
+	["startpos"] => INITIAL,
+	["fen",fields @ ..] => fen::from_str(&fields.join(" ")).map_err(|e| format!("invalid FEN ({})",e))?,
+	_ => return Err("expected \"startpos\" or \"fen\"".to_string())
+    };
+    for s in args.iter().skip(moves + 1) {
+	let m = from_str(&board,s).map_err(|e| format!("invalid move {} ({})",s,e))?;
+	board = m.apply(board).unwrap();
+    }
+    Ok(board)
+}
+
+/**
+ * The arguments of a "go" command.
+ */
+struct Go {
+    limits: Limits,
+    /** Whether the search should only finish when stopped */
+    infinite: bool,
+    /** Moves the search is restricted to (or empty for every move) */
+    moves: Vec<Box<dyn Move>>
+}
+
+/**
+ * Read the arguments of a "go" command for a given board.  When
+ * playing on a clock, a share of the time left is used for each move.
+ * No limits at all means searching until stopped.
+ */
+fn limits(args: &[&str], board: &Board) -> Result<Go,String> {
+    let mut limits = Limits::default();
+    let mut infinite = false;
+    let (mut clock, mut increment, mut moves) = (None,0,30);
+    let mut searchmoves = Vec::new();
+    let turn = board.turn();
+    let mut i = 0;
+    while i < args.len() {
+	if args[i] == "searchmoves" {
+	    // Moves continue up to the next argument
+	    i += 1;
+	    while let Some(s) = args.get(i).filter(|s| from_str(board,s).err() != Some(Error::Syntax)) {
+		searchmoves.push(from_str(board,s).map_err(|e| format!("invalid move {} ({})",s,e))?);
+		i += 1;
+	    }
+	    continue;
+	}
+	let value = |i: usize| -> Result<i64,String> {
+	    let s = args.get(i + 1).ok_or(format!("missing value for {}",args[i]))?;
+	    s.parse().map_err(|_| format!("invalid value for {}: {}",args[i],s))
+	};
+	let ms = |v: i64| Duration::from_millis(v.max(0) as u64);
+	match (args[i],turn) {
+	    ("infinite",_) => infinite = true,
+	    ("ponder",_) => {}
+	    ("depth",_) => limits.depth = Some(value(i)?.max(1) as usize),
+	    ("nodes",_) => limits.nodes = Some(value(i)?.max(1) as u64),
+	    ("movetime",_) => limits.time = Some(ms(value(i)?)),
+	    ("movestogo",_) => moves = value(i)?.max(1) as u64,
+	    ("wtime",Player::White) | ("btime",Player::Black) => clock = Some(value(i)?.max(0) as u64),
+	    ("winc",Player::White) | ("binc",Player::Black) => increment = value(i)?.max(0) as u64,
+	    ("wtime",_) | ("btime",_) | ("winc",_) | ("binc",_) | ("mate",_) => { value(i)?; }
+	    (arg,_) => return Err(format!("unknown argument {}",arg))
+	}
+	// Flags have no value
+	i += if matches!(args[i],"infinite" | "ponder") { 1 } else { 2 };
+    }
+    if let (Some(c),None) = (clock,limits.time) {
+	let share = (c / moves + increment * 3 / 4).min(c.saturating_sub(OVERHEAD));
+	limits.time = Some(Duration::from_millis(share.max(1)));
+    }
+    if limits == Limits::default() {
+	infinite = true;
+    }
+    Ok(Go{limits, infinite, moves: searchmoves})
+}
+
+// ======================================================
+// Client
+// ======================================================
+
+/**
+ * Identifies why an external engine could not be used.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineError {
+    /** The engine could not be started or written to (i.e. why) */
+    Io(String),
+    /** The engine did not reply in time (i.e. the reply awaited) */
+    Timeout(&'static str),
+    /** The engine exited, or closed its output */
+    Exited,
+    /** The engine chose a move which is not legal (i.e. the move and the position in FEN) */
+    Move(String,String)
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	match self {
+	    EngineError::Io(e) => write!(f,"{}",e),
+	    EngineError::Timeout(r) => write!(f,"engine did not reply with \"{}\" in time",r),
+	    EngineError::Exited => write!(f,"engine exited unexpectedly"),
+	    EngineError::Move(m,b) => write!(f,"engine chose illegal move {} in {}",m,b)
+	}
+    }
+}
+
+/**
+ * An external engine which speaks the Universal Chess Interface,
+ * running as a subprocess.  Every reply is awaited for at most a
+ * given timeout, so an engine which hangs cannot hang its client.
+ * The engine is told to quit (and then killed) when this is dropped.
+ */
+pub struct Engine {
+    child: Child,
+    input: ChildStdin,
+    /** Lines written by the engine, as they are read */
+    lines: Receiver<String>,
+    name: Option<String>,
+    timeout: Duration
+}
+
+impl Engine {
+    /**
+     * Start an engine by running a given program with some arguments,
+     * and check it speaks UCI (i.e. replies to "uci" and "isready").
+     */
+    pub fn start(program: &str, args: &[String], timeout: Duration) -> Result<Engine,EngineError> {
+	let mut child = Command::new(program).args(args).stdin(Stdio::piped()).stdout(Stdio::piped())
+	    .stderr(Stdio::null()).spawn().map_err(|e| EngineError::Io(e.to_string()))?;
+	let input = child.stdin.take().unwrap();
+	let output = BufReader::new(child.stdout.take().unwrap());
+	let (sender,lines) = mpsc::channel();
+	// Reading happens in the background, so waiting can time out
+	thread::spawn(move || {
+	    for line in output.lines().map_while(Result::ok) {
+		if sender.send(line).is_err() { break; }
+	    }
+	});
+	let mut engine = Engine{child, input, lines, name: None, timeout};
+	engine.send("uci")?;
+	let deadline = Instant::now() + timeout;
+	loop {
+	    let line = engine.receive(deadline,"uciok")?;
+	    if let Some(name) = line.strip_prefix("id name ") {
+		engine.name = Some(name.trim().to_string());
+	    } else if line.trim() == "uciok" {
+		break;
+	    }
+	}
+	engine.ready()?;
+	Ok(engine)
+    }
+
+    /**
+     * Get the name the engine gave itself (if any).
+     */
+    pub fn name(&self) -> Option<&str> {
+	self.name.as_deref()
+    }
+
+    /**
+     * Have the engine analyse a given board within some limits.
+     * Without any limits, the engine searches until the timeout and is
+     * then stopped.
+     */
+    pub fn analyse(&mut self, board: &Board, limits: Limits) -> Result<Analysis,EngineError> {
+	self.search(&format!("position fen {}",fen::to_string(board)),board,limits)
+    }
+
+    /**
+     * Have the engine analyse every position of a game played from a
+     * given starting board, within some limits.  The first analysis is
+     * of the starting board, and each other is of the board after the
+     * corresponding move.  Positions are given as the moves played from
+     * the start, so the engine can spot repetitions.  Analysis stops at
+     * the first move which cannot be applied.
+     */
+    pub fn evaluate(&mut self, start: Board, game: &Game, limits: Limits) -> Result<Vec<Analysis>,EngineError> {
+	self.send("ucinewgame")?;
+	let mut position = format!("position fen {} moves",fen::to_string(&start));
+	let mut analyses = vec![self.search(&position,&start,limits)?];
+	for (_,m,_,after) in game.positions(start) {
+	    position.push(' ');
+	    position.push_str(&to_string(m));
+	    analyses.push(self.search(&position,&after,limits)?);
+	}
+	Ok(analyses)
+    }
+
+    /**
+     * Wait until the engine is ready for more commands.
+     */
+    fn ready(&mut self) -> Result<(),EngineError> {
+	self.send("isready")?;
+	let deadline = Instant::now() + self.timeout;
+	while self.receive(deadline,"readyok")?.trim() != "readyok" {}
+	Ok(())
+    }
+
+    /**
+     * Search a given position (as a "position" command) which reaches
+     * a given board, producing the deepest analysis reported along with
+     * the move chosen.  Positions where the game is over are not sent.
+     * If the engine takes too long, it is told to stop and given one
+     * more timeout to reply.  Anything still to come from an earlier
+     * search (e.g. one which timed out) is discarded first.
+     */
+    fn search(&mut self, position: &str, board: &Board, limits: Limits) -> Result<Analysis,EngineError> {
+	let mut result = Analysis{depth: 0, score: Score(0), nodes: 0, time: Duration::ZERO, pv: Vec::new()};
+	if movegen::legal_moves(board).is_empty() {
+	    if board.in_check(board.turn()) {
+		result.score = Score::from_mate(0);
+	    }
+	    return Ok(result);
+	}
+	self.ready()?;
+	self.send(position)?;
+	let mut go = "go".to_string();
+	if let Some(d) = limits.depth { go.push_str(&format!(" depth {}",d)); }
+	if let Some(n) = limits.nodes { go.push_str(&format!(" nodes {}",n)); }
+	if let Some(t) = limits.time { go.push_str(&format!(" movetime {}",t.as_millis())); }
+	self.send(&go)?;
+	let start = Instant::now();
+	let mut deadline = start + limits.time.unwrap_or(Duration::ZERO) + self.timeout;
+	let mut stopped = false;
+	let best = loop {
+	    let line = match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+		Ok(line) => line,
+		Err(RecvTimeoutError::Disconnected) => return Err(EngineError::Exited),
+		Err(RecvTimeoutError::Timeout) if stopped => return Err(EngineError::Timeout("bestmove")),
+		Err(RecvTimeoutError::Timeout) => {
+		    self.send("stop")?;
+		    deadline = Instant::now() + self.timeout;
+		    stopped = true;
+		    continue;
+		}
+	    };
+	    let words: Vec<&str> = line.split_whitespace().collect();
+	    match words.as_slice() {
+		["info",rest @ ..] => {
+		    if let Some(a) = parse_info(board,rest) { result = a; }
+		}
+		["bestmove",m,..] => break m.to_string(),
+		_ => {}
+	    }
+	};
+	// A search stopped straight away may not have reported anything
+	if result.time.is_zero() {
+	    result.time = start.elapsed();
+	}
+	if best != "0000" && best != "(none)" {
+	    let m = from_str(board,&best).map_err(|_| EngineError::Move(best.clone(),fen::to_string(board)))?;
+	    if result.pv.first().is_none_or(|p| to_string(p.as_ref()) != best) {
+		result.pv = vec![m];
+	    }
+	}
+	Ok(result)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(),EngineError> {
+	writeln!(self.input,"{}",line).and_then(|_| self.input.flush()).map_err(|e| match e.kind() {
+	    io::ErrorKind::BrokenPipe => EngineError::Exited,
+	    _ => EngineError::Io(e.to_string())
+	})
+    }
+
+    /**
+     * Wait for the next line from the engine until a given deadline,
+     * whilst awaiting a given reply.
+     */
+    fn receive(&mut self, deadline: Instant, awaited: &'static str) -> Result<String,EngineError> {
+	self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())).map_err(|e| match e {
+	    RecvTimeoutError::Timeout => EngineError::Timeout(awaited),
+	    RecvTimeoutError::Disconnected => EngineError::Exited
+	})
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+	let _ = self.send("quit");
+	let deadline = Instant::now() + self.timeout.min(Duration::from_millis(100));
+	while Instant::now() < deadline {
+	    if let Ok(Some(_)) = self.child.try_wait() { return; }
+	    thread::sleep(Duration::from_millis(5));
+	}
+	let _ = self.child.kill();
+	let _ = self.child.wait();
+    }
+}
+
+/**
+ * Read the words of an "info" line which reports a score (other lines,
+ * or those giving only a bound, produce nothing).  Moves of the
+ * principal variation are read up to the first which is not legal.
+ */
+fn parse_info(board: &Board, words: &[&str]) -> Option<Analysis> {
+    let mut a = Analysis{depth: 0, score: Score(0), nodes: 0, time: Duration::ZERO, pv: Vec::new()};
+    let mut score = None;
+    let mut i = 0;
+    while i < words.len() {
+	let next = words.get(i + 1).copied().unwrap_or("");
+	match words[i] {
+	    "depth" => a.depth = next.parse().ok()?,
+	    "nodes" => a.nodes = next.parse().ok()?,
+	    "time" => a.time = Duration::from_millis(next.parse().ok()?),
+	    "score" => {
+		let n: i32 = words.get(i + 2)?.parse().ok()?;
+		score = Some(if next == "mate" { Score::from_mate(n) } else { Score(n) });
+		i += 1;
+	    }
+	    "lowerbound" | "upperbound" => return None,
+	    "multipv" if next != "1" => return None,
+	    "pv" => {
+		let mut b = *board;
+		for w in &words[i + 1..] {
+		    match from_str(&b,w) {
+			Ok(m) => {
+			    b = m.apply(b).unwrap();
+			    a.pv.push(m);
+			}
+			Err(_) => break
+		    }
+		}
+		break;
+	    }
+	    // The rest of the line is text
+	    "string" => return None,
+	    _ => { i += 1; continue; }
+	}
+	i += 2;
+    }
+    a.score = score?;
+    Some(a)
+}
+
+// ======================================================
+// Tests
+// ======================================================
+
+#[cfg(test)]
+use std::str::FromStr;
+#[cfg(test)]
+use super::san;
+
+/**
+ * Output which can be read back once the server has finished.
+ */
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+	self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+	Ok(())
+    }
+}
+
+#[cfg(test)]
+fn script(commands: &str) -> Vec<String> {
+    let out = Shared::default();
+    serve(commands.as_bytes(),out.clone(),Search::default()).unwrap();
+    let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+    text.lines().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn test_01() {
+    let b = fen::from_str("r3k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    for (uci,san) in [("e1g1","O-O"),("b7a8q","bxa8=Q+"),("b7b8n","b8=N"),("h1h8","Rh8+")] {
+	let m = from_str(&b,uci).unwrap();
+	assert_eq!(san::to_string(&b,m.as_ref()), san);
+	assert_eq!(to_string(m.as_ref()), uci);
+    }
+    assert_eq!(from_str(&b,"e1e3").err(), Some(Error::Illegal));
+    assert_eq!(from_str(&b,"b7b8").err(), Some(Error::Illegal));
+    assert_eq!(from_str(&b,"b7b8k").err(), Some(Error::Syntax));
+    assert_eq!(from_str(&b,"e1").err(), Some(Error::Syntax));
+    assert_eq!(from_str(&b,"i1e2").err(), Some(Error::Syntax));
+}
+
+#[test]
+fn test_02() {
+    let out = script("uci\nisready\nposition fen 7k/8/6K1/8/8/8/8/1Q6 w - - 0 1\ngo depth 3\n");
+    assert_eq!(&out[..4], &["id name ChessCheck","id author David J. Pearce","uciok","readyok"]);
+    assert!(out[4].starts_with("info depth 1 score mate 1 "), "{:?}", out);
+    assert!(out[4].ends_with(" pv b1b8"), "{:?}", out);
+    assert_eq!(out.last().unwrap(), "bestmove b1b8");
+    // Moves are played from the position given
+    let out = script("position startpos moves e2e4 e7e5 d1h5 b8c6 f1c4 g8f6\ngo depth 2\nquit\n");
+    assert_eq!(out.last().unwrap(), "bestmove h5f7");
+}
+
+#[test]
+fn test_03() {
+    // Stopping an infinite search still gives a move
+    let out = script("go infinite\nstop\nisready\n");
+    assert!(out.iter().any(|l| l == "readyok"));
+    let best = out.iter().find_map(|l| l.strip_prefix("bestmove ")).unwrap();
+    assert!(from_str(&INITIAL,best).is_ok());
+    // Nothing to move
+    let out = script("position fen k7/1Q6/1K6/8/8/8/8/8 b - - 0 1\ngo depth 2\n");
+    assert_eq!(out, vec!["bestmove 0000"]);
+    // Errors are reported without stopping
+    let out = script("position startpos moves e2e5\nposition fen 8/8 w\nfoo\nisready\n");
+    assert_eq!(out, vec!["info string invalid move e2e5 (illegal move)",
+			 "info string invalid FEN (expected four to six fields)",
+			 "info string unknown command foo",
+			 "readyok"]);
+}
+
+#[test]
+fn test_04() {
+    let black = fen::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+    let go = |args: &str, board| limits(&args.split_whitespace().collect::<Vec<_>>(),board);
+    let get = |args: &str, board| go(args,board).map(|g| (g.limits,g.infinite)).unwrap();
+    assert_eq!(get("",&INITIAL), (Limits::default(),true));
+    assert_eq!(get("depth 5",&INITIAL), (Limits{depth: Some(5), ..Limits::default()},false));
+    let ms = |n| Some(Duration::from_millis(n));
+    assert_eq!(get("movetime 250 nodes 10",&black).0, Limits{time: ms(250), nodes: Some(10), depth: None});
+    // A share of the clock, with some increment
+    assert_eq!(get("wtime 60000 btime 1000 winc 1000 binc 0",&INITIAL).0.time, ms(2750));
+    assert_eq!(get("wtime 60000 btime 1000 winc 1000 binc 0 movestogo 2",&black).0.time, ms(500));
+    assert_eq!(get("btime 10",&black).0.time, ms(1));
+    assert!(go("wtime",&INITIAL).is_err());
+    assert!(go("foo",&INITIAL).is_err());
+    // Moves to search continue up to the next argument
+    let g = go("searchmoves e2e4 d2d4 depth 2",&INITIAL).unwrap();
+    assert_eq!(g.moves.iter().map(|m| to_string(m.as_ref())).collect::<Vec<_>>(), vec!["e2e4","d2d4"]);
+    assert_eq!(g.limits.depth, Some(2));
+    assert!(go("searchmoves e2e5",&INITIAL).is_err());
+}
+
+#[test]
+fn test_05() {
+    // The search is restricted to the moves given
+    let out = script("position fen 7k/8/6K1/8/8/8/8/1Q6 w - - 0 1\ngo searchmoves b1h1 depth 3\ngo depth 1\n");
+    let best: Vec<&str> = out.iter().filter_map(|l| l.strip_prefix("bestmove ")).collect();
+    assert_eq!(best, vec!["b1h1","b1b8"]);
+    // A malformed "go" still gives a move
+    let out = script("go depth x\n");
+    assert_eq!(out[0], "info string invalid value for depth: x");
+    assert!(out.last().unwrap().starts_with("bestmove "));
+}
+
+/**
+ * A stand-in engine, which replies to a few known positions.  A search
+ * without limits only finishes when stopped.
+ */
+#[cfg(test)]
+const STUB: &str = r#"
+while read cmd args; do
+  case "$cmd" in
+    uci) echo "Stub engine"; echo "id name Stub 1.0"; echo "option name Hash type spin default 1 min 1 max 1"; echo uciok;;
+    isready) echo readyok;;
+    position) position="$args";;
+    stop) echo "bestmove e2e4";;
+    quit) exit 0;;
+    go)
+      [ -z "$args" ] && continue
+      case "$position" in
+        *"moves e2e4 e7e5 g1f3") echo "info depth 1 score mate -1 pv b8c6"; echo "bestmove b8c6";;
+        *"moves e2e4 e7e5") echo "info string thinking"; echo "info depth 1 score cp 30 nodes 10 time 2 pv g1f3 b8c6"; echo "bestmove g1f3";;
+        *"moves e2e4") echo "info depth 1 score cp -400 pv d7d5"; echo "info depth 2 score cp 500 upperbound"; echo "bestmove d7d5";;
+        *" w KQkq - 0 1"*) echo "info depth 1 score cp 25 pv e2e4 e7e5 e1e8"; echo "bestmove e2e4";;
+        *) echo "bestmove a1a1";;
+      esac;;
+  esac
+done
+"#;
+
+#[cfg(test)]
+fn stub(script: &str) -> Result<Engine,EngineError> {
+    Engine::start("sh",&["-c".to_string(),script.to_string()],Duration::from_millis(500))
+}
+
+#[test]
+fn test_06() {
+    let mut engine = stub(STUB).unwrap();
+    assert_eq!(engine.name(), Some("Stub 1.0"));
+    let depth = Limits{depth: Some(1), ..Limits::default()};
+    let a = engine.analyse(&INITIAL,depth).unwrap();
+    assert_eq!((a.depth,a.score), (1,Score(25)));
+    // Moves are read up to the first which is not legal
+    assert_eq!(a.pv.iter().map(|m| to_string(m.as_ref())).collect::<Vec<_>>(), vec!["e2e4","e7e5"]);
+    // Positions of a game
+    let game = Game::from_str("e2-e4 e7-e5\nNg1-f3").unwrap();
+    let analyses = engine.evaluate(INITIAL,&game,depth).unwrap();
+    let scores: Vec<Score> = analyses.iter().map(|a| a.score).collect();
+    assert_eq!(scores, vec![Score(25),Score(-400),Score(30),Score::from_mate(-1)]);
+    assert_eq!((analyses[2].nodes,analyses[2].time), (10,Duration::from_millis(2)));
+    // The best move leads the variation
+    assert_eq!(to_string(analyses[3].pv[0].as_ref()), "b8c6");
+    // Stopped after the timeout
+    let a = engine.analyse(&INITIAL,Limits::default()).unwrap();
+    assert_eq!(to_string(a.pv[0].as_ref()), "e2e4");
+    assert!(a.time >= Duration::from_millis(500));
+    // Illegal moves
+    let b = fen::from_str("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1").unwrap();
+    assert_eq!(engine.analyse(&b,depth).err(), Some(EngineError::Move("a1a1".to_string(),fen::to_string(&b))));
+    // Positions where the game is over are not sent
+    let mated = fen::from_str("1Q5k/8/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    assert_eq!(engine.analyse(&mated,depth).unwrap().score.mate(), Some(0));
+}
+
+#[test]
+fn test_07() {
+    let err = |r: Result<Engine,EngineError>| r.err().unwrap();
+    assert!(matches!(err(Engine::start("./no-such-engine",&[],Duration::from_millis(500))), EngineError::Io(_)));
+    assert_eq!(err(stub("read cmd; exit 0")), EngineError::Exited);
+    assert_eq!(err(stub("read cmd; echo uciok; sleep 5")), EngineError::Timeout("readyok"));
+    assert_eq!(err(stub("sleep 5")), EngineError::Timeout("uciok"));
+    // An engine which never finishes searching
+    let mut engine = stub("while read cmd; do case $cmd in uci) echo uciok;; isready) echo readyok;; esac; done").unwrap();
+    assert_eq!(engine.name(), None);
+    let depth = Limits{depth: Some(1), ..Limits::default()};
+    assert_eq!(engine.analyse(&INITIAL,depth).err(), Some(EngineError::Timeout("bestmove")));
+}
+
+#[test]
+fn test_08() {
+    // A reply arriving after the timeout is not taken for the next search
+    let script = "while read cmd args; do case $cmd in uci) echo uciok;; isready) echo readyok;; \
+		  go) if [ -z \"$late\" ]; then late=1; sleep 2.5; echo \"bestmove a2a3\"; else echo \"bestmove e2e4\"; fi;; \
+		  esac; done";
+    let mut engine = Engine::start("sh",&["-c".to_string(),script.to_string()],Duration::from_millis(1000)).unwrap();
+    let depth = Limits{depth: Some(1), ..Limits::default()};
+    assert_eq!(engine.analyse(&INITIAL,depth).err(), Some(EngineError::Timeout("bestmove")));
+    let a = engine.analyse(&INITIAL,depth).unwrap();
+    assert_eq!(to_string(a.pv[0].as_ref()), "e2e4");
+}